@@ -0,0 +1,332 @@
+//! Offline Environment Bundles
+//!
+//! Packs everything needed to recreate a workflow's environments on a
+//! machine without internet access into one tarball: the workflow's
+//! lockfiles and every package archive they list, taken from the local
+//! package cache.
+//!
+//! ```text
+//! pipeline-envs.tar.gz
+//! ├── pipeline.lock/          # one explicit lockfile per environment
+//! │   ├── samtools.txt
+//! │   └── rr-1f0c9a3e5b7d2468.txt
+//! └── pkgs/                   # package archives named in the lockfiles
+//!     ├── samtools-1.17-h00cdaf9_0.tar.bz2
+//!     └── ...
+//! ```
+//!
+//! On the air-gapped machine the archives are copied into the package
+//! cache and the environments are created offline from the lockfiles.
+//! Archiving uses the system `tar` command.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{debug, info};
+
+use super::lock::Lockfile;
+
+/// Directory of package archives within a bundle.
+pub const BUNDLE_PKGS_DIR: &str = "pkgs";
+
+/// Contents of an unpacked bundle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnpackedBundle {
+    /// Directory the bundle's lockfiles were written to
+    pub lock_dir: PathBuf,
+
+    /// Lockfiles of the bundled environments
+    pub lockfiles: Vec<Lockfile>,
+
+    /// Number of package archives added to the package cache
+    pub new_packages: usize,
+}
+
+/// Writes a bundle of the given environments to `output`.
+///
+/// `lock_dir` names the lockfile directory inside the bundle (only its
+/// file name is used). Every package of every lockfile must be present in
+/// `pkgs_dir`. Returns the number of package archives bundled.
+pub fn create_bundle(
+    lock_dir: &Path,
+    lockfiles: &[Lockfile],
+    pkgs_dir: &Path,
+    output: &Path,
+) -> Result<usize, Box<dyn Error>> {
+    let missing: Vec<String> = lockfiles
+        .iter()
+        .flat_map(|lock| {
+            lock.packages
+                .iter()
+                .filter(|package| !pkgs_dir.join(package.file_name()).is_file())
+                .map(move |package| format!("{} ({})", package.file_name(), lock.env_name))
+        })
+        .collect();
+
+    if !missing.is_empty() {
+        return Err(format!(
+            "{} packages are not in the package cache {}: {}",
+            missing.len(),
+            pkgs_dir.display(),
+            missing.join(", ")
+        )
+        .into());
+    }
+
+    let staging = staging_dir("bundle")?;
+    let result = stage_and_pack(&staging, lock_dir, lockfiles, pkgs_dir, output);
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+/// Copies the bundle contents into `staging` and archives them.
+fn stage_and_pack(
+    staging: &Path,
+    lock_dir: &Path,
+    lockfiles: &[Lockfile],
+    pkgs_dir: &Path,
+    output: &Path,
+) -> Result<usize, Box<dyn Error>> {
+    let lock_name = lock_dir.file_name().ok_or("Lockfile directory has no name")?;
+    let staged_locks = staging.join(lock_name);
+    let staged_pkgs = staging.join(BUNDLE_PKGS_DIR);
+    fs::create_dir_all(&staged_pkgs)?;
+
+    let mut packages = 0;
+    for lock in lockfiles {
+        lock.save(&staged_locks)?;
+
+        for package in &lock.packages {
+            let target = staged_pkgs.join(package.file_name());
+            if !target.exists() {
+                link_or_copy(&pkgs_dir.join(package.file_name()), &target)?;
+                packages += 1;
+            }
+        }
+    }
+
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Resolve before `tar -C` changes directory
+    let output = std::env::current_dir()?.join(output);
+    run_tar(
+        Command::new("tar")
+            .arg("-czf")
+            .arg(&output)
+            .arg("-C")
+            .arg(staging)
+            .arg(lock_name)
+            .arg(BUNDLE_PKGS_DIR),
+    )?;
+
+    info!("Bundled {} environments ({} packages) into {}", lockfiles.len(), packages, output.display());
+    Ok(packages)
+}
+
+/// Unpacks a bundle: package archives go into `pkgs_dir`, the lockfile
+/// directory into `dest_dir`.
+///
+/// Archives already in the package cache are left untouched. Creating the
+/// environments is left to the caller, see
+/// [`create_env_from_lock`](super::conda::create_env_from_lock).
+pub fn unpack_bundle(
+    bundle: &Path,
+    pkgs_dir: &Path,
+    dest_dir: &Path,
+) -> Result<UnpackedBundle, Box<dyn Error>> {
+    let staging = staging_dir("unbundle")?;
+    let result = unpack_into(&staging, bundle, pkgs_dir, dest_dir);
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+/// Extracts a bundle into `staging` and moves its contents into place.
+fn unpack_into(
+    staging: &Path,
+    bundle: &Path,
+    pkgs_dir: &Path,
+    dest_dir: &Path,
+) -> Result<UnpackedBundle, Box<dyn Error>> {
+    run_tar(Command::new("tar").arg("-xzf").arg(bundle).arg("-C").arg(staging))?;
+
+    let staged_locks = fs::read_dir(staging)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| path.is_dir() && path.extension().is_some_and(|ext| ext == "lock"))
+        .ok_or_else(|| format!("'{}' contains no lockfile directory", bundle.display()))?;
+
+    let mut new_packages = 0;
+    let staged_pkgs = staging.join(BUNDLE_PKGS_DIR);
+    if staged_pkgs.is_dir() {
+        fs::create_dir_all(pkgs_dir)?;
+        for entry in fs::read_dir(&staged_pkgs)? {
+            let source = entry?.path();
+            let Some(name) = source.file_name() else { continue };
+            let target = pkgs_dir.join(name);
+            if target.exists() {
+                debug!("Package {} already cached", target.display());
+                continue;
+            }
+            link_or_copy(&source, &target)?;
+            new_packages += 1;
+        }
+    }
+
+    let lock_dir = dest_dir.join(staged_locks.file_name().ok_or("Invalid lockfile directory")?);
+    let mut lockfiles = Vec::new();
+    for entry in fs::read_dir(&staged_locks)? {
+        let path = entry?.path();
+        if path.extension().map_or(true, |ext| ext != "txt") {
+            continue;
+        }
+        let env_name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let lock = Lockfile::parse(&env_name, &fs::read_to_string(&path)?)?;
+        lock.save(&lock_dir)?;
+        lockfiles.push(lock);
+    }
+    lockfiles.sort_by(|a, b| a.env_name.cmp(&b.env_name));
+
+    Ok(UnpackedBundle {
+        lock_dir,
+        lockfiles,
+        new_packages,
+    })
+}
+
+/// Creates a fresh scratch directory in the system temp directory.
+fn staging_dir(purpose: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let dir = std::env::temp_dir().join(format!(
+        "rustrunner-{}-{}-{}",
+        purpose,
+        std::process::id(),
+        nanos
+    ));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Hard-links a file, falling back to copying across file systems.
+fn link_or_copy(source: &Path, target: &Path) -> Result<(), Box<dyn Error>> {
+    if fs::hard_link(source, target).is_err() {
+        fs::copy(source, target)
+            .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Runs a `tar` command and reports failures.
+fn run_tar(cmd: &mut Command) -> Result<(), Box<dyn Error>> {
+    let output = cmd
+        .output()
+        .map_err(|e| format!("Failed to run tar: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("tar failed: {}", stderr.trim()).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::lock::LockedPackage;
+    use tempfile::tempdir;
+
+    fn lockfile(env_name: &str, files: &[&str]) -> Lockfile {
+        Lockfile {
+            env_name: env_name.to_string(),
+            packages: files
+                .iter()
+                .map(|file| LockedPackage {
+                    url: format!("https://conda.anaconda.org/bioconda/linux-64/{}", file),
+                    hash: Some("abc123".to_string()),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let cache = temp_dir.path().join("cache");
+        fs::create_dir_all(&cache).unwrap();
+        for file in ["samtools-1.17-0.tar.bz2", "zlib-1.3-0.conda"] {
+            fs::write(cache.join(file), file).unwrap();
+        }
+
+        let locks = vec![
+            lockfile("samtools", &["zlib-1.3-0.conda", "samtools-1.17-0.tar.bz2"]),
+            lockfile("zlib", &["zlib-1.3-0.conda"]),
+        ];
+        let bundle = temp_dir.path().join("out/wf-envs.tar.gz");
+        let count = create_bundle(Path::new("wf.lock"), &locks, &cache, &bundle).unwrap();
+        assert_eq!(count, 2);
+
+        // Unpack on the "offline" machine
+        let target_cache = temp_dir.path().join("offline/pkgs");
+        let dest = temp_dir.path().join("offline/project");
+        fs::create_dir_all(&dest).unwrap();
+        let unpacked = unpack_bundle(&bundle, &target_cache, &dest).unwrap();
+
+        assert_eq!(unpacked.lock_dir, dest.join("wf.lock"));
+        assert_eq!(unpacked.lockfiles, locks);
+        assert_eq!(unpacked.new_packages, 2);
+        assert_eq!(
+            fs::read_to_string(target_cache.join("zlib-1.3-0.conda")).unwrap(),
+            "zlib-1.3-0.conda"
+        );
+
+        // Cached packages are not replaced
+        let again = unpack_bundle(&bundle, &target_cache, &dest).unwrap();
+        assert_eq!(again.new_packages, 0);
+    }
+
+    #[test]
+    fn test_bundle_requires_cached_packages() {
+        let temp_dir = tempdir().unwrap();
+        let locks = vec![lockfile("bwa", &["bwa-0.7.17-0.tar.bz2"])];
+
+        let error = create_bundle(
+            Path::new("wf.lock"),
+            &locks,
+            temp_dir.path(),
+            &temp_dir.path().join("b.tar.gz"),
+        )
+        .unwrap_err()
+        .to_string();
+
+        assert!(error.contains("bwa-0.7.17-0.tar.bz2 (bwa)"), "{}", error);
+        assert!(!temp_dir.path().join("b.tar.gz").exists());
+    }
+
+    #[test]
+    fn test_unpack_rejects_other_archives() {
+        let temp_dir = tempdir().unwrap();
+        fs::write(temp_dir.path().join("file.txt"), "x").unwrap();
+        let archive = temp_dir.path().join("other.tar.gz");
+        run_tar(
+            Command::new("tar")
+                .arg("-czf")
+                .arg(&archive)
+                .arg("-C")
+                .arg(temp_dir.path())
+                .arg("file.txt"),
+        )
+        .unwrap();
+
+        assert!(unpack_bundle(&archive, &temp_dir.path().join("pkgs"), temp_dir.path()).is_err());
+    }
+}
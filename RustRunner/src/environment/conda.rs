@@ -20,6 +20,13 @@ use log::{debug, error, info, warn};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 
+use super::detect::find_in_path;
+use super::filelock::{lock_path_for, FileLock};
+use super::lock::Lockfile;
+use super::packages::{parse_env_list, parse_list, parse_search, Package};
+use super::setup::EnvOptions;
+use super::spec::{CondaSpec, DEFAULT_CHANNELS};
+
 /// Lazily-initialized path to the environment mapping file.
 pub static ENV_MAP_PATH: Lazy<PathBuf> = Lazy::new(|| {
     // Priority 1: Production environment (next to executable)
@@ -52,22 +59,27 @@ pub static ENV_MAP_PATH: Lazy<PathBuf> = Lazy::new(|| {
 });
 
 /// Lazily-initialized path to the micromamba binary.
+///
+/// If micromamba isn't found, this is the path it was expected at; `rustrunner
+/// env doctor` and the engine's pre-flight check report it as missing.
 pub static MICROMAMBA_PATH: Lazy<PathBuf> = Lazy::new(|| {
     // Priority 1: Production environment (next to executable)
     // Check this first to ensure packaged apps use their bundled micromamba
-    let exe_path = std::env::current_exe().expect("Failed to get current executable path");
-    let exe_dir = exe_path.parent().expect("Executable must be in a directory");
-    let prod_path = exe_dir.join("micromamba");
-
-    if prod_path.exists() {
-        info!("Using production micromamba: {}", prod_path.display());
-        return prod_path;
+    let prod_path = std::env::current_exe()
+        .ok()
+        .and_then(|exe_path| exe_path.parent().map(|dir| dir.join(MICROMAMBA_BINARY)));
+
+    if let Some(ref prod_path) = prod_path {
+        if prod_path.exists() {
+            info!("Using production micromamba: {}", prod_path.display());
+            return prod_path.clone();
+        }
     }
 
     // Priority 2: Development environment (only if production not found)
     let dev_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
         .join("runtime")
-        .join("micromamba");
+        .join(MICROMAMBA_BINARY);
 
     if dev_path.exists() {
         info!("Using development micromamba: {}", dev_path.display());
@@ -75,27 +87,31 @@ pub static MICROMAMBA_PATH: Lazy<PathBuf> = Lazy::new(|| {
     }
 
     // Priority 3: System PATH
-    if let Ok(output) = Command::new("which").arg("micromamba").output() {
-        if output.status.success() {
-            let path_str = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !path_str.is_empty() {
-                let system_path = PathBuf::from(path_str);
-                info!("Using system micromamba: {}", system_path.display());
-                return system_path;
-            }
-        }
+    if let Some(system_path) = find_in_path(MICROMAMBA_BINARY) {
+        info!("Using system micromamba: {}", system_path.display());
+        return system_path;
     }
 
     // Not found
     warn!("Micromamba binary not found");
-    warn!("  Searched: {}", prod_path.display());
+    if let Some(ref prod_path) = prod_path {
+        warn!("  Searched: {}", prod_path.display());
+    }
     warn!("  Searched: {}", dev_path.display());
     warn!("  Searched: system PATH");
     warn!("  Download from: https://micro.mamba.pm/");
 
-    prod_path
+    prod_path.unwrap_or(dev_path)
 });
 
+/// File name of the micromamba binary.
+#[cfg(windows)]
+const MICROMAMBA_BINARY: &str = "micromamba.exe";
+
+/// File name of the micromamba binary.
+#[cfg(not(windows))]
+const MICROMAMBA_BINARY: &str = "micromamba";
+
 /// Lazily-initialized path to the micromamba root prefix (where environments are stored).
 /// This ensures the app uses its own isolated environment directory, not the system's.
 pub static MAMBA_ROOT_PREFIX: Lazy<PathBuf> = Lazy::new(|| {
@@ -142,25 +158,62 @@ impl ToolEnvMap {
     }
 
     /// Loads the mapping from disk.
+    ///
+    /// A missing or unreadable file gives an empty mapping.
     pub fn load() -> Self {
-        if ENV_MAP_PATH.exists() {
-            let content = fs::read_to_string(&*ENV_MAP_PATH).unwrap_or_default();
-            serde_json::from_str(&content).unwrap_or_else(|_| Self::new())
-        } else {
+        Self::try_load(&ENV_MAP_PATH).unwrap_or_else(|e| {
+            warn!("{}; starting with an empty environment map", e);
             Self::new()
+        })
+    }
+
+    /// Loads the mapping from a file; a missing file gives an empty mapping.
+    pub fn try_load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Self::new());
         }
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e).into())
     }
 
     /// Saves the mapping to disk.
+    ///
+    /// The file is replaced in one step, so readers never see it half
+    /// written. To change the mapping on disk use [`ToolEnvMap::update`],
+    /// which keeps other processes from overwriting the change.
     pub fn save(&self) -> Result<(), Box<dyn Error>> {
-        if let Some(parent) = ENV_MAP_PATH.parent() {
+        self.save_to(&ENV_MAP_PATH)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
             fs::create_dir_all(parent)?;
         }
         let json = serde_json::to_string_pretty(self)?;
-        fs::write(&*ENV_MAP_PATH, json)?;
+        let temp_path = path.with_extension(format!("json.{}.tmp", std::process::id()));
+        fs::write(&temp_path, json)?;
+        fs::rename(&temp_path, path)?;
         Ok(())
     }
 
+    /// Changes the mapping on disk: locks `env_map.json`, loads it, applies
+    /// `change` and saves the result.
+    ///
+    /// Changes made by other processes in the meantime are kept.
+    pub fn update<R>(change: impl FnOnce(&mut Self) -> R) -> Result<R, Box<dyn Error>> {
+        Self::update_file(&ENV_MAP_PATH, change)
+    }
+
+    fn update_file<R>(path: &Path, change: impl FnOnce(&mut Self) -> R) -> Result<R, Box<dyn Error>> {
+        let _lock = FileLock::exclusive(lock_path_for(path))?;
+        let mut env_map = Self::try_load(path)?;
+        let result = change(&mut env_map);
+        env_map.save_to(path)?;
+        Ok(result)
+    }
+
     /// Gets the environment name for a tool.
     pub fn get(&self, tool: &str) -> Option<&String> {
         self.map.get(tool)
@@ -171,6 +224,28 @@ impl ToolEnvMap {
         self.map.insert(tool.into(), env.into());
     }
 
+    /// Returns the tools mapped to an environment, sorted.
+    pub fn tools_in(&self, env: &str) -> Vec<String> {
+        let mut tools: Vec<String> = self
+            .map
+            .iter()
+            .filter(|(_, mapped)| mapped.as_str() == env)
+            .map(|(tool, _)| tool.clone())
+            .collect();
+        tools.sort();
+        tools
+    }
+
+    /// Removes the mappings to an environment, returning the tools that
+    /// were mapped to it.
+    pub fn unmap_env(&mut self, env: &str) -> Vec<String> {
+        let tools = self.tools_in(env);
+        for tool in &tools {
+            self.map.remove(tool);
+        }
+        tools
+    }
+
     /// Returns the internal map.
     pub fn as_map(&self) -> &HashMap<String, String> {
         &self.map
@@ -183,11 +258,38 @@ impl Default for ToolEnvMap {
     }
 }
 
+/// Returns the directory of an environment below the root prefix.
+pub fn env_prefix(env_name: &str) -> PathBuf {
+    MAMBA_ROOT_PREFIX.join("envs").join(env_name)
+}
+
+/// Returns the lock file guarding an environment.
+pub fn env_lock_path(env_name: &str) -> PathBuf {
+    MAMBA_ROOT_PREFIX.join("locks").join(format!("{}.lock", env_name))
+}
+
+/// Locks an environment against other processes until the lock is dropped.
+fn lock_env(env_name: &str) -> Result<FileLock, Box<dyn Error>> {
+    FileLock::exclusive(env_lock_path(env_name)).map_err(|e| {
+        format!("Failed to lock environment '{}': {}", env_name, e).into()
+    })
+}
+
+/// Returns the path a tool's executable has inside an environment.
+pub fn env_binary(prefix: &Path, tool: &str) -> PathBuf {
+    if cfg!(windows) {
+        prefix.join("Library").join("bin").join(format!("{}.exe", tool))
+    } else {
+        prefix.join("bin").join(tool)
+    }
+}
+
 /// Checks whether a micromamba environment exists.
 fn check_env(env_name: &str) -> Result<bool, Box<dyn Error>> {
     let output = micromamba_command()
         .arg("env")
         .arg("list")
+        .arg("--json")
         .output()?;
 
     if !output.status.success() {
@@ -196,12 +298,8 @@ fn check_env(env_name: &str) -> Result<bool, Box<dyn Error>> {
         return Err("Failed to list micromamba environments".into());
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let exists = stdout.lines().any(|line| {
-        matches!(line.split_whitespace().next(), Some(name) if name == env_name)
-    });
-
-    Ok(exists)
+    let envs = parse_env_list(&String::from_utf8_lossy(&output.stdout))?;
+    Ok(envs.iter().any(|name| name == env_name))
 }
 
 /// Creates a new micromamba environment with specified tools.
@@ -212,20 +310,30 @@ fn check_env(env_name: &str) -> Result<bool, Box<dyn Error>> {
 ///
 /// * `env_name` - Name for the new environment
 /// * `tools` - Tools to install (e.g., ["bowtie2", "samtools=1.17"])
+/// * `options` - Channels to install from and offline mode
 ///
 /// # Example
 ///
 /// ```rust,no_run
 /// use rustrunner::environment::conda::create_env;
+/// use rustrunner::environment::EnvOptions;
 ///
 /// fn main() -> Result<(), Box<dyn std::error::Error>> {
-///     create_env("alignment_env", &["bowtie2".to_string(), "samtools".to_string()])?;
+///     let tools = ["bowtie2".to_string(), "samtools".to_string()];
+///     create_env("alignment_env", &tools, &EnvOptions::default())?;
+///
+///     // From a local channel on a machine without internet access
+///     let offline = EnvOptions::default()
+///         .with_channel("file:///data/conda-channel")
+///         .with_offline(true);
+///     create_env("alignment_env", &tools, &offline)?;
 ///     Ok(())
 /// }
 /// ```
-pub fn create_env(env_name: &str, tools: &[String]) -> Result<(), Box<dyn Error>> {
+pub fn create_env(env_name: &str, tools: &[String], options: &EnvOptions) -> Result<(), Box<dyn Error>> {
     debug!("Checking for environment: {}", env_name);
 
+    let _lock = lock_env(env_name)?;
     if check_env(env_name)? {
         info!("Environment '{}' already exists", env_name);
         return Ok(());
@@ -236,18 +344,175 @@ pub fn create_env(env_name: &str, tools: &[String]) -> Result<(), Box<dyn Error>
         env_name, tools
     );
 
+    let mut cmd = micromamba_command();
+    cmd.arg("create").arg("-y").arg("-n").arg(env_name);
+    add_package_source(&mut cmd, options, DEFAULT_CHANNELS);
+    cmd.args(tools);
+
+    run_create(cmd, env_name)
+}
+
+/// Creates the environment described by a step's conda spec.
+///
+/// Relative environment files are resolved against `base_dir`. Returns
+/// the environment's name, which is derived from the spec's hash; if it
+/// already exists nothing is installed.
+pub fn create_spec_env(
+    spec: &CondaSpec,
+    base_dir: Option<&Path>,
+    options: &EnvOptions,
+) -> Result<String, Box<dyn Error>> {
+    spec.validate()?;
+    let env_name = spec
+        .env_name(base_dir)
+        .map_err(|e| -> Box<dyn Error> { e.to_string().into() })?;
+
+    let _lock = lock_env(&env_name)?;
+    if check_env(&env_name)? {
+        info!("Environment '{}' already exists", env_name);
+        return Ok(env_name);
+    }
+
+    let mut cmd = micromamba_command();
+    cmd.arg("create").arg("-y").arg("-n").arg(&env_name);
+
+    if let Some(file) = spec.file_path(base_dir) {
+        info!("Creating environment '{}' from {}", env_name, file.display());
+        cmd.arg("-f").arg(file);
+        add_package_source(&mut cmd, options, &[]);
+    } else {
+        info!(
+            "Creating environment '{}' with packages: {:?}",
+            env_name, spec.packages
+        );
+        let channels = spec.channels();
+        let channels: Vec<&str> = channels.iter().map(String::as_str).collect();
+        add_package_source(&mut cmd, options, &channels);
+        cmd.args(&spec.packages);
+    }
+
+    run_create(cmd, &env_name)?;
+    Ok(env_name)
+}
+
+/// Creates an environment from an explicit lockfile without solving.
+///
+/// If the environment already exists, this function returns immediately.
+///
+/// Packages already in the package cache are not downloaded again, so in
+/// offline mode the cache must hold every package of the lockfile.
+pub fn create_env_from_lock(
+    env_name: &str,
+    lockfile: &Path,
+    options: &EnvOptions,
+) -> Result<(), Box<dyn Error>> {
+    let _lock = lock_env(env_name)?;
+    if check_env(env_name)? {
+        info!("Environment '{}' already exists", env_name);
+        return Ok(());
+    }
+
+    info!("Creating environment '{}' from lockfile {}", env_name, lockfile.display());
+
+    let mut cmd = micromamba_command();
+    cmd.arg("create").arg("-y").arg("-n").arg(env_name).arg("-f").arg(lockfile);
+    add_package_source(&mut cmd, options, &[]);
+
+    run_create(cmd, env_name)
+}
+
+/// Removes an environment and its directory.
+pub fn remove_env(env_name: &str) -> Result<(), Box<dyn Error>> {
+    let _lock = lock_env(env_name)?;
+    if !check_env(env_name)? {
+        return Err(format!("Environment '{}' does not exist", env_name).into());
+    }
+
+    info!("Removing environment '{}'", env_name);
     let output = micromamba_command()
-        .arg("create")
+        .arg("env")
+        .arg("remove")
         .arg("-y")
         .arg("-n")
         .arg(env_name)
-        .arg("-c")
-        .arg("bioconda")
-        .arg("-c")
-        .arg("conda-forge")
-        .args(tools)
         .output()?;
 
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to remove environment '{}': {}", env_name, stderr.trim()).into());
+    }
+    Ok(())
+}
+
+/// Updates all packages of an environment to the newest versions its
+/// specs allow.
+pub fn update_env(env_name: &str, options: &EnvOptions) -> Result<(), Box<dyn Error>> {
+    let _lock = lock_env(env_name)?;
+    if !check_env(env_name)? {
+        return Err(format!("Environment '{}' does not exist", env_name).into());
+    }
+
+    info!("Updating environment '{}'", env_name);
+    let mut cmd = micromamba_command();
+    cmd.arg("update").arg("-y").arg("-n").arg(env_name).arg("--all");
+    add_package_source(&mut cmd, options, DEFAULT_CHANNELS);
+
+    let output = cmd.output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to update environment '{}': {}", env_name, stderr.trim()).into());
+    }
+    Ok(())
+}
+
+/// Exports the exact packages of an environment as a lockfile.
+pub fn export_lock(env_name: &str) -> Result<Lockfile, Box<dyn Error>> {
+    let output = micromamba_command()
+        .arg("env")
+        .arg("export")
+        .arg("-n")
+        .arg(env_name)
+        .arg("--explicit")
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to export environment '{}': {}", env_name, stderr.trim()).into());
+    }
+
+    Lockfile::parse(env_name, &String::from_utf8_lossy(&output.stdout))
+}
+
+/// Adds the channels and offline flag of the options to a command.
+///
+/// Channels given in the options replace `default_channels`, so a local
+/// channel is used on its own rather than in addition to remote ones.
+fn add_package_source(cmd: &mut Command, options: &EnvOptions, default_channels: &[&str]) {
+    if options.channels.is_empty() {
+        for channel in default_channels {
+            cmd.arg("-c").arg(channel);
+        }
+    } else {
+        cmd.arg("--override-channels");
+        for channel in &options.channels {
+            cmd.arg("-c").arg(channel);
+        }
+    }
+
+    if options.offline {
+        cmd.arg("--offline");
+    }
+}
+
+/// Returns the package cache holding downloaded package archives.
+pub fn package_cache_dir() -> PathBuf {
+    MAMBA_ROOT_PREFIX.join("pkgs")
+}
+
+/// Runs a `micromamba create` command and reports its outcome.
+fn run_create(mut cmd: Command, env_name: &str) -> Result<(), Box<dyn Error>> {
+    let output = cmd.output()?;
+
     if output.status.success() {
         info!("Successfully created environment '{}'", env_name);
         Ok(())
@@ -262,50 +527,37 @@ pub fn create_env(env_name: &str, tools: &[String]) -> Result<(), Box<dyn Error>
 ///
 /// # Arguments
 ///
-/// * `query` - Search term
-/// * `channel` - Channel to search (default: "bioconda")
+/// * `query` - Search term or package spec, e.g. `samtools`, `samtools=1.17`
+///   or `sam*`
+/// * `options` - Channels to search (default: those environments are
+///   created from) and offline mode
 ///
 /// # Returns
 ///
-/// List of matching package names with versions
-pub fn search_packages(query: &str, channel: Option<&str>) -> Result<Vec<String>, Box<dyn Error>> {
-    let channel = channel.unwrap_or("bioconda");
+/// Matching packages, one per version and build; an error if
+/// the search itself failed, e.g. without network access
+pub fn search_packages(query: &str, options: &EnvOptions) -> Result<Vec<Package>, Box<dyn Error>> {
+    let mut cmd = micromamba_command();
+    cmd.arg("search").arg("--json");
+    add_package_source(&mut cmd, options, DEFAULT_CHANNELS);
 
-    let output = micromamba_command()
-        .arg("search")
-        .arg("-c")
-        .arg(channel)
-        .arg(query)
-        .output()?;
+    let output = cmd.arg(query).output()?;
 
     if !output.status.success() {
-        debug!("Search returned no results for '{}'", query);
-        return Ok(Vec::new());
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let packages: Vec<String> = stdout
-        .lines()
-        .skip(2) // Skip header
-        .filter_map(|line| {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                Some(format!("{}={}", parts[0], parts[1]))
-            } else {
-                None
-            }
-        })
-        .collect();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to search for '{}': {}", query, stderr.trim()).into());
+    }
 
-    Ok(packages)
+    Ok(parse_search(&String::from_utf8_lossy(&output.stdout))?)
 }
 
 /// Lists packages installed in an environment.
-pub fn list_packages(env_name: &str) -> Result<Vec<String>, Box<dyn Error>> {
+pub fn list_packages(env_name: &str) -> Result<Vec<Package>, Box<dyn Error>> {
     let output = micromamba_command()
         .arg("list")
         .arg("-n")
         .arg(env_name)
+        .arg("--json")
         .output()?;
 
     if !output.status.success() {
@@ -313,27 +565,38 @@ pub fn list_packages(env_name: &str) -> Result<Vec<String>, Box<dyn Error>> {
         return Err(format!("Failed to list packages: {}", stderr).into());
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let packages: Vec<String> = stdout
-        .lines()
-        .skip(3) // Skip header
-        .filter_map(|line| {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 2 {
-                Some(format!("{}={}", parts[0], parts[1]))
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    Ok(packages)
+    Ok(parse_list(&String::from_utf8_lossy(&output.stdout))?)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.get_args().map(|a| a.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_package_source_defaults() {
+        let mut cmd = Command::new("micromamba");
+        add_package_source(&mut cmd, &EnvOptions::default(), DEFAULT_CHANNELS);
+        assert_eq!(args(&cmd), vec!["-c", "bioconda", "-c", "conda-forge"]);
+    }
+
+    #[test]
+    fn test_package_source_local_channel_offline() {
+        let options = EnvOptions::default()
+            .with_channel("file:///data/channel")
+            .with_offline(true);
+
+        let mut cmd = Command::new("micromamba");
+        add_package_source(&mut cmd, &options, DEFAULT_CHANNELS);
+        assert_eq!(
+            args(&cmd),
+            vec!["--override-channels", "-c", "file:///data/channel", "--offline"]
+        );
+    }
+
     #[test]
     fn test_tool_env_map_new() {
         let map = ToolEnvMap::new();
@@ -395,6 +658,43 @@ mod tests {
         assert_eq!(map.as_map().len(), 2);
     }
 
+    #[test]
+    fn test_tool_env_map_unmap_env() {
+        let mut map = ToolEnvMap::new();
+        map.set("samtools", "align");
+        map.set("bwa", "align");
+        map.set("fastqc", "qc");
+
+        assert_eq!(map.tools_in("align"), vec!["bwa", "samtools"]);
+        assert_eq!(map.unmap_env("align"), vec!["bwa", "samtools"]);
+        assert!(map.get("bwa").is_none());
+        assert_eq!(map.get("fastqc"), Some(&"qc".to_string()));
+        assert!(map.unmap_env("align").is_empty());
+    }
+
+    #[test]
+    fn test_tool_env_map_update_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("env_map.json");
+
+        ToolEnvMap::update_file(&path, |map| map.set("bwa", "align")).unwrap();
+        let count = ToolEnvMap::update_file(&path, |map| {
+            map.set("fastqc", "fastqc");
+            map.as_map().len()
+        })
+        .unwrap();
+
+        assert_eq!(count, 2);
+        let saved = ToolEnvMap::try_load(&path).unwrap();
+        assert_eq!(saved.get("bwa"), Some(&"align".to_string()));
+        assert!(temp_dir.path().join("env_map.json.lock").exists());
+
+        // A map that doesn't parse is left alone
+        fs::write(&path, "{").unwrap();
+        assert!(ToolEnvMap::update_file(&path, |map| map.set("bwa", "align")).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{");
+    }
+
     #[test]
     fn test_tool_env_map_clone() {
         let mut map = ToolEnvMap::new();
@@ -0,0 +1,285 @@
+//! System Tool Detection
+//!
+//! Finds conda tools that are already installed on this machine, e.g. by the
+//! OS package manager or a module system, so that no environment has to be
+//! created for them. What happens depends on the [`ToolPolicy`]:
+//!
+//! | Policy          | Tool on PATH                  | Environment creation fails   |
+//! |-----------------|-------------------------------|------------------------------|
+//! | `prefer-system` | used, no environment created  | warning                      |
+//! | `prefer-conda`  | ignored                       | falls back to PATH if found  |
+//! | `conda-only`    | ignored                       | error                        |
+//!
+//! A tool's version is read from its `--version` output. If the tool
+//! registry pins a version, a binary on PATH reporting a different one is
+//! not used.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+
+use crate::workflow::Workflow;
+
+use super::registry::{ToolMode, ToolRegistry};
+
+/// How long a tool may take to print its version.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where conda tools come from.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ToolPolicy {
+    /// Use tools found on PATH; create environments for the others
+    PreferSystem,
+    /// Create environments; fall back to PATH if that fails
+    #[default]
+    PreferConda,
+    /// Always run conda tools in their environments
+    CondaOnly,
+}
+
+impl ToolPolicy {
+    /// Parses `prefer-system`, `prefer-conda` or `conda-only`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "prefer-system" => Ok(Self::PreferSystem),
+            "prefer-conda" => Ok(Self::PreferConda),
+            "conda-only" => Ok(Self::CondaOnly),
+            other => Err(format!(
+                "Unknown tool policy '{}' (expected prefer-system, prefer-conda or conda-only)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ToolPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PreferSystem => write!(f, "prefer-system"),
+            Self::PreferConda => write!(f, "prefer-conda"),
+            Self::CondaOnly => write!(f, "conda-only"),
+        }
+    }
+}
+
+/// A tool installed on this machine.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SystemTool {
+    /// Path of the binary
+    pub path: PathBuf,
+    /// Version from its `--version` output, if it printed one
+    pub version: Option<String>,
+}
+
+impl fmt::Display for SystemTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.version {
+            Some(ref version) => write!(f, "{} ({})", self.path.display(), version),
+            None => write!(f, "{} (unknown version)", self.path.display()),
+        }
+    }
+}
+
+/// Looks up a program on PATH.
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Finds a tool on PATH and asks it for its version.
+pub fn detect_tool(tool: &str) -> Option<SystemTool> {
+    let path = find_in_path(tool)?;
+    let version = detect_version(&path);
+    Some(SystemTool { path, version })
+}
+
+/// Runs `<program> --version` and extracts the version it prints.
+///
+/// Many bioinformatics tools print their version as part of the usage text
+/// on stderr, with a non-zero exit code, so both streams are searched
+/// regardless of the exit status.
+fn detect_version(program: &Path) -> Option<String> {
+    let mut child = Command::new(program)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+
+    let started = Instant::now();
+    while child.try_wait().ok()?.is_none() {
+        if started.elapsed() > VERSION_TIMEOUT {
+            debug!("{} --version did not finish; killing it", program.display());
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    let output = child.wait_with_output().ok()?;
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+        .or_else(|| parse_version(&String::from_utf8_lossy(&output.stderr)))
+}
+
+/// Returns the first version number in a tool's output, e.g. `1.17` in
+/// `samtools 1.17` or `0.12.1` in `FastQC v0.12.1`.
+pub fn parse_version(output: &str) -> Option<String> {
+    output.split_whitespace().find_map(|word| {
+        let word = word.trim_end_matches([',', ';', ')', ':']);
+        let word = word.strip_prefix(['v', 'V']).unwrap_or(word);
+
+        let mut parts = word.split('.');
+        let major = parts.next()?;
+        let minor = parts.next()?;
+        let numeric = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+        let valid = numeric(major)
+            && minor.starts_with(|c: char| c.is_ascii_digit())
+            && word
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+'));
+
+        valid.then(|| word.to_string())
+    })
+}
+
+/// Checks a detected version against a pinned one: `1.17` matches `1.17`,
+/// `1.17.1` and `1.17-r2`, but not `1.170`.
+pub fn version_matches(found: &str, pinned: &str) -> bool {
+    match found.strip_prefix(pinned) {
+        Some(rest) => !rest.starts_with(|c: char| c.is_ascii_digit()),
+        None => false,
+    }
+}
+
+/// Returns the conda tools of a workflow that run in a tool environment:
+/// those of steps without a conda spec or container image.
+pub fn conda_tools<'a>(workflow: &'a Workflow, registry: &ToolRegistry) -> Vec<&'a str> {
+    let mut tools: Vec<&str> = workflow
+        .steps
+        .iter()
+        .filter(|step| step.conda.is_none() && step.container.is_none())
+        .map(|step| step.tool.as_str())
+        .filter(|tool| registry.mode(tool) == ToolMode::Conda)
+        .collect();
+    tools.sort();
+    tools.dedup();
+    tools
+}
+
+/// Finds a tool on PATH if its version fits the registry's pin.
+pub fn usable_system_tool(tool: &str, registry: &ToolRegistry) -> Option<SystemTool> {
+    let found = detect_tool(tool)?;
+    let pinned = registry.get(tool).and_then(|info| info.version.as_deref());
+
+    match (pinned, found.version.as_deref()) {
+        (Some(pinned), Some(version)) if version_matches(version, pinned) => Some(found),
+        (Some(pinned), _) => {
+            info!(
+                "Found {} {}, but the tool registry pins version {}",
+                tool, found, pinned
+            );
+            None
+        }
+        (None, _) => Some(found),
+    }
+}
+
+/// Decides which of a workflow's conda tools run from PATH before any
+/// environment is created.
+///
+/// Only `prefer-system` uses tools found on PATH up front.
+pub fn resolve_system_tools(
+    workflow: &Workflow,
+    registry: &ToolRegistry,
+    policy: ToolPolicy,
+) -> BTreeMap<String, SystemTool> {
+    if policy != ToolPolicy::PreferSystem {
+        return BTreeMap::new();
+    }
+
+    conda_tools(workflow, registry)
+        .into_iter()
+        .filter_map(|tool| usable_system_tool(tool, registry).map(|found| (tool.to_string(), found)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::Step;
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!(ToolPolicy::parse("prefer-system"), Ok(ToolPolicy::PreferSystem));
+        assert_eq!(ToolPolicy::parse("conda-only"), Ok(ToolPolicy::CondaOnly));
+        assert!(ToolPolicy::parse("system").is_err());
+        assert_eq!(ToolPolicy::default().to_string(), "prefer-conda");
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("samtools 1.17\nUsing htslib 1.17\n").as_deref(), Some("1.17"));
+        assert_eq!(parse_version("FastQC v0.12.1").as_deref(), Some("0.12.1"));
+        assert_eq!(
+            parse_version("\nProgram: bwa (alignment)\nVersion: 0.7.17-r1188\n").as_deref(),
+            Some("0.7.17-r1188")
+        );
+        assert_eq!(parse_version("STAR 2.7.11a").as_deref(), Some("2.7.11a"));
+        assert_eq!(parse_version("grep (GNU grep) 3.11,").as_deref(), Some("3.11"));
+        assert_eq!(parse_version("usage: tool [options]"), None);
+        assert_eq!(parse_version("see section 3. for details"), None);
+    }
+
+    #[test]
+    fn test_version_matches() {
+        assert!(version_matches("1.17", "1.17"));
+        assert!(version_matches("1.17.1", "1.17"));
+        assert!(version_matches("0.7.17-r1188", "0.7.17"));
+        assert!(!version_matches("1.170", "1.17"));
+        assert!(!version_matches("1.16", "1.17"));
+    }
+
+    #[test]
+    fn test_conda_tools() {
+        let workflow = Workflow::from_steps(vec![
+            Step::new("list", "ls", "ls"),
+            Step::new("sort", "samtools", "samtools sort"),
+            Step::new("index", "samtools", "samtools index"),
+            Step::new("qc", "fastqc", "fastqc").with_container("fastqc:0.12.1"),
+        ]);
+        assert_eq!(conda_tools(&workflow, &ToolRegistry::builtin()), vec!["samtools"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_system_tools() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // `sh` is a system tool; treat it as a conda tool that is installed
+        let mut registry = ToolRegistry::builtin();
+        registry.insert("sh", Default::default());
+        let workflow = Workflow::from_steps(vec![Step::new("a", "sh", "true")]);
+
+        assert!(resolve_system_tools(&workflow, &registry, ToolPolicy::PreferConda).is_empty());
+        let found = resolve_system_tools(&workflow, &registry, ToolPolicy::PreferSystem);
+        assert!(found["sh"].path.ends_with("sh"));
+
+        // The version comes from the tool's `--version` output
+        let temp_dir = tempfile::tempdir().unwrap();
+        let tool = temp_dir.path().join("mytool");
+        std::fs::write(&tool, "#!/bin/sh\necho 'mytool 2.1.0'\n").unwrap();
+        std::fs::set_permissions(&tool, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(detect_version(&tool).as_deref(), Some("2.1.0"));
+    }
+}
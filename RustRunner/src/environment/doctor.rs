@@ -0,0 +1,462 @@
+//! Environment Health Checks
+//!
+//! Checks that conda environments can be created and used:
+//!
+//! - micromamba is reachable and runs
+//! - the root prefix (`~/.rustrunner/micromamba`) is writable
+//! - `env_map.json` parses
+//! - each mapped environment exists and contains its tool's binary
+//!
+//! Every [`Problem`] comes with a fix. `rustrunner env doctor` runs all
+//! checks on the environments in `env_map.json`. Before setting up a
+//! workflow's environments the engine runs the first three as a
+//! [`preflight`], makes sure each tool's package exists with
+//! [`check_package`] (suggesting close matches for typos like `bowtei2`),
+//! and checks each tool environment once it is created.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use log::warn;
+
+use super::conda::{
+    env_binary, env_prefix, search_packages, ToolEnvMap, ENV_MAP_PATH, MAMBA_ROOT_PREFIX,
+    MICROMAMBA_PATH,
+};
+use super::packages::Package;
+use super::setup::{EnvOptions, RequiredEnv};
+
+/// Something that keeps environments from working, and how to fix it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// What is wrong
+    pub message: String,
+    /// What to do about it
+    pub fix: String,
+}
+
+impl Problem {
+    fn new(message: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            fix: fix.into(),
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n    Fix: {}", self.message, self.fix)
+    }
+}
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    /// What was checked, e.g. `micromamba` or `environment 'fastqc'`
+    pub subject: String,
+    /// What was found if the check passed, or the problem if it failed
+    pub result: Result<String, Problem>,
+}
+
+impl Check {
+    fn new(subject: impl Into<String>, result: Result<String, Problem>) -> Self {
+        Self {
+            subject: subject.into(),
+            result,
+        }
+    }
+}
+
+/// Results of a set of checks.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diagnosis {
+    /// Checks in the order they ran
+    pub checks: Vec<Check>,
+}
+
+impl Diagnosis {
+    /// Returns the problems found.
+    pub fn problems(&self) -> impl Iterator<Item = &Problem> {
+        self.checks.iter().filter_map(|check| check.result.as_ref().err())
+    }
+
+    /// Returns `true` if every check passed.
+    pub fn is_healthy(&self) -> bool {
+        self.problems().next().is_none()
+    }
+
+    fn push(&mut self, subject: impl Into<String>, result: Result<String, Problem>) {
+        self.checks.push(Check::new(subject, result));
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            match check.result {
+                Ok(ref found) => writeln!(f, "  ok    {}: {}", check.subject, found)?,
+                Err(ref problem) => writeln!(f, "  FAIL  {}: {}", check.subject, problem)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that micromamba exists and runs.
+pub fn check_micromamba(path: &Path) -> Result<String, Problem> {
+    let fix = "Download micromamba from https://micro.mamba.pm/ into RustRunner/runtime/, \
+               next to the rustrunner executable, or onto PATH";
+
+    if !path.is_file() {
+        return Err(Problem::new(format!("not found at {}", path.display()), fix));
+    }
+
+    let output = Command::new(path)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| Problem::new(format!("{} can't be run: {}", path.display(), e), fix))?;
+
+    if !output.status.success() {
+        return Err(Problem::new(
+            format!("{} --version failed ({})", path.display(), output.status),
+            fix,
+        ));
+    }
+
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(format!("{} ({})", path.display(), version))
+}
+
+/// Checks that environments can be created below the root prefix.
+pub fn check_root_prefix(prefix: &Path) -> Result<String, Problem> {
+    let fix = format!(
+        "Make {} writable for your user, or set HOME to a writable directory",
+        prefix.display()
+    );
+
+    fs::create_dir_all(prefix)
+        .map_err(|e| Problem::new(format!("{} can't be created: {}", prefix.display(), e), &fix))?;
+
+    let probe = prefix.join(".rustrunner-write-test");
+    fs::write(&probe, b"")
+        .map_err(|e| Problem::new(format!("{} is not writable: {}", prefix.display(), e), &fix))?;
+    let _ = fs::remove_file(&probe);
+
+    Ok(prefix.display().to_string())
+}
+
+/// Checks that the environment map parses, returning it.
+pub fn check_env_map(path: &Path) -> Result<ToolEnvMap, Problem> {
+    ToolEnvMap::try_load(path).map_err(|e| {
+        Problem::new(
+            e.to_string(),
+            format!(
+                "Fix the JSON in {} (expected {{\"map\": {{\"tool\": \"env\"}}}}) or delete it to start over",
+                path.display()
+            ),
+        )
+    })
+}
+
+/// Checks that a tool's environment exists in `env_dir` and contains the
+/// tool's binary.
+pub fn check_tool_env(env_dir: &Path, env_name: &str, tool: &str) -> Result<String, Problem> {
+    if !env_dir.is_dir() {
+        return Err(Problem::new(
+            format!("environment '{}' does not exist ({})", env_name, env_dir.display()),
+            format!(
+                "Run `rustrunner env create {} --name {}` to create it, or the workflow again",
+                tool, env_name
+            ),
+        ));
+    }
+
+    let binary = env_binary(env_dir, tool);
+    if !binary.is_file() {
+        return Err(Problem::new(
+            format!("environment '{}' has no '{}' binary", env_name, tool),
+            format!(
+                "Install the package providing '{}' into it (micromamba install -r {} -n {} <package>), \
+                 or map '{}' to another environment in {}",
+                tool,
+                MAMBA_ROOT_PREFIX.display(),
+                env_name,
+                tool,
+                ENV_MAP_PATH.display()
+            ),
+        ));
+    }
+
+    Ok(binary.display().to_string())
+}
+
+/// Checks that a conda package spec, e.g. `bowtie2` or `samtools=1.17`,
+/// matches a package in the channels environments are created from.
+///
+/// If the package can't be searched for, e.g. without network access, the
+/// check passes with a warning, leaving it to environment creation to fail.
+pub fn check_package(spec: &str, options: &EnvOptions) -> Result<String, Problem> {
+    let not_checked = |e: Box<dyn std::error::Error>| {
+        warn!("Could not check that package '{}' exists: {}", spec, e);
+        Ok(format!("{} (not checked)", spec))
+    };
+
+    match search_packages(spec, options) {
+        Ok(found) if !found.is_empty() => return Ok(found[0].to_string()),
+        Ok(_) => {}
+        Err(e) => return not_checked(e),
+    }
+
+    // Names starting like the package's are candidates for what was meant
+    let name = package_name(spec);
+    let prefix: String = name.chars().take(3).collect();
+    match search_packages(&format!("{}*", prefix), options) {
+        Ok(candidates) => Err(unknown_package(spec, &candidates)),
+        Err(e) => not_checked(e),
+    }
+}
+
+/// Describes a package spec that matched nothing, given the packages found
+/// for names like it.
+fn unknown_package(spec: &str, candidates: &[Package]) -> Problem {
+    let name = package_name(spec);
+    let package_fix = "correct the step's `tool:`, or set the package providing it in \
+                       ~/.rustrunner/tools.yaml";
+
+    let mut versions: Vec<&str> = candidates
+        .iter()
+        .filter(|package| package.name == name)
+        .map(|package| package.version.as_str())
+        .collect();
+    if !versions.is_empty() {
+        versions.dedup();
+        versions.truncate(5);
+        return Problem::new(
+            format!("no version of '{}' matches '{}'", name, spec),
+            format!(
+                "Use one of the versions available, e.g. {}, in ~/.rustrunner/tools.yaml",
+                versions.join(", ")
+            ),
+        );
+    }
+
+    let names = candidates.iter().map(|package| package.name.as_str());
+    let fix = match close_matches(name, names).as_slice() {
+        [] => format!("Check the spelling: {}", package_fix),
+        matches => format!("Did you mean {}? If so, {}", matches.join(", "), package_fix),
+    };
+    Problem::new(format!("no conda package named '{}'", name), fix)
+}
+
+/// Returns the package name of a spec like `samtools=1.17` or `samtools>=1.17`.
+fn package_name(spec: &str) -> &str {
+    spec.split(['=', '<', '>', '!', ' ']).next().unwrap_or(spec)
+}
+
+/// Returns up to three names close to `name`, closest first.
+///
+/// Names are close if they differ in at most a third of `name`'s
+/// characters, counting a swap of two neighbours as one.
+pub fn close_matches<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let limit = (name.chars().count() / 3).max(1);
+    let mut matches: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .collect();
+
+    matches.sort();
+    matches.dedup();
+    matches.into_iter().take(3).map(|(_, candidate)| candidate.to_string()).collect()
+}
+
+/// Counts the insertions, deletions, substitutions and swaps of
+/// neighbouring characters turning `a` into `b`, ignoring case.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+
+    // distances[i][j]: distance between the first i chars of a and j of b
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// Checks micromamba and the root prefix.
+fn check_setup(diagnosis: &mut Diagnosis) {
+    diagnosis.push("micromamba", check_micromamba(&MICROMAMBA_PATH));
+    diagnosis.push("root prefix", check_root_prefix(&MAMBA_ROOT_PREFIX));
+}
+
+/// Runs every check on the environments mapped in `env_map.json`.
+pub fn doctor() -> Diagnosis {
+    let mut diagnosis = Diagnosis::default();
+    check_setup(&mut diagnosis);
+
+    let env_map = match check_env_map(&ENV_MAP_PATH) {
+        Ok(env_map) => {
+            diagnosis.push(
+                "env_map.json",
+                Ok(format!("{} ({} tools)", ENV_MAP_PATH.display(), env_map.as_map().len())),
+            );
+            env_map
+        }
+        Err(problem) => {
+            diagnosis.push("env_map.json", Err(problem));
+            return diagnosis;
+        }
+    };
+
+    let mut mappings: Vec<(&String, &String)> = env_map.as_map().iter().collect();
+    mappings.sort();
+    for (tool, env_name) in mappings {
+        diagnosis.push(
+            format!("{} -> {}", tool, env_name),
+            check_tool_env(&env_prefix(env_name), env_name, tool),
+        );
+    }
+
+    diagnosis
+}
+
+/// Checks what a workflow's environments need before they are set up:
+/// micromamba, the root prefix and the environment map.
+pub fn preflight(required: &[RequiredEnv]) -> Diagnosis {
+    let mut diagnosis = Diagnosis::default();
+    if required.is_empty() {
+        return diagnosis;
+    }
+
+    check_setup(&mut diagnosis);
+    if let Err(problem) = check_env_map(&ENV_MAP_PATH) {
+        diagnosis.push("env_map.json", Err(problem));
+    }
+    diagnosis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::packages;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_micromamba_missing() {
+        let temp_dir = tempdir().unwrap();
+        let problem = check_micromamba(&temp_dir.path().join("micromamba")).unwrap_err();
+        assert!(problem.message.contains("not found"));
+        assert!(problem.fix.contains("micro.mamba.pm"));
+    }
+
+    #[test]
+    fn test_check_root_prefix() {
+        let temp_dir = tempdir().unwrap();
+        let prefix = temp_dir.path().join("micromamba");
+        assert!(check_root_prefix(&prefix).is_ok());
+        assert!(prefix.is_dir());
+
+        let file = temp_dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        assert!(check_root_prefix(&file.join("micromamba")).is_err());
+    }
+
+    #[test]
+    fn test_check_env_map() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("env_map.json");
+        assert!(check_env_map(&path).unwrap().as_map().is_empty());
+
+        fs::write(&path, r#"{"map": {"bwa": "align"}}"#).unwrap();
+        assert_eq!(check_env_map(&path).unwrap().get("bwa").unwrap(), "align");
+
+        fs::write(&path, r#"{"map": {"bwa": }"#).unwrap();
+        let problem = check_env_map(&path).unwrap_err();
+        assert!(problem.message.contains("Failed to parse"), "{}", problem);
+        assert!(problem.fix.contains("delete it"), "{}", problem);
+    }
+
+    #[test]
+    fn test_check_tool_env() {
+        let temp_dir = tempdir().unwrap();
+        let env_dir = temp_dir.path().join("envs/align");
+
+        let problem = check_tool_env(&env_dir, "align", "bwa").unwrap_err();
+        assert!(problem.message.contains("does not exist"), "{}", problem);
+        assert!(problem.fix.contains("rustrunner env create bwa --name align"), "{}", problem);
+
+        fs::create_dir_all(env_binary(&env_dir, "bwa").parent().unwrap()).unwrap();
+        let problem = check_tool_env(&env_dir, "align", "bwa").unwrap_err();
+        assert!(problem.message.contains("no 'bwa' binary"), "{}", problem);
+        assert!(problem.fix.contains("micromamba install"), "{}", problem);
+
+        fs::write(env_binary(&env_dir, "bwa"), "").unwrap();
+        assert!(check_tool_env(&env_dir, "align", "bwa").is_ok());
+    }
+
+    #[test]
+    fn test_close_matches() {
+        assert_eq!(edit_distance("bowtei2", "bowtie2"), 1);
+        assert_eq!(edit_distance("samtools", "Samtools"), 0);
+        assert_eq!(edit_distance("bwa", "bowtie2"), 5);
+
+        let candidates = ["bowtie", "bowtie2", "bowtie2-wrapper", "bwa", "bowtei2"];
+        assert_eq!(close_matches("bowtei2", candidates), vec!["bowtie2", "bowtie"]);
+        assert_eq!(close_matches("samtols", ["samtools", "samblaster"]), vec!["samtools"]);
+        assert!(close_matches("xyz", ["samtools"]).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_package() {
+        let candidates =
+            packages::parse_search(include_str!("../../tests/fixtures/micromamba/search_v2.json"))
+                .unwrap();
+
+        let problem = unknown_package("bowtei2", &candidates);
+        assert_eq!(problem.message, "no conda package named 'bowtei2'");
+        assert!(problem.fix.starts_with("Did you mean bowtie2?"), "{}", problem);
+
+        let problem = unknown_package("bowtie2=9.9", &candidates);
+        assert!(problem.message.contains("no version"), "{}", problem);
+        assert!(problem.fix.contains("2.5.1"), "{}", problem);
+
+        let problem = unknown_package("kallisto", &[]);
+        assert!(problem.fix.starts_with("Check the spelling"), "{}", problem);
+        assert_eq!(package_name("samtools>=1.17"), "samtools");
+    }
+
+    #[test]
+    fn test_diagnosis() {
+        let mut diagnosis = Diagnosis::default();
+        diagnosis.push("a", Ok("fine".to_string()));
+        assert!(diagnosis.is_healthy());
+
+        diagnosis.push("b", Err(Problem::new("broken", "repair it")));
+        assert!(!diagnosis.is_healthy());
+        assert_eq!(diagnosis.problems().count(), 1);
+        assert_eq!(
+            diagnosis.to_string(),
+            "  ok    a: fine\n  FAIL  b: broken\n    Fix: repair it\n"
+        );
+    }
+}
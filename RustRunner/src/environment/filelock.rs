@@ -0,0 +1,121 @@
+//! Inter-Process File Locks
+//!
+//! Keeps concurrent rustrunner processes (and the desktop app) from racing
+//! on the same environment or on `env_map.json`:
+//!
+//! - each environment is created, updated or removed while holding
+//!   `<root prefix>/locks/<env>.lock`
+//! - `env_map.json` is read, changed and written back while holding
+//!   `env_map.json.lock` next to it
+//!
+//! Locks are advisory and released when the [`FileLock`] is dropped, or
+//! when the process exits.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use fs2::FileExt;
+use log::info;
+
+/// An exclusive lock on a file, held until dropped.
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+    file: File,
+}
+
+impl FileLock {
+    /// Locks a file exclusively, creating it and its parent directories if
+    /// needed, and waits as long as another process holds the lock.
+    pub fn exclusive(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let file = Self::open(&path)?;
+
+        match file.try_lock_exclusive() {
+            Ok(()) => {}
+            Err(e) if is_contended(&e) => {
+                info!("Waiting for another process to release {}", path.display());
+                file.lock_exclusive()?;
+            }
+            Err(e) => return Err(e),
+        }
+
+        Ok(Self { path, file })
+    }
+
+    /// Locks a file exclusively if no other process holds the lock.
+    pub fn try_exclusive(path: impl Into<PathBuf>) -> io::Result<Option<Self>> {
+        let path = path.into();
+        let file = Self::open(&path)?;
+
+        match file.try_lock_exclusive() {
+            Ok(()) => Ok(Some(Self { path, file })),
+            Err(e) if is_contended(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the path of the lock file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn open(path: &Path) -> io::Result<File> {
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("Failed to open lock {}: {}", path.display(), e)))
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+/// Returns `true` if a lock attempt failed because another process holds
+/// the lock.
+fn is_contended(error: &io::Error) -> bool {
+    error.raw_os_error() == fs2::lock_contended_error().raw_os_error()
+}
+
+/// Returns the lock file guarding a file, e.g. `env_map.json.lock`.
+pub fn lock_path_for(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lock_is_exclusive() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("locks/align.lock");
+
+        let lock = FileLock::exclusive(&path).unwrap();
+        assert_eq!(lock.path(), path);
+        assert!(FileLock::try_exclusive(&path).unwrap().is_none());
+
+        drop(lock);
+        assert!(FileLock::try_exclusive(&path).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_lock_path_for() {
+        assert_eq!(
+            lock_path_for(Path::new("/opt/runtime/env_map.json")),
+            PathBuf::from("/opt/runtime/env_map.json.lock")
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Environment Lockfiles
+//!
+//! After an environment is solved, its exact contents are written to an
+//! explicit lockfile: one package URL per line, each followed by the
+//! package's hash. Creating an environment from a lockfile installs exactly
+//! those packages without running the solver, so a workflow re-run months
+//! later uses the same versions as the original analysis.
+//!
+//! Lockfiles live next to the workflow in a `<workflow>.lock/` directory,
+//! one `<env>.txt` per environment, and are meant to be committed with it:
+//!
+//! ```text
+//! pipeline.yaml
+//! pipeline.lock/
+//!     bwa.txt
+//!     rr-1f0c9a3e5b7d2468.txt
+//! ```
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Marker line that starts the package list of an explicit lockfile.
+pub const EXPLICIT_MARKER: &str = "@EXPLICIT";
+
+/// Returns the lockfile directory of a workflow file.
+///
+/// `analysis/pipeline.yaml` locks its environments in
+/// `analysis/pipeline.lock/`.
+pub fn lock_dir(workflow_path: &Path) -> PathBuf {
+    let stem = workflow_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "workflow".to_string());
+
+    workflow_path.with_file_name(format!("{}.lock", stem))
+}
+
+/// A package pinned by URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    /// Download URL of the package archive
+    pub url: String,
+
+    /// Hash of the archive, e.g. an MD5 digest or `sha256:<digest>`
+    pub hash: Option<String>,
+}
+
+impl LockedPackage {
+    /// Returns the archive's file name, e.g. `samtools-1.17-h00cdaf9_0.tar.bz2`.
+    pub fn file_name(&self) -> &str {
+        self.url.rsplit('/').next().unwrap_or(&self.url)
+    }
+}
+
+/// The exact packages of one environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lockfile {
+    /// Name of the locked environment
+    pub env_name: String,
+
+    /// Packages in installation order
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Lockfile {
+    /// Returns the path of an environment's lockfile within a lock directory.
+    pub fn path(lock_dir: &Path, env_name: &str) -> PathBuf {
+        lock_dir.join(format!("{}.txt", env_name))
+    }
+
+    /// Parses an explicit lockfile, e.g. the output of
+    /// `micromamba env export --explicit`.
+    ///
+    /// Comments and lines before the `@EXPLICIT` marker are ignored.
+    pub fn parse(env_name: &str, text: &str) -> Result<Self, Box<dyn Error>> {
+        let mut lines = text.lines().map(str::trim);
+
+        if !lines.any(|line| line == EXPLICIT_MARKER) {
+            return Err(format!(
+                "Lockfile for '{}' is not an explicit package list (no {} line)",
+                env_name, EXPLICIT_MARKER
+            )
+            .into());
+        }
+
+        let packages = lines
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| match line.split_once('#') {
+                Some((url, hash)) => LockedPackage {
+                    url: url.to_string(),
+                    hash: Some(hash.to_string()),
+                },
+                None => LockedPackage {
+                    url: line.to_string(),
+                    hash: None,
+                },
+            })
+            .collect();
+
+        Ok(Self {
+            env_name: env_name.to_string(),
+            packages,
+        })
+    }
+
+    /// Renders the lockfile in the explicit format micromamba installs from.
+    pub fn render(&self) -> String {
+        let mut text = format!(
+            "# rustrunner lockfile for environment '{}'\n# Install with: micromamba create -n {} -f <this file>\n{}\n",
+            self.env_name, self.env_name, EXPLICIT_MARKER
+        );
+
+        for package in &self.packages {
+            text.push_str(&package.url);
+            if let Some(ref hash) = package.hash {
+                text.push('#');
+                text.push_str(hash);
+            }
+            text.push('\n');
+        }
+
+        text
+    }
+
+    /// Loads an environment's lockfile from a lock directory.
+    pub fn load(lock_dir: &Path, env_name: &str) -> Result<Self, Box<dyn Error>> {
+        let path = Self::path(lock_dir, env_name);
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read lockfile '{}': {}", path.display(), e))?;
+        Self::parse(env_name, &text)
+    }
+
+    /// Writes the lockfile into a lock directory and returns its path.
+    pub fn save(&self, lock_dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        fs::create_dir_all(lock_dir)?;
+        let path = Self::path(lock_dir, &self.env_name);
+        fs::write(&path, self.render())?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    const EXPORT: &str = "\
+# This file may be used to create an environment using:
+# $ conda create --name <env> --file <this file>
+# platform: linux-64
+@EXPLICIT
+https://conda.anaconda.org/conda-forge/linux-64/zlib-1.2.13-hd590300_5.conda#68c34ec6149623be41a1933ab996a209
+https://conda.anaconda.org/bioconda/linux-64/samtools-1.17-h00cdaf9_0.tar.bz2#0a6e5b0e1d1e4c8b2f1a0c9d8e7f6a5b
+";
+
+    #[test]
+    fn test_lock_dir() {
+        assert_eq!(
+            lock_dir(Path::new("analysis/pipeline.yaml")),
+            PathBuf::from("analysis/pipeline.lock")
+        );
+        assert_eq!(lock_dir(Path::new("wf.yml")), PathBuf::from("wf.lock"));
+    }
+
+    #[test]
+    fn test_parse_explicit_export() {
+        let lock = Lockfile::parse("samtools", EXPORT).unwrap();
+
+        assert_eq!(lock.packages.len(), 2);
+        assert_eq!(lock.packages[1].file_name(), "samtools-1.17-h00cdaf9_0.tar.bz2");
+        assert_eq!(
+            lock.packages[0].hash.as_deref(),
+            Some("68c34ec6149623be41a1933ab996a209")
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_non_explicit() {
+        assert!(Lockfile::parse("env", "name: env\ndependencies:\n  - samtools\n").is_err());
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let temp_dir = tempdir().unwrap();
+        let lock = Lockfile::parse("samtools", EXPORT).unwrap();
+
+        let path = lock.save(temp_dir.path()).unwrap();
+        assert_eq!(path, temp_dir.path().join("samtools.txt"));
+
+        let loaded = Lockfile::load(temp_dir.path(), "samtools").unwrap();
+        assert_eq!(loaded, lock);
+        assert!(Lockfile::load(temp_dir.path(), "bwa").is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! Environment Management
+//!
+//! Lists, creates, updates and removes the environments below the root
+//! prefix (`~/.rustrunner/micromamba/envs`), keeping `env_map.json` in
+//! step: a tool is mapped to the environment created for it and unmapped
+//! when that environment is removed. Both the environments and the map are
+//! locked while they change (see [`filelock`](super::filelock)).
+//!
+//! Environments no workflow references any more can be pruned; which ones
+//! those are is decided by [`unreferenced_envs`] from the environments of
+//! the workflows still in use.
+
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::conda::{create_env, env_prefix, remove_env, ToolEnvMap, MAMBA_ROOT_PREFIX};
+use super::registry::ToolRegistry;
+use super::setup::EnvOptions;
+
+/// An environment below the root prefix.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EnvInfo {
+    /// Name of the environment
+    pub name: String,
+    /// Directory of the environment
+    pub prefix: PathBuf,
+    /// Tools mapped to it in `env_map.json`
+    pub tools: Vec<String>,
+}
+
+/// Returns the names of the environments in an `envs` directory, sorted.
+///
+/// A missing directory has no environments.
+pub fn env_names(envs_dir: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    if !envs_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut names = Vec::new();
+    for entry in fs::read_dir(envs_dir)
+        .map_err(|e| format!("Failed to read {}: {}", envs_dir.display(), e))?
+    {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Lists the environments below the root prefix with the tools mapped to
+/// each.
+pub fn list_envs(env_map: &ToolEnvMap) -> Result<Vec<EnvInfo>, Box<dyn Error>> {
+    Ok(env_names(&MAMBA_ROOT_PREFIX.join("envs"))?
+        .into_iter()
+        .map(|name| EnvInfo {
+            prefix: env_prefix(&name),
+            tools: env_map.tools_in(&name),
+            name,
+        })
+        .collect())
+}
+
+/// Creates an environment with the packages the registry lists for
+/// `tools` and maps each tool to it in `env_map.json`.
+pub fn create_tool_env(
+    env_name: &str,
+    tools: &[String],
+    registry: &ToolRegistry,
+    options: &EnvOptions,
+) -> Result<(), Box<dyn Error>> {
+    let packages: Vec<String> = tools.iter().map(|tool| registry.package_spec(tool)).collect();
+    create_env(env_name, &packages, options)?;
+
+    ToolEnvMap::update(|env_map| {
+        for tool in tools {
+            env_map.set(tool, env_name);
+        }
+    })
+}
+
+/// Removes an environment and unmaps the tools mapped to it in
+/// `env_map.json`, returning those tools.
+pub fn remove_tool_env(env_name: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    remove_env(env_name)?;
+    ToolEnvMap::update(|env_map| env_map.unmap_env(env_name))
+}
+
+/// Returns the installed environments that are not in `referenced`.
+pub fn unreferenced_envs(installed: &[String], referenced: &BTreeSet<String>) -> Vec<String> {
+    installed
+        .iter()
+        .filter(|name| !referenced.contains(*name))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_env_names() {
+        let temp_dir = tempdir().unwrap();
+        let envs_dir = temp_dir.path().join("envs");
+        assert!(env_names(&envs_dir).unwrap().is_empty());
+
+        fs::create_dir_all(envs_dir.join("samtools")).unwrap();
+        fs::create_dir_all(envs_dir.join("fastqc")).unwrap();
+        fs::write(envs_dir.join("notes.txt"), "").unwrap();
+        assert_eq!(env_names(&envs_dir).unwrap(), vec!["fastqc", "samtools"]);
+    }
+
+    #[test]
+    fn test_unreferenced_envs() {
+        let installed = vec!["fastqc".to_string(), "old".to_string(), "rr-1a2b".to_string()];
+        let referenced: BTreeSet<String> = ["fastqc".to_string(), "rr-1a2b".to_string()].into();
+        assert_eq!(unreferenced_envs(&installed, &referenced), vec!["old"]);
+        assert_eq!(unreferenced_envs(&installed, &BTreeSet::new()), installed);
+    }
+}
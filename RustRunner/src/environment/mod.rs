@@ -3,6 +3,24 @@
 //! Handles integration with conda/micromamba for managing
 //! isolated bioinformatics tool environments.
 
+pub mod bundle;
 pub mod conda;
+pub mod detect;
+pub mod doctor;
+pub mod filelock;
+pub mod lock;
+pub mod manage;
+pub mod packages;
+pub mod registry;
+pub mod setup;
+pub mod spec;
 
-pub use conda::{create_env, search_packages, ToolEnvMap, MICROMAMBA_PATH, ENV_MAP_PATH, MAMBA_ROOT_PREFIX};
+pub use conda::{create_env, create_spec_env, search_packages, ToolEnvMap, MICROMAMBA_PATH, ENV_MAP_PATH, MAMBA_ROOT_PREFIX};
+pub use detect::{SystemTool, ToolPolicy};
+pub use doctor::{Diagnosis, Problem};
+pub use lock::Lockfile;
+pub use manage::EnvInfo;
+pub use packages::Package;
+pub use registry::{ToolInfo, ToolMode, ToolRegistry, TOOL_REGISTRY};
+pub use setup::{EnvOptions, RequiredEnv};
+pub use spec::CondaSpec;
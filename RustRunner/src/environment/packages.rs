@@ -0,0 +1,200 @@
+//! Micromamba JSON Output
+//!
+//! Parses the `--json` output of `micromamba search`, `micromamba list` and
+//! `micromamba env list` into typed values, rather than scraping the
+//! tables micromamba prints for people, whose layout changes between
+//! releases.
+//!
+//! Field names differ a little between commands and micromamba versions:
+//! `list` reports the build as `build_string` and the subdir as
+//! `platform`, and micromamba 2 reports channels as URLs. [`Package`]
+//! smooths these over.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Base URL of channels hosted on anaconda.org.
+const ANACONDA_CHANNELS: &str = "https://conda.anaconda.org/";
+
+/// A conda package, as found by `micromamba search` or installed in an
+/// environment.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "RawPackage")]
+pub struct Package {
+    /// Package name, e.g. `samtools`
+    pub name: String,
+    /// Version, e.g. `1.17`
+    pub version: String,
+    /// Build string, e.g. `h50ea8bc_0`
+    pub build: String,
+    /// Channel name, e.g. `bioconda`, or its URL for channels not on
+    /// anaconda.org
+    pub channel: String,
+    /// Platform subdirectory, e.g. `linux-64` or `noarch`
+    pub subdir: String,
+}
+
+impl Package {
+    /// Returns the spec installing exactly this version, e.g.
+    /// `samtools=1.17`.
+    pub fn spec(&self) -> String {
+        format!("{}={}", self.name, self.version)
+    }
+}
+
+impl fmt::Display for Package {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}/{}",
+            self.name, self.version, self.build, self.channel, self.subdir
+        )
+    }
+}
+
+/// A package entry with every field name micromamba uses.
+#[derive(Deserialize)]
+struct RawPackage {
+    name: String,
+    version: String,
+    #[serde(default)]
+    build: Option<String>,
+    #[serde(default)]
+    build_string: Option<String>,
+    #[serde(default)]
+    channel: Option<String>,
+    #[serde(default)]
+    subdir: Option<String>,
+    #[serde(default)]
+    platform: Option<String>,
+}
+
+impl From<RawPackage> for Package {
+    fn from(raw: RawPackage) -> Self {
+        let channel = raw.channel.unwrap_or_default();
+        let channel = channel.trim_end_matches('/');
+
+        Self {
+            name: raw.name,
+            version: raw.version,
+            build: raw.build.or(raw.build_string).unwrap_or_default(),
+            channel: channel
+                .strip_prefix(ANACONDA_CHANNELS)
+                .unwrap_or(channel)
+                .to_string(),
+            subdir: raw.subdir.or(raw.platform).unwrap_or_default(),
+        }
+    }
+}
+
+/// Output of `micromamba search --json`.
+#[derive(Deserialize)]
+struct SearchOutput {
+    result: SearchResult,
+}
+
+#[derive(Deserialize)]
+struct SearchResult {
+    #[serde(default)]
+    pkgs: Vec<Package>,
+}
+
+/// Output of `micromamba env list --json`.
+#[derive(Deserialize)]
+struct EnvListOutput {
+    envs: Vec<PathBuf>,
+}
+
+/// Parses the output of `micromamba search --json`.
+pub fn parse_search(json: &str) -> Result<Vec<Package>, String> {
+    serde_json::from_str::<SearchOutput>(json)
+        .map(|output| output.result.pkgs)
+        .map_err(|e| format!("Unexpected micromamba search output: {}", e))
+}
+
+/// Parses the output of `micromamba list --json`.
+pub fn parse_list(json: &str) -> Result<Vec<Package>, String> {
+    serde_json::from_str(json).map_err(|e| format!("Unexpected micromamba list output: {}", e))
+}
+
+/// Parses the output of `micromamba env list --json` into the names of
+/// the named environments (those in an `envs` directory).
+pub fn parse_env_list(json: &str) -> Result<Vec<String>, String> {
+    let output: EnvListOutput = serde_json::from_str(json)
+        .map_err(|e| format!("Unexpected micromamba env list output: {}", e))?;
+
+    Ok(output
+        .envs
+        .iter()
+        .filter(|path| path.parent().and_then(|parent| parent.file_name()) == Some("envs".as_ref()))
+        .filter_map(|path| path.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEARCH: &str = include_str!("../../tests/fixtures/micromamba/search.json");
+    const SEARCH_V2: &str = include_str!("../../tests/fixtures/micromamba/search_v2.json");
+    const LIST: &str = include_str!("../../tests/fixtures/micromamba/list.json");
+    const ENV_LIST: &str = include_str!("../../tests/fixtures/micromamba/env_list.json");
+
+    #[test]
+    fn test_parse_search() {
+        let packages = parse_search(SEARCH).unwrap();
+        assert_eq!(packages.len(), 2);
+        assert_eq!(
+            packages[0],
+            Package {
+                name: "samtools".to_string(),
+                version: "1.17".to_string(),
+                build: "h50ea8bc_0".to_string(),
+                channel: "bioconda".to_string(),
+                subdir: "linux-64".to_string(),
+            }
+        );
+        assert_eq!(packages[1].spec(), "samtools=1.16.1");
+    }
+
+    #[test]
+    fn test_parse_search_channel_urls() {
+        let packages = parse_search(SEARCH_V2).unwrap();
+        let names: Vec<&str> = packages.iter().map(|package| package.name.as_str()).collect();
+        assert_eq!(names, vec!["bowtie2", "bowtie2-wrapper"]);
+        assert!(packages.iter().all(|package| package.channel == "bioconda"));
+        assert_eq!(packages[0].subdir, "linux-64");
+        assert_eq!(packages[1].subdir, "noarch");
+    }
+
+    #[test]
+    fn test_parse_search_no_results() {
+        let json = r#"{"query": {"query": "bowtei2", "type": "search"}, "result": {"msg": "", "pkgs": []}}"#;
+        assert!(parse_search(json).unwrap().is_empty());
+        assert!(parse_search("No entries matching \"bowtei2\" found").is_err());
+    }
+
+    #[test]
+    fn test_parse_list() {
+        let packages = parse_list(LIST).unwrap();
+        assert_eq!(packages.len(), 3);
+
+        let samtools = packages.iter().find(|package| package.name == "samtools").unwrap();
+        assert_eq!(samtools.build, "h50ea8bc_0");
+        assert_eq!(samtools.channel, "bioconda");
+        assert_eq!(samtools.subdir, "linux-64");
+        assert_eq!(samtools.to_string(), "samtools 1.17 h50ea8bc_0 bioconda/linux-64");
+    }
+
+    #[test]
+    fn test_parse_env_list() {
+        assert_eq!(
+            parse_env_list(ENV_LIST).unwrap(),
+            vec!["fastqc", "rr-3f9a1c2b4d5e6f70", "samtools"]
+        );
+        assert!(parse_env_list(r#"{"envs": []}"#).unwrap().is_empty());
+    }
+}
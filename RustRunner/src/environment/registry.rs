@@ -0,0 +1,294 @@
+//! Tool Registry
+//!
+//! Describes how each tool is run: from the system PATH, from a conda
+//! environment or in a container, with the conda package and version to
+//! install, the container image and the threads steps get by default.
+//!
+//! The registry shipped in `runtime/tools.yaml` is compiled into the binary.
+//! Users can extend or override it with `~/.rustrunner/tools.yaml` (YAML or
+//! JSON):
+//!
+//! ```yaml
+//! tools:
+//!   samtools: { version: "1.17", threads: 4 }
+//!   gatk: { mode: container, container: broadinstitute/gatk:4.5.0.0 }
+//!   module-bwa: { mode: system }
+//! ```
+//!
+//! Tools that aren't listed are installed from conda under their own name.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+/// The registry shipped with RustRunner.
+const BUILTIN_REGISTRY: &str = include_str!("../../runtime/tools.yaml");
+
+/// Path of the user's registry, `~/.rustrunner/tools.yaml`.
+pub static USER_REGISTRY_PATH: Lazy<PathBuf> = Lazy::new(|| {
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .unwrap_or_else(|_| ".".to_string());
+
+    PathBuf::from(home).join(".rustrunner").join("tools.yaml")
+});
+
+/// The built-in registry extended with the user's, loaded once.
+///
+/// An invalid user registry is reported and ignored.
+pub static TOOL_REGISTRY: Lazy<ToolRegistry> = Lazy::new(|| {
+    ToolRegistry::load().unwrap_or_else(|e| {
+        warn!("{}; using the built-in tool registry", e);
+        ToolRegistry::builtin()
+    })
+});
+
+/// How a tool is run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolMode {
+    /// From the system PATH
+    System,
+    /// From a conda environment
+    #[default]
+    Conda,
+    /// In a container
+    Container,
+}
+
+impl fmt::Display for ToolMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::System => write!(f, "system"),
+            Self::Conda => write!(f, "conda"),
+            Self::Container => write!(f, "container"),
+        }
+    }
+}
+
+/// Registry entry for a tool.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ToolInfo {
+    /// How the tool is run
+    #[serde(default)]
+    pub mode: ToolMode,
+
+    /// Conda package providing the tool (default: the tool name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+
+    /// Conda package version, e.g. `1.17`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// Container image for container tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
+
+    /// Threads for steps that don't set `threads`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads: Option<usize>,
+}
+
+impl ToolInfo {
+    /// Checks that the entry's fields fit its mode.
+    fn validate(&self, tool: &str) -> Result<(), String> {
+        let invalid = |reason: &str| Err(format!("Tool '{}': {}", tool, reason));
+
+        if self.threads == Some(0) {
+            return invalid("threads must be at least 1");
+        }
+        if let Some(ref package) = self.package {
+            if package.is_empty() || package.contains(char::is_whitespace) {
+                return invalid("invalid conda package name");
+            }
+        }
+        if let Some(ref version) = self.version {
+            if version.is_empty() || version.contains(char::is_whitespace) {
+                return invalid("invalid conda package version");
+            }
+        }
+
+        match self.mode {
+            ToolMode::System if self.package.is_some() || self.version.is_some() => {
+                invalid("system tools have no conda package")
+            }
+            ToolMode::Container if self.container.is_none() => {
+                invalid("container tools need a container image")
+            }
+            ToolMode::System | ToolMode::Conda if self.container.is_some() => {
+                invalid("a container image requires mode: container")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Tool names and how each tool is run.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, ToolInfo>,
+}
+
+impl ToolRegistry {
+    /// Returns the registry shipped with RustRunner.
+    pub fn builtin() -> Self {
+        static BUILTIN: Lazy<ToolRegistry> = Lazy::new(|| {
+            ToolRegistry::parse(BUILTIN_REGISTRY).expect("Built-in tool registry is invalid")
+        });
+        BUILTIN.clone()
+    }
+
+    /// Returns the built-in registry extended with the user's registry, if
+    /// there is one.
+    pub fn load() -> Result<Self, String> {
+        let mut registry = Self::builtin();
+        if USER_REGISTRY_PATH.exists() {
+            info!("Using tool registry: {}", USER_REGISTRY_PATH.display());
+            registry.extend(Self::from_file(&USER_REGISTRY_PATH)?);
+        }
+        Ok(registry)
+    }
+
+    /// Reads and validates a registry file (YAML or JSON).
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read tool registry {}: {}", path.display(), e))?;
+        Self::parse(&content).map_err(|e| format!("Invalid tool registry {}: {}", path.display(), e))
+    }
+
+    /// Parses and validates a registry.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        // JSON is valid YAML
+        let registry: Self = serde_yaml::from_str(content).map_err(|e| e.to_string())?;
+        registry.validate()?;
+        Ok(registry)
+    }
+
+    /// Checks every entry.
+    pub fn validate(&self) -> Result<(), String> {
+        for (tool, info) in &self.tools {
+            if tool.is_empty() || tool.contains(char::is_whitespace) {
+                return Err(format!("Invalid tool name '{}'", tool));
+            }
+            info.validate(tool)?;
+        }
+        Ok(())
+    }
+
+    /// Adds or replaces entries with those of another registry.
+    pub fn extend(&mut self, other: ToolRegistry) {
+        self.tools.extend(other.tools);
+    }
+
+    /// Adds or replaces a tool's entry.
+    pub fn insert(&mut self, tool: impl Into<String>, info: ToolInfo) {
+        self.tools.insert(tool.into(), info);
+    }
+
+    /// Returns a tool's entry.
+    pub fn get(&self, tool: &str) -> Option<&ToolInfo> {
+        self.tools.get(tool)
+    }
+
+    /// Returns all tools and their entries, sorted by name.
+    pub fn tools(&self) -> impl Iterator<Item = (&String, &ToolInfo)> {
+        self.tools.iter()
+    }
+
+    /// Returns how a tool is run; unlisted tools come from conda.
+    pub fn mode(&self, tool: &str) -> ToolMode {
+        self.get(tool).map(|info| info.mode).unwrap_or_default()
+    }
+
+    /// Checks if a tool runs from the system PATH (doesn't require conda).
+    pub fn is_system_tool(&self, tool: &str) -> bool {
+        self.mode(tool) == ToolMode::System
+    }
+
+    /// Returns the conda package spec installing a tool, e.g. `star` or
+    /// `samtools=1.17`.
+    pub fn package_spec(&self, tool: &str) -> String {
+        let info = self.get(tool);
+        let package = info.and_then(|info| info.package.as_deref()).unwrap_or(tool);
+        match info.and_then(|info| info.version.as_deref()) {
+            Some(version) => format!("{}={}", package, version),
+            None => package.to_string(),
+        }
+    }
+
+    /// Returns the container image of a container tool.
+    pub fn container(&self, tool: &str) -> Option<&str> {
+        self.get(tool)
+            .filter(|info| info.mode == ToolMode::Container)
+            .and_then(|info| info.container.as_deref())
+    }
+
+    /// Returns the default threads of a tool.
+    pub fn threads(&self, tool: &str) -> Option<usize> {
+        self.get(tool).and_then(|info| info.threads)
+    }
+}
+
+impl Default for ToolRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_registry() {
+        let registry = ToolRegistry::builtin();
+        assert!(registry.is_system_tool("find"));
+        assert!(registry.is_system_tool("xargs"));
+        assert!(!registry.is_system_tool("samtools"));
+
+        assert_eq!(registry.mode("samtools"), ToolMode::Conda);
+        assert_eq!(registry.package_spec("samtools"), "samtools");
+        assert_eq!(registry.package_spec("STAR"), "star");
+    }
+
+    #[test]
+    fn test_extend_registry() {
+        let mut registry = ToolRegistry::builtin();
+        registry.extend(
+            ToolRegistry::parse(
+                r#"{"tools": {
+                    "samtools": {"version": "1.17", "threads": 4},
+                    "gatk": {"mode": "container", "container": "broadinstitute/gatk:4.5.0.0"},
+                    "grep": {"package": "grep", "version": "3.11"}
+                }}"#,
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(registry.package_spec("samtools"), "samtools=1.17");
+        assert_eq!(registry.threads("samtools"), Some(4));
+        assert_eq!(registry.container("gatk"), Some("broadinstitute/gatk:4.5.0.0"));
+        assert!(!registry.is_system_tool("grep"));
+        assert_eq!(registry.package_spec("grep"), "grep=3.11");
+        assert!(registry.is_system_tool("bash"));
+    }
+
+    #[test]
+    fn test_invalid_registry() {
+        let error = |yaml: &str| ToolRegistry::parse(yaml).unwrap_err();
+
+        assert!(error("tools:\n  gatk: { mode: container }\n").contains("Tool 'gatk'"));
+        assert!(error("tools:\n  ls: { mode: system, version: '1' }\n").contains("no conda package"));
+        assert!(error("tools:\n  bwa: { container: bwa:0.7 }\n").contains("mode: container"));
+        assert!(error("tools:\n  bwa: { threads: 0 }\n").contains("threads"));
+        assert!(error("tools:\n  bwa: { mode: module }\n").contains("unknown variant"));
+        assert!(error("tools:\n  bwa: { thread: 2 }\n").contains("unknown field"));
+    }
+}
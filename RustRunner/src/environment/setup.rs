@@ -0,0 +1,279 @@
+//! Environment Setup
+//!
+//! Works out which conda environments a workflow needs and creates them,
+//! either by solving their packages or, in locked mode, by installing the
+//! exact packages recorded in the workflow's lockfiles.
+//!
+//! Each step runs in one environment:
+//! - a step with a `conda:` spec uses the environment named after the
+//!   spec's hash, shared by all steps with the same spec
+//! - a step with a `container:` image needs no environment
+//! - a system tool (see the [tool registry](super::registry)) needs none
+//! - any other conda tool uses the environment mapped to it in
+//!   `env_map.json`, or one named after the tool, with the package the
+//!   registry lists for it
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+
+use crate::workflow::Workflow;
+
+use super::conda::{create_env, create_env_from_lock, create_spec_env, export_lock, ToolEnvMap};
+use super::lock::Lockfile;
+use super::registry::ToolRegistry;
+use super::spec::CondaSpec;
+
+/// How environments are created.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnvOptions {
+    /// Directory holding the workflow's lockfiles
+    pub lock_dir: Option<PathBuf>,
+
+    /// Install from lockfiles instead of solving
+    pub locked: bool,
+
+    /// Channels replacing the default ones, e.g. `file:///data/channel`
+    pub channels: Vec<String>,
+
+    /// Never access the network; use only local channels and the package cache
+    pub offline: bool,
+}
+
+impl EnvOptions {
+    /// Sets the lockfile directory.
+    pub fn with_lock_dir(mut self, lock_dir: impl Into<PathBuf>) -> Self {
+        self.lock_dir = Some(lock_dir.into());
+        self
+    }
+
+    /// Installs environments from their lockfiles instead of solving.
+    pub fn with_locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+
+    /// Adds a channel to install from instead of the defaults.
+    ///
+    /// A path to an existing local directory is turned into a `file://`
+    /// URL.
+    pub fn with_channel(mut self, channel: impl Into<String>) -> Self {
+        let channel = channel.into();
+        let path = Path::new(&channel);
+
+        if !channel.contains("://") && path.is_dir() {
+            let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+            self.channels.push(format!("file://{}", path.display()));
+        } else {
+            self.channels.push(channel);
+        }
+        self
+    }
+
+    /// Never accesses the network.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+}
+
+/// What an environment is built from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EnvSource {
+    /// A tool's conda package
+    Tool {
+        /// Name of the tool
+        tool: String,
+        /// Package spec installing it, e.g. `samtools=1.17`
+        package: String,
+    },
+    /// A step's conda spec
+    Spec(CondaSpec),
+}
+
+/// An environment needed by one or more steps of a workflow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequiredEnv {
+    /// Name of the environment
+    pub name: String,
+
+    /// What the environment is built from
+    pub source: EnvSource,
+
+    /// IDs of the steps running in it
+    pub steps: Vec<String>,
+}
+
+impl RequiredEnv {
+    /// Creates the environment if it doesn't exist and writes its lockfile
+    /// if there is none yet.
+    ///
+    /// In locked mode the environment is installed from its lockfile, which
+    /// must exist.
+    pub fn prepare(&self, base_dir: Option<&Path>, options: &EnvOptions) -> Result<(), Box<dyn Error>> {
+        let lockfile = options
+            .lock_dir
+            .as_ref()
+            .map(|dir| Lockfile::path(dir, &self.name));
+
+        if options.locked {
+            let lockfile = lockfile.ok_or("Locked mode requires a lockfile directory")?;
+            if !lockfile.exists() {
+                return Err(format!(
+                    "No lockfile for environment '{}' at {}; run `rustrunner env export` first",
+                    self.name,
+                    lockfile.display()
+                )
+                .into());
+            }
+            return create_env_from_lock(&self.name, &lockfile, options);
+        }
+
+        match &self.source {
+            EnvSource::Tool { package, .. } => create_env(&self.name, std::slice::from_ref(package), options)?,
+            EnvSource::Spec(spec) => {
+                create_spec_env(spec, base_dir, options)?;
+            }
+        }
+
+        if let (Some(dir), Some(lockfile)) = (&options.lock_dir, lockfile) {
+            if !lockfile.exists() {
+                match export_lock(&self.name).and_then(|lock| lock.save(dir)) {
+                    Ok(path) => info!("Locked environment '{}' in {}", self.name, path.display()),
+                    Err(e) => warn!("Failed to lock environment '{}': {}", self.name, e),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Lists the conda environments a workflow's steps run in.
+///
+/// Steps running in a container, and steps whose tool is a system tool in
+/// `registry` and that have no conda spec, need no environment. Relative
+/// environment files are resolved against `base_dir`.
+pub fn required_environments(
+    workflow: &Workflow,
+    base_dir: Option<&Path>,
+    env_map: &ToolEnvMap,
+    registry: &ToolRegistry,
+) -> Result<Vec<RequiredEnv>, String> {
+    let mut envs: BTreeMap<String, RequiredEnv> = BTreeMap::new();
+
+    for step in &workflow.steps {
+        let (name, source) = match &step.conda {
+            Some(spec) => {
+                let name = spec
+                    .env_name(base_dir)
+                    .map_err(|e| format!("Step '{}': {}", step.id, e))?;
+                (name, EnvSource::Spec(spec.clone()))
+            }
+            None if step.container.is_some() || registry.is_system_tool(&step.tool) => {
+                continue
+            }
+            None => {
+                let name = env_map.get(&step.tool).unwrap_or(&step.tool).clone();
+                let source = EnvSource::Tool {
+                    tool: step.tool.clone(),
+                    package: registry.package_spec(&step.tool),
+                };
+                (name, source)
+            }
+        };
+
+        envs.entry(name.clone())
+            .or_insert_with(|| RequiredEnv {
+                name,
+                source,
+                steps: Vec::new(),
+            })
+            .steps
+            .push(step.id.clone());
+    }
+
+    Ok(envs.into_values().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::Step;
+    use tempfile::tempdir;
+
+    fn workflow() -> Workflow {
+        let spec = CondaSpec::with_packages(["bwa=0.7.17", "samtools=1.17"]);
+        Workflow::from_steps(vec![
+            Step::new("list", "ls", "ls"),
+            Step::new("qc", "fastqc", "fastqc {input}"),
+            Step::new("align", "bwa", "bwa mem | samtools sort").with_conda(spec.clone()),
+            Step::new("sort", "samtools", "samtools sort").with_conda(spec),
+            Step::new("index", "bowtie2-build", "bowtie2-build ref.fa ref"),
+            Step::new("stats", "samtools", "samtools stats").with_container("samtools:1.17"),
+        ])
+    }
+
+    #[test]
+    fn test_required_environments() {
+        let mut env_map = ToolEnvMap::new();
+        env_map.set("bowtie2-build", "bowtie2");
+
+        let envs = required_environments(&workflow(), None, &env_map, &ToolRegistry::builtin()).unwrap();
+        let names: Vec<&str> = envs.iter().map(|env| env.name.as_str()).collect();
+
+        assert_eq!(envs.len(), 3);
+        assert!(names.contains(&"fastqc"));
+        assert!(names.contains(&"bowtie2"));
+
+        let shared = envs.iter().find(|env| env.name.starts_with("rr-")).unwrap();
+        assert_eq!(shared.steps, vec!["align", "sort"]);
+        assert!(matches!(shared.source, EnvSource::Spec(_)));
+
+        // The registry names the package providing bowtie2-build
+        let index = envs.iter().find(|env| env.name == "bowtie2").unwrap();
+        assert!(matches!(&index.source, EnvSource::Tool { package, .. } if package == "bowtie2"));
+    }
+
+    #[test]
+    fn test_locked_mode_requires_lockfile() {
+        let temp_dir = tempdir().unwrap();
+        let env = RequiredEnv {
+            name: "fastqc".to_string(),
+            source: EnvSource::Tool {
+                tool: "fastqc".to_string(),
+                package: "fastqc".to_string(),
+            },
+            steps: vec!["qc".to_string()],
+        };
+
+        let options = EnvOptions::default().with_locked(true);
+        assert!(env.prepare(None, &options).is_err());
+
+        let options = options.with_lock_dir(temp_dir.path());
+        let error = env.prepare(None, &options).unwrap_err().to_string();
+        assert!(error.contains("fastqc.txt"), "{}", error);
+    }
+
+    #[test]
+    fn test_local_channel_becomes_file_url() {
+        let temp_dir = tempdir().unwrap();
+        let channel = temp_dir.path().canonicalize().unwrap();
+
+        let options = EnvOptions::default()
+            .with_channel(temp_dir.path().to_string_lossy())
+            .with_channel("file:///data/channel")
+            .with_channel("conda-forge");
+
+        assert_eq!(
+            options.channels,
+            vec![
+                format!("file://{}", channel.display()),
+                "file:///data/channel".to_string(),
+                "conda-forge".to_string(),
+            ]
+        );
+    }
+}
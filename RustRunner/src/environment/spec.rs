@@ -0,0 +1,257 @@
+//! Declarative Conda Environment Specs
+//!
+//! A step can describe the environment it needs instead of relying on an
+//! environment named after its tool. This covers tools whose package has a
+//! different name (`bowtie2-build` ships in `bowtie2`), steps that pipe
+//! several tools together and pinned versions.
+//!
+//! # Example YAML Format
+//!
+//! ```yaml
+//! steps:
+//!   - id: align
+//!     tool: bwa
+//!     command: bwa mem ref.fa {input} | samtools sort -o {output}
+//!     conda:
+//!       packages: [bwa=0.7.17, samtools>=1.17]
+//!       channels: [conda-forge, bioconda]
+//!
+//!   - id: call
+//!     tool: bcftools
+//!     command: bcftools call -mv {input} -o {output}
+//!     conda: envs/calling.yml
+//! ```
+//!
+//! The environment's name is derived from a hash of the spec (`rr-<hash>`),
+//! so steps with identical specs share one environment and changing a spec
+//! creates a fresh one rather than mutating an environment in use.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::workflow::fingerprint::fingerprint;
+
+/// Channels used when a spec names none.
+pub const DEFAULT_CHANNELS: &[&str] = &["bioconda", "conda-forge"];
+
+/// Prefix of environment names derived from specs.
+pub const SPEC_ENV_PREFIX: &str = "rr-";
+
+/// The conda environment a step runs in.
+///
+/// Either lists `packages` (with optional `channels`) or points at an
+/// `environment.yml` through `file`. In YAML a bare string is read as
+/// `file`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(from = "RawCondaSpec")]
+pub struct CondaSpec {
+    /// Package specs, e.g. `samtools=1.17` or `bowtie2>=2.5`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<String>,
+
+    /// Channels in priority order (default: bioconda, conda-forge)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<String>,
+
+    /// Path of an environment YAML file, relative to the working directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+}
+
+/// Accepted YAML forms of a [`CondaSpec`].
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawCondaSpec {
+    File(PathBuf),
+    Spec {
+        #[serde(default)]
+        packages: Vec<String>,
+        #[serde(default)]
+        channels: Vec<String>,
+        #[serde(default)]
+        file: Option<PathBuf>,
+    },
+}
+
+impl From<RawCondaSpec> for CondaSpec {
+    fn from(raw: RawCondaSpec) -> Self {
+        match raw {
+            RawCondaSpec::File(file) => Self::from_file(file),
+            RawCondaSpec::Spec {
+                packages,
+                channels,
+                file,
+            } => Self {
+                packages,
+                channels,
+                file,
+            },
+        }
+    }
+}
+
+impl CondaSpec {
+    /// Creates a spec installing the given packages.
+    pub fn with_packages<I, S>(packages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            packages: packages.into_iter().map(Into::into).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a spec from an environment YAML file.
+    pub fn from_file(file: impl Into<PathBuf>) -> Self {
+        Self {
+            file: Some(file.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Adds a channel.
+    pub fn with_channel(mut self, channel: impl Into<String>) -> Self {
+        self.channels.push(channel.into());
+        self
+    }
+
+    /// Checks that the spec names either packages or a file.
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.file, self.packages.is_empty()) {
+            (Some(_), false) => Err("give either packages or an environment file, not both".into()),
+            (Some(_), true) if !self.channels.is_empty() => {
+                Err("channels belong in the environment file".into())
+            }
+            (None, true) => Err("lists no packages".into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the channels to install from.
+    pub fn channels(&self) -> Vec<String> {
+        if self.channels.is_empty() {
+            DEFAULT_CHANNELS.iter().map(|c| c.to_string()).collect()
+        } else {
+            self.channels.clone()
+        }
+    }
+
+    /// Returns the environment file resolved against a base directory.
+    pub fn file_path(&self, base_dir: Option<&Path>) -> Option<PathBuf> {
+        self.file.as_ref().map(|file| match base_dir {
+            Some(dir) if file.is_relative() => dir.join(file),
+            _ => file.clone(),
+        })
+    }
+
+    /// Returns a hash identifying the environment this spec describes.
+    ///
+    /// Package order doesn't matter; channel order does, since it sets
+    /// priority. For file specs the file's contents are hashed, so editing
+    /// the file yields a new environment.
+    pub fn hash(&self, base_dir: Option<&Path>) -> Result<String, Box<dyn Error + Send + Sync>> {
+        if let Some(path) = self.file_path(base_dir) {
+            let content = fs::read_to_string(&path).map_err(|e| {
+                format!("Failed to read environment file '{}': {}", path.display(), e)
+            })?;
+            return Ok(fingerprint(["file", content.as_str()]));
+        }
+
+        let mut packages: Vec<&str> = self.packages.iter().map(|p| p.trim()).collect();
+        packages.sort_unstable();
+        packages.dedup();
+
+        let channels = self.channels();
+        let parts = std::iter::once("packages")
+            .chain(packages)
+            .chain(std::iter::once("channels"))
+            .chain(channels.iter().map(String::as_str));
+
+        Ok(fingerprint(parts))
+    }
+
+    /// Returns the name of the environment for this spec.
+    pub fn env_name(&self, base_dir: Option<&Path>) -> Result<String, Box<dyn Error + Send + Sync>> {
+        Ok(format!("{}{}", SPEC_ENV_PREFIX, self.hash(base_dir)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_yaml_forms() {
+        let spec: CondaSpec =
+            serde_yaml::from_str("packages: [bwa=0.7.17, samtools]\nchannels: [bioconda]\n")
+                .unwrap();
+        assert_eq!(spec.packages, vec!["bwa=0.7.17", "samtools"]);
+        assert_eq!(spec.channels(), vec!["bioconda"]);
+        assert!(spec.validate().is_ok());
+
+        let spec: CondaSpec = serde_yaml::from_str("envs/align.yml").unwrap();
+        assert_eq!(spec, CondaSpec::from_file("envs/align.yml"));
+
+        let spec: CondaSpec = serde_yaml::from_str("file: envs/align.yml").unwrap();
+        assert_eq!(spec.file, Some(PathBuf::from("envs/align.yml")));
+    }
+
+    #[test]
+    fn test_identical_specs_share_env() {
+        let a = CondaSpec::with_packages(["samtools=1.17", "bwa"]);
+        let b = CondaSpec::with_packages(["bwa", "samtools=1.17"])
+            .with_channel("bioconda")
+            .with_channel("conda-forge");
+
+        assert_eq!(a.env_name(None).unwrap(), b.env_name(None).unwrap());
+        assert!(a.env_name(None).unwrap().starts_with(SPEC_ENV_PREFIX));
+    }
+
+    #[test]
+    fn test_different_specs_get_different_envs() {
+        let base = CondaSpec::with_packages(["samtools=1.17"]);
+        let pinned = CondaSpec::with_packages(["samtools=1.18"]);
+        let reordered = CondaSpec::with_packages(["samtools=1.17"])
+            .with_channel("conda-forge")
+            .with_channel("bioconda");
+
+        assert_ne!(base.hash(None).unwrap(), pinned.hash(None).unwrap());
+        assert_ne!(base.hash(None).unwrap(), reordered.hash(None).unwrap());
+    }
+
+    #[test]
+    fn test_file_spec_hashes_contents() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("env.yml");
+        fs::write(&file, "dependencies:\n  - samtools=1.17\n").unwrap();
+
+        let spec = CondaSpec::from_file("env.yml");
+        let first = spec.hash(Some(temp_dir.path())).unwrap();
+        assert_eq!(first, spec.hash(Some(temp_dir.path())).unwrap());
+
+        fs::write(&file, "dependencies:\n  - samtools=1.18\n").unwrap();
+        assert_ne!(first, spec.hash(Some(temp_dir.path())).unwrap());
+
+        assert!(CondaSpec::from_file("missing.yml").hash(Some(temp_dir.path())).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(CondaSpec::default().validate().is_err());
+        assert!(CondaSpec::from_file("env.yml").validate().is_ok());
+        assert!(CondaSpec::from_file("env.yml").with_channel("bioconda").validate().is_err());
+
+        let both = CondaSpec {
+            packages: vec!["bwa".to_string()],
+            file: Some(PathBuf::from("env.yml")),
+            ..CondaSpec::default()
+        };
+        assert!(both.validate().is_err());
+    }
+}
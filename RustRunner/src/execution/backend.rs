@@ -0,0 +1,236 @@
+//! Step Backends
+//!
+//! A backend decides what a step's generated script runs inside:
+//!
+//! - [`Backend::Host`]: the interpreter on the host, for system tools
+//! - [`Backend::Conda`]: `micromamba run` in the step's conda environment
+//! - [`Backend::Container`]: a container image via podman, docker or
+//!   apptainer, for steps with a `container:` image
+//!
+//! [`execute_step`](super::step::execute_step) resolves the backend of a
+//! step, asks it for the command launching the script and then runs that
+//! command the same way for every backend.
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use log::debug;
+use tokio::process::Command;
+
+use crate::environment::conda::{MAMBA_ROOT_PREFIX, MICROMAMBA_PATH};
+use crate::workflow::{ShellConfig, Step};
+
+use super::container::{container_name, ContainerGuard, ContainerRuntime};
+use super::step::StepContext;
+
+/// A generated script ready to be launched.
+#[derive(Debug, Clone)]
+pub struct ScriptRun<'a> {
+    /// ID of the step
+    pub step_id: &'a str,
+    /// Interpreter settings of the script
+    pub shell: &'a ShellConfig,
+    /// The generated script
+    pub script_path: &'a Path,
+    /// Directory holding the run's scripts
+    pub script_dir: &'a Path,
+    /// Directory the step runs in, if not the current one
+    pub working_dir: Option<&'a Path>,
+    /// Environment variables for the step
+    pub env: BTreeMap<String, String>,
+    /// The step's input and output paths
+    pub files: Vec<String>,
+}
+
+impl ScriptRun<'_> {
+    /// Returns the working directory as an absolute path.
+    pub fn absolute_working_dir(&self) -> PathBuf {
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        match self.working_dir {
+            Some(dir) => cwd.join(dir),
+            None => cwd,
+        }
+    }
+}
+
+/// What a step's script runs inside.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backend {
+    /// The interpreter on the host
+    Host,
+    /// A conda environment
+    Conda {
+        /// Name of the environment
+        env_name: String,
+    },
+    /// A container image
+    Container {
+        /// Engine running the image
+        runtime: ContainerRuntime,
+        /// Image reference, e.g. `quay.io/biocontainers/samtools:1.17--h00cdaf9_0`
+        image: String,
+    },
+}
+
+impl Backend {
+    /// Picks the backend for a step.
+    ///
+    /// A `container:` image wins, then a `conda:` spec; system tools run
+    /// on the host and any other tool in its mapped conda environment.
+    pub fn resolve(step: &Step, context: &StepContext) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        if let Some(ref image) = step.container {
+            let runtime = match context.container_runtime {
+                Some(ref runtime) => runtime.clone(),
+                None => ContainerRuntime::detect().ok_or(
+                    "No container runtime found on PATH (looked for podman, docker and apptainer)",
+                )?,
+            };
+            return Ok(Self::Container {
+                runtime,
+                image: image.clone(),
+            });
+        }
+
+        if let Some(ref spec) = step.conda {
+            let env_name = spec.env_name(context.working_dir.as_deref())?;
+            return Ok(Self::Conda { env_name });
+        }
+
+        if context.tool_registry.is_system_tool(&step.tool) {
+            return Ok(Self::Host);
+        }
+
+        let env_name = tool_env_name(&step.tool, &context.tool_env_map)?;
+        Ok(Self::Conda {
+            env_name: env_name.to_string(),
+        })
+    }
+
+    /// Builds the command launching a script.
+    ///
+    /// For docker and podman a guard is returned as well, which removes the
+    /// container if it is dropped before being disarmed.
+    pub fn command(&self, run: &ScriptRun) -> (Command, Option<ContainerGuard>) {
+        let mut cmd = match self {
+            Self::Host => {
+                let argv = run.shell.interpreter_argv();
+                let mut cmd = Command::new(&argv[0]);
+                cmd.args(&argv[1..]).arg(run.script_path);
+                cmd
+            }
+            Self::Conda { env_name } => {
+                let mut cmd = Command::new(&*MICROMAMBA_PATH);
+                cmd.env("MAMBA_ROOT_PREFIX", &*MAMBA_ROOT_PREFIX);
+                cmd.arg("run")
+                    .arg("-n")
+                    .arg(env_name)
+                    .args(run.shell.interpreter_argv())
+                    .arg(run.script_path);
+                cmd
+            }
+            Self::Container { runtime, image } => {
+                let name = container_name(run.step_id);
+                let cmd = runtime.command(image, &name, run);
+                return (cmd, runtime.guard(&name));
+            }
+        };
+
+        cmd.envs(&run.env);
+        if let Some(dir) = run.working_dir {
+            cmd.current_dir(dir);
+            debug!("Executing in directory: {} ({})", dir.display(), self);
+        }
+
+        (cmd, None)
+    }
+}
+
+impl std::fmt::Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Host => write!(f, "host"),
+            Self::Conda { env_name } => write!(f, "conda env: {}", env_name),
+            Self::Container { runtime, image } => {
+                write!(f, "{} image: {}", runtime.engine, image)
+            }
+        }
+    }
+}
+
+/// Looks up the conda environment configured for a tool.
+fn tool_env_name<'a>(
+    tool: &str,
+    tool_env_map: &'a HashMap<String, String>,
+) -> Result<&'a str, Box<dyn Error + Send + Sync>> {
+    tool_env_map.get(tool).map(String::as_str).ok_or_else(|| {
+        format!(
+            "No conda environment configured for tool '{}'. \
+             Create one with: micromamba create -n {} {} -c bioconda -c conda-forge, \
+             or give the step a conda spec",
+            tool, tool, tool
+        )
+        .into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::environment::CondaSpec;
+    use crate::execution::container::ContainerEngine;
+
+    #[test]
+    fn test_resolve_backend() {
+        let context = StepContext::new("/s")
+            .with_tool_env_map(HashMap::from([("bwa".to_string(), "align".to_string())]))
+            .with_container_runtime(Some(ContainerRuntime::new(ContainerEngine::Docker)));
+
+        let host = Step::new("a", "cat", "cat in");
+        assert_eq!(Backend::resolve(&host, &context).unwrap(), Backend::Host);
+
+        let mapped = Step::new("b", "bwa", "bwa mem");
+        assert_eq!(
+            Backend::resolve(&mapped, &context).unwrap(),
+            Backend::Conda { env_name: "align".to_string() }
+        );
+
+        let spec = Step::new("c", "cat", "cat in").with_conda(CondaSpec::with_packages(["coreutils"]));
+        assert!(matches!(
+            Backend::resolve(&spec, &context).unwrap(),
+            Backend::Conda { env_name } if env_name.starts_with("rr-")
+        ));
+
+        let image = Step::new("d", "samtools", "samtools --version").with_container("biocontainers/samtools:1.17");
+        assert_eq!(
+            Backend::resolve(&image, &context).unwrap().to_string(),
+            "docker image: biocontainers/samtools:1.17"
+        );
+
+        let unmapped = Step::new("e", "fastqc", "fastqc in");
+        let error = Backend::resolve(&unmapped, &context).unwrap_err().to_string();
+        assert!(error.contains("No conda environment configured for tool 'fastqc'"));
+    }
+
+    #[test]
+    fn test_host_command() {
+        let shell = ShellConfig::with_interpreter("python3 -u");
+        let run = ScriptRun {
+            step_id: "a",
+            shell: &shell,
+            script_path: Path::new("/s/a.py"),
+            script_dir: Path::new("/s"),
+            working_dir: Some(Path::new("/data")),
+            env: BTreeMap::from([("KEY".to_string(), "value".to_string())]),
+            files: Vec::new(),
+        };
+
+        let (cmd, guard) = Backend::Host.command(&run);
+        let cmd = cmd.as_std();
+        assert!(guard.is_none());
+        assert_eq!(cmd.get_program(), "python3");
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["-u", "/s/a.py"]);
+        assert_eq!(cmd.get_current_dir(), Some(Path::new("/data")));
+        assert_eq!(cmd.get_envs().count(), 1);
+    }
+}
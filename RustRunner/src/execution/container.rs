@@ -0,0 +1,403 @@
+//! Container Runtimes
+//!
+//! Runs a step's script inside a container image with podman, docker or
+//! apptainer. Many BioContainers tools are easier to pin as an image
+//! (`quay.io/biocontainers/samtools:1.17--h00cdaf9_0`) than as conda
+//! packages.
+//!
+//! The container sees the host's files at the same paths: the working
+//! directory, the run's script directory and the directories of any
+//! absolute input or output paths are bind-mounted in place, and the step
+//! runs as the invoking user so that outputs are not owned by root.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+use tokio::process::Command;
+
+use crate::environment::detect::find_in_path;
+
+use super::backend::ScriptRun;
+
+/// Container engine CLIs, in the order they are looked for on PATH.
+const ENGINES: &[ContainerEngine] = &[
+    ContainerEngine::Podman,
+    ContainerEngine::Docker,
+    ContainerEngine::Apptainer,
+];
+
+/// User and group ID of the current user, used to run containers as them.
+static CURRENT_IDS: Lazy<Option<(u32, u32)>> = Lazy::new(|| {
+    let id = |flag: &str| -> Option<u32> {
+        let output = std::process::Command::new("id").arg(flag).output().ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    };
+    Some((id("-u")?, id("-g")?))
+});
+
+/// A container engine CLI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContainerEngine {
+    /// `podman run`, rootless with the user namespace kept
+    Podman,
+    /// `docker run --user UID:GID`
+    Docker,
+    /// `apptainer exec`, which runs as the invoking user by design
+    Apptainer,
+}
+
+impl ContainerEngine {
+    /// Returns the engine's default program name.
+    pub fn program(self) -> &'static str {
+        match self {
+            Self::Podman => "podman",
+            Self::Docker => "docker",
+            Self::Apptainer => "apptainer",
+        }
+    }
+}
+
+impl FromStr for ContainerEngine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "podman" => Ok(Self::Podman),
+            "docker" => Ok(Self::Docker),
+            "apptainer" | "singularity" => Ok(Self::Apptainer),
+            other => Err(format!(
+                "Unknown container runtime '{}' (expected podman, docker or apptainer)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ContainerEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.program())
+    }
+}
+
+/// A container engine and the program used to invoke it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerRuntime {
+    /// Which engine's command line to speak
+    pub engine: ContainerEngine,
+
+    /// Program to run, e.g. `docker` or `/opt/apptainer/bin/apptainer`
+    pub program: PathBuf,
+}
+
+impl ContainerRuntime {
+    /// Creates a runtime invoking the engine's default program.
+    pub fn new(engine: ContainerEngine) -> Self {
+        Self {
+            engine,
+            program: PathBuf::from(engine.program()),
+        }
+    }
+
+    /// Uses a specific program, e.g. a wrapper script.
+    pub fn with_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    /// Parses a runtime name (`podman`, `docker`, `apptainer`,
+    /// `singularity`) or a path to one of those programs.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let name = Path::new(value)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let engine = name.parse()?;
+        Ok(Self::new(engine).with_program(value))
+    }
+
+    /// Finds the first container engine installed on PATH.
+    pub fn detect() -> Option<Self> {
+        ENGINES.iter().find_map(|&engine| {
+            let program = find_in_path(engine.program())?;
+            debug!("Using container runtime: {}", program.display());
+            Some(Self::new(engine).with_program(program))
+        })
+    }
+
+    /// Builds a command that runs a script inside a container image.
+    ///
+    /// `name` names docker and podman containers so that they can be
+    /// removed if the step is cancelled. The step's environment variables
+    /// are passed into the container.
+    pub fn command(&self, image: &str, name: &str, run: &ScriptRun) -> Command {
+        let working_dir = run.absolute_working_dir();
+        let mounts = bind_mounts(&working_dir, run.script_dir, &run.files);
+        let mut cmd = Command::new(&self.program);
+
+        match self.engine {
+            ContainerEngine::Podman | ContainerEngine::Docker => {
+                cmd.arg("run").arg("--rm").arg("--name").arg(name);
+                cmd.arg("--entrypoint").arg("");
+                match (self.engine, *CURRENT_IDS) {
+                    (ContainerEngine::Podman, Some((uid, _))) if uid != 0 => {
+                        cmd.arg("--userns=keep-id");
+                    }
+                    (ContainerEngine::Docker, Some((uid, gid))) => {
+                        cmd.arg("--user").arg(format!("{}:{}", uid, gid));
+                    }
+                    _ => {}
+                }
+                for mount in mounts {
+                    cmd.arg("-v").arg(format!("{0}:{0}", mount.display()));
+                }
+                cmd.arg("-w").arg(&working_dir);
+                for (key, value) in &run.env {
+                    cmd.arg("-e").arg(format!("{}={}", key, value));
+                }
+                cmd.arg(image);
+            }
+            ContainerEngine::Apptainer => {
+                cmd.arg("exec");
+                if !mounts.is_empty() {
+                    let binds: Vec<String> =
+                        mounts.iter().map(|m| m.display().to_string()).collect();
+                    cmd.arg("--bind").arg(binds.join(","));
+                }
+                cmd.arg("--pwd").arg(&working_dir);
+                for (key, value) in &run.env {
+                    cmd.arg("--env").arg(format!("{}={}", key, value));
+                }
+                cmd.arg(apptainer_image(image));
+            }
+        }
+
+        cmd.args(run.shell.interpreter_argv()).arg(run.script_path);
+        cmd.current_dir(&working_dir);
+        cmd
+    }
+
+    /// Returns a guard removing a named container if the step doesn't
+    /// finish normally.
+    ///
+    /// Killing the `docker`/`podman` client leaves the container running,
+    /// so a cancelled or timed-out step has to remove it explicitly.
+    /// Apptainer containers are ordinary child processes and die with the
+    /// step's process group.
+    pub fn guard(&self, name: &str) -> Option<ContainerGuard> {
+        match self.engine {
+            ContainerEngine::Podman | ContainerEngine::Docker => Some(ContainerGuard {
+                program: self.program.clone(),
+                name: Some(name.to_string()),
+            }),
+            ContainerEngine::Apptainer => None,
+        }
+    }
+}
+
+impl fmt::Display for ContainerRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.engine, self.program.display())
+    }
+}
+
+/// Force-removes a docker or podman container when dropped.
+#[derive(Debug)]
+pub struct ContainerGuard {
+    program: PathBuf,
+    name: Option<String>,
+}
+
+impl ContainerGuard {
+    /// Leaves the container alone on drop (it exited and removed itself).
+    pub fn disarm(&mut self) {
+        self.name = None;
+    }
+}
+
+impl Drop for ContainerGuard {
+    fn drop(&mut self) {
+        if let Some(name) = self.name.take() {
+            debug!("Removing container {}", name);
+            let result = std::process::Command::new(&self.program)
+                .arg("rm")
+                .arg("-f")
+                .arg(&name)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+
+            if let Err(e) = result {
+                warn!("Failed to remove container {}: {}", name, e);
+            }
+        }
+    }
+}
+
+/// Returns a unique container name for a step.
+pub fn container_name(step_id: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or_default();
+    let step: String = step_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+
+    format!("rustrunner-{}-{}-{:x}", step, std::process::id(), nanos)
+}
+
+/// Returns the directories to bind-mount for a step.
+///
+/// Always includes the working and script directories; the parent
+/// directories of input and output paths outside them are added, and
+/// directories nested in another mount are dropped.
+pub fn bind_mounts(working_dir: &Path, script_dir: &Path, files: &[String]) -> Vec<PathBuf> {
+    let mut dirs = vec![working_dir.to_path_buf(), script_dir.to_path_buf()];
+
+    for file in files {
+        let path = working_dir.join(file);
+        if let Some(parent) = path.parent().filter(|p| p.is_dir()) {
+            dirs.push(parent.canonicalize().unwrap_or_else(|_| parent.to_path_buf()));
+        }
+    }
+
+    dirs.sort();
+    dirs.dedup();
+
+    let mut mounts: Vec<PathBuf> = Vec::new();
+    for dir in dirs {
+        if !mounts.iter().any(|mount| dir.starts_with(mount)) {
+            mounts.push(dir);
+        }
+    }
+    mounts
+}
+
+/// Returns an image reference apptainer understands.
+///
+/// Registry references like `quay.io/biocontainers/samtools:1.17` are
+/// pulled through `docker://`; local `.sif` files and explicit URIs are
+/// passed through.
+fn apptainer_image(image: &str) -> String {
+    if image.contains("://") || image.ends_with(".sif") || Path::new(image).is_file() {
+        image.to_string()
+    } else {
+        format!("docker://{}", image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::ShellConfig;
+    use std::collections::BTreeMap;
+
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.as_std()
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_runtime() {
+        let runtime = ContainerRuntime::parse("/opt/bin/singularity").unwrap();
+        assert_eq!(runtime.engine, ContainerEngine::Apptainer);
+        assert_eq!(runtime.program, PathBuf::from("/opt/bin/singularity"));
+
+        assert_eq!(ContainerRuntime::parse("docker").unwrap(), ContainerRuntime::new(ContainerEngine::Docker));
+        assert!(ContainerRuntime::parse("lxc").is_err());
+    }
+
+    fn script_run<'a>(shell: &'a ShellConfig, script_path: &'a Path) -> ScriptRun<'a> {
+        ScriptRun {
+            step_id: "a",
+            shell,
+            script_path,
+            script_dir: Path::new("/s"),
+            working_dir: Some(Path::new("/data")),
+            env: BTreeMap::new(),
+            files: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_docker_command() {
+        let runtime = ContainerRuntime::new(ContainerEngine::Docker);
+        let shell = ShellConfig::default();
+        let mut run = script_run(&shell, Path::new("/s/a.sh"));
+        run.env.insert("RUSTRUNNER_STEP_JSON".to_string(), "/s/a.step.json".to_string());
+
+        let cmd = runtime.command("quay.io/biocontainers/samtools:1.17", "rustrunner-a", &run);
+        let args = args(&cmd);
+
+        assert_eq!(&args[..6], ["run", "--rm", "--name", "rustrunner-a", "--entrypoint", ""]);
+        if let Some((uid, gid)) = *CURRENT_IDS {
+            assert!(args.windows(2).any(|w| w == ["--user".to_string(), format!("{}:{}", uid, gid)]));
+        }
+        assert!(args.windows(2).any(|w| w == ["-v", "/data:/data"]));
+        assert!(args.windows(2).any(|w| w == ["-v", "/s:/s"]));
+        assert!(args.windows(2).any(|w| w == ["-w", "/data"]));
+        assert!(args.windows(2).any(|w| w == ["-e", "RUSTRUNNER_STEP_JSON=/s/a.step.json"]));
+        assert!(args.ends_with(&[
+            "quay.io/biocontainers/samtools:1.17".to_string(),
+            "bash".to_string(),
+            "/s/a.sh".to_string()
+        ]));
+    }
+
+    #[test]
+    fn test_apptainer_command() {
+        let runtime = ContainerRuntime::new(ContainerEngine::Apptainer);
+        let shell = ShellConfig::with_interpreter("python3");
+        let run = script_run(&shell, Path::new("/s/a.py"));
+        let cmd = runtime.command("quay.io/biocontainers/samtools:1.17", "unused", &run);
+
+        assert_eq!(
+            args(&cmd),
+            vec![
+                "exec", "--bind", "/data,/s", "--pwd", "/data",
+                "docker://quay.io/biocontainers/samtools:1.17", "python3", "/s/a.py"
+            ]
+        );
+        assert!(runtime.guard("unused").is_none());
+    }
+
+    #[test]
+    fn test_bind_mounts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let work = root.join("work");
+        let refs = root.join("refs");
+        std::fs::create_dir_all(work.join("out")).unwrap();
+        std::fs::create_dir_all(&refs).unwrap();
+
+        let files = vec![
+            "out/a.bam".to_string(),
+            refs.join("ref.fa").to_string_lossy().into_owned(),
+            "/nonexistent/dir/x".to_string(),
+        ];
+        let mounts = bind_mounts(&work, &work.join("scripts"), &files);
+
+        assert_eq!(mounts, vec![refs, work]);
+    }
+
+    #[test]
+    fn test_apptainer_image() {
+        assert_eq!(apptainer_image("ubuntu:22.04"), "docker://ubuntu:22.04");
+        assert_eq!(apptainer_image("/images/tool.sif"), "/images/tool.sif");
+        assert_eq!(apptainer_image("oras://ghcr.io/x/y:1"), "oras://ghcr.io/x/y:1");
+    }
+
+    #[test]
+    fn test_container_name() {
+        let name = container_name("align sample/1");
+        assert!(name.starts_with("rustrunner-align_sample_1-"));
+    }
+}
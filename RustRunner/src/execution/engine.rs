@@ -3,25 +3,50 @@
 //! The core engine that orchestrates workflow execution including:
 //! - Parallel step scheduling with dependency resolution
 //! - Resource monitoring
-//! - Pause/resume functionality via file-based signaling
+//! - Pause/resume functionality via file-based signaling or control messages
+//! - Cancellation and per-step timeouts
 //! - State persistence for crash recovery
 //! - Automatic conda environment setup for tools
+//! - Container images for steps that declare one
+//! - Pluggable executors: local processes, SLURM batch jobs or SSH
+//! - Progress events delivered to registered [`EngineObserver`]s
+//!
+//! The engine runs on tokio: every step is a `tokio::process` child owned by
+//! a task in a [`JoinSet`], and step completion, resource sampling, pause
+//! polling and control messages are all handled in a single `select!` loop.
 
-use std::collections::{HashMap,HashSet};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
-use std::thread;
 use std::time::{Duration, Instant};
 
+use chrono::Utc;
 use log::{error, info, warn};
-
-use crate::environment::conda::{create_env, ToolEnvMap};
-use crate::monitoring::{EventType, ExecutionTimeline, ResourceMonitor};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinSet;
+use tokio::time::{interval, MissedTickBehavior};
+
+use crate::environment::conda::{env_prefix, ToolEnvMap};
+use crate::environment::detect::{resolve_system_tools, usable_system_tool, SystemTool, ToolPolicy};
+use crate::environment::doctor::{check_package, check_tool_env, preflight};
+use crate::environment::lock::lock_dir;
+use crate::environment::registry::{ToolInfo, ToolMode, ToolRegistry, TOOL_REGISTRY};
+use crate::environment::setup::{required_environments, EnvOptions, EnvSource, RequiredEnv};
+use crate::monitoring::{
+    EventType, ExecutionTimeline, ResourceMonitor, RunHistory, RunRecord, RunReport,
+};
+use crate::workflow::planner::StepStatus;
 use crate::workflow::{ExecutionPlanner, Workflow, WorkflowState};
 
-use super::step::execute_step;
+use super::container::ContainerRuntime;
+use super::error::StepError;
+use super::events::{format_eta, ChannelObserver, EngineEvent, EngineObserver};
+use super::executor::Executor;
+use super::local::LocalExecutor;
+use super::remote::SshExecutor;
+use super::scripts;
+use super::step::{execute_step, StepContext, StepOutput};
 
 /// Interval for checking the pause flag file.
 const PAUSE_CHECK_INTERVAL: Duration = Duration::from_millis(500);
@@ -29,8 +54,53 @@ const PAUSE_CHECK_INTERVAL: Duration = Duration::from_millis(500);
 /// Interval for resource monitoring samples.
 const MONITOR_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
 
-/// System tools that don't require conda environments
-const SYSTEM_TOOLS: &[&str] = &["bash", "sh", "echo", "cat", "cp", "mv", "rm", "mkdir", "sleep", "curl", "wget", "grep", "awk", "sed", "sort", "uniq", "head", "tail", "wc", "tr", "cut", "bc", "gzip", "gunzip", "tar", "zip", "unzip"];
+/// Interval between progress/ETA updates while steps are running.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Result of a finished step task: the step ID and its outcome.
+type StepResult = (String, Result<StepOutput, StepError>);
+
+/// Control messages that can be sent to a running engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineControl {
+    /// Stop launching new steps; running steps continue
+    Pause,
+    /// Resume launching steps after a pause
+    Resume,
+    /// Kill all running steps and stop the workflow
+    Cancel,
+}
+
+/// Cloneable handle for controlling a running engine from another task or thread.
+///
+/// Obtained with [`Engine::handle`] before the engine is started.
+#[derive(Debug, Clone)]
+pub struct EngineHandle {
+    sender: UnboundedSender<EngineControl>,
+}
+
+impl EngineHandle {
+    /// Pauses scheduling of new steps.
+    pub fn pause(&self) {
+        self.send(EngineControl::Pause);
+    }
+
+    /// Resumes scheduling after a pause.
+    pub fn resume(&self) {
+        self.send(EngineControl::Resume);
+    }
+
+    /// Cancels the workflow, killing all running steps.
+    pub fn cancel(&self) {
+        self.send(EngineControl::Cancel);
+    }
+
+    /// Sends a control message to the engine.
+    pub fn send(&self, control: EngineControl) {
+        // The engine may already have finished; nothing to control then
+        let _ = self.sender.send(control);
+    }
+}
 
 /// Workflow execution engine.
 ///
@@ -58,22 +128,57 @@ pub struct Engine {
     workflow_path: String,
     max_parallel: usize,
     dry_run: bool,
+    keep_scripts: bool,
+    runs_dir: PathBuf,
+    env_options: EnvOptions,
+    container_runtime: Option<ContainerRuntime>,
+    tool_registry: Arc<ToolRegistry>,
+    tool_policy: ToolPolicy,
+    /// Conda tools running from PATH in this run
+    system_tools: BTreeMap<String, SystemTool>,
+    executor: Arc<dyn Executor>,
     pause_flag_path: Option<String>,
     working_dir: Option<PathBuf>,
-    wildcard_files: Option<HashMap<String, Vec<String>>>
+    wildcard_files: Option<HashMap<String, Vec<String>>>,
+    control_tx: UnboundedSender<EngineControl>,
+    control_rx: UnboundedReceiver<EngineControl>,
+    observers: Vec<Box<dyn EngineObserver>>,
+    report: Option<RunReport>,
+    timeline: Option<ExecutionTimeline>,
+    history: Option<RunHistory>,
 }
 
 impl Engine {
     /// Creates a new execution engine for a workflow.
     pub fn new(workflow: Workflow) -> Self {
+        let (control_tx, control_rx) = unbounded_channel();
+        let executor: Arc<dyn Executor> = match workflow.remote {
+            Some(ref remote) => Arc::new(SshExecutor::new(remote.clone())),
+            None => Arc::new(LocalExecutor::new()),
+        };
+
         Self {
             workflow,
             workflow_path: String::new(),
             max_parallel: 4,
             dry_run: false,
+            keep_scripts: false,
+            runs_dir: PathBuf::from(scripts::RUNS_DIR),
+            env_options: EnvOptions::default(),
+            container_runtime: None,
+            tool_registry: Arc::new(TOOL_REGISTRY.clone()),
+            tool_policy: ToolPolicy::default(),
+            system_tools: BTreeMap::new(),
+            executor,
             pause_flag_path: None,
             working_dir: None,
-            wildcard_files: None
+            wildcard_files: None,
+            control_tx,
+            control_rx,
+            observers: Vec::new(),
+            report: None,
+            timeline: None,
+            history: Some(RunHistory::default()),
         }
     }
 
@@ -96,6 +201,67 @@ impl Engine {
         self.dry_run = dry_run;
     }
 
+    /// Keeps the scripts of successful steps in the run directory.
+    ///
+    /// Scripts of failed steps are always kept under
+    /// `<runs dir>/<run-id>/scripts/`.
+    pub fn set_keep_scripts(&mut self, keep_scripts: bool) {
+        self.keep_scripts = keep_scripts;
+    }
+
+    /// Sets the directory that holds per-run script directories
+    /// (default: `.rustrunner/runs`).
+    pub fn set_runs_dir(&mut self, runs_dir: impl Into<PathBuf>) {
+        self.runs_dir = runs_dir.into();
+    }
+
+    /// Sets how conda environments are created.
+    ///
+    /// Lockfiles default to the `<workflow>.lock/` directory next to the
+    /// workflow file.
+    pub fn set_env_options(&mut self, options: EnvOptions) {
+        self.env_options = options;
+    }
+
+    /// Sets the registry deciding which tools run from PATH and which
+    /// conda packages provide the others.
+    ///
+    /// Defaults to the built-in registry extended with the user's.
+    pub fn set_tool_registry(&mut self, registry: ToolRegistry) {
+        self.tool_registry = Arc::new(registry);
+    }
+
+    /// Sets whether conda tools already installed on PATH are used instead
+    /// of creating their environments (default: `prefer-conda`).
+    pub fn set_tool_policy(&mut self, policy: ToolPolicy) {
+        self.tool_policy = policy;
+    }
+
+    /// Sets the runtime for steps with a container image.
+    ///
+    /// Without one, podman, docker or apptainer is looked up on PATH.
+    pub fn set_container_runtime(&mut self, runtime: ContainerRuntime) {
+        self.container_runtime = Some(runtime);
+    }
+
+    /// Sets the executor steps are launched through.
+    ///
+    /// Steps run as local processes by default, or over SSH if the
+    /// workflow has a `remote:` section.
+    pub fn set_executor(&mut self, executor: impl Executor + 'static) {
+        self.executor = Arc::new(executor);
+    }
+
+    /// Returns the conda environments the workflow's steps run in.
+    pub fn environments(&self) -> Result<Vec<RequiredEnv>, String> {
+        required_environments(
+            &self.workflow,
+            self.working_dir.as_deref(),
+            &ToolEnvMap::load(),
+            &self.tool_registry,
+        )
+    }
+
     /// Sets the path for pause/resume signaling.
     pub fn set_pause_flag_path(&mut self, path: impl Into<String>) {
         self.pause_flag_path = Some(path.into());
@@ -106,7 +272,61 @@ impl Engine {
         self.working_dir = Some(dir.into());
     }
 
-    /// Executes the workflow.
+    /// Sets the run history used for duration estimates and recording.
+    ///
+    /// Defaults to `.rustrunner/history.jsonl`; `None` disables history.
+    /// Dry runs read the history but are never recorded.
+    pub fn set_history(&mut self, history: Option<RunHistory>) {
+        self.history = history;
+    }
+
+    /// Returns a handle for pausing, resuming or cancelling the run.
+    pub fn handle(&self) -> EngineHandle {
+        EngineHandle {
+            sender: self.control_tx.clone(),
+        }
+    }
+
+    /// Registers an observer that receives every [`EngineEvent`] of the run.
+    pub fn add_observer(&mut self, observer: impl EngineObserver + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Returns a channel receiving every [`EngineEvent`] of the run.
+    pub fn subscribe(&mut self) -> UnboundedReceiver<EngineEvent> {
+        let (tx, rx) = unbounded_channel();
+        self.add_observer(ChannelObserver::new(tx));
+        rx
+    }
+
+    /// Delivers an event to all registered observers.
+    fn emit(&mut self, event: EngineEvent) {
+        for observer in &mut self.observers {
+            observer.on_event(&event);
+        }
+    }
+
+    /// Executes the workflow, blocking the current thread until it finishes.
+    ///
+    /// Creates a tokio runtime and drives [`Engine::run_async`] on it. Must not
+    /// be called from within an existing tokio runtime; use `run_async` there.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(RunReport)` - Workflow completed successfully
+    /// * `Err` - A step failed or an error occurred; the partial report is
+    ///   still available from [`Engine::report`]
+    pub fn run(&mut self) -> Result<RunReport, Box<dyn Error>> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+
+        runtime
+            .block_on(self.run_async())
+            .map_err(|e| e as Box<dyn Error>)
+    }
+
+    /// Executes the workflow asynchronously.
     ///
     /// This is the main entry point that:
     /// 1. Sets up conda environments for required tools
@@ -114,23 +334,154 @@ impl Engine {
     /// 3. Verifies previously completed steps
     /// 4. Executes remaining steps in parallel
     /// 5. Saves state after each step
-    /// 6. Reports final results
+    /// 6. Returns a [`RunReport`] of the run
     ///
-    /// # Returns
+    /// # Example
     ///
-    /// * `Ok(())` - Workflow completed successfully
-    /// * `Err` - A step failed or an error occurred
-    pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    /// ```rust,no_run
+    /// use rustrunner::execution::Engine;
+    /// use rustrunner::load_workflow;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    ///     let workflow = load_workflow("pipeline.yaml").map_err(|e| e.to_string())?;
+    ///     let mut engine = Engine::new(workflow);
+    ///
+    ///     let handle = engine.handle();
+    ///     tokio::spawn(async move {
+    ///         tokio::signal::ctrl_c().await.ok();
+    ///         handle.cancel();
+    ///     });
+    ///
+    ///     let report = engine.run_async().await?;
+    ///     println!("{}", report);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn run_async(&mut self) -> Result<RunReport, Box<dyn Error + Send + Sync>> {
+        let started_at = Utc::now();
         let start_time = Instant::now();
+        let mut monitor = ResourceMonitor::new();
+        let mut timeline = ExecutionTimeline::new();
+        let run_id = RunReport::new_run_id(&started_at);
+
+        let (result, planner) = match self.prepare().await {
+            Ok((mut state, mut planner)) => {
+                let result = self
+                    .execute(&run_id, &mut planner, &mut state, &mut monitor, &mut timeline)
+                    .await;
+                scripts::remove_if_empty(&self.runs_dir, &run_id);
+                (result, Some(planner))
+            }
+            Err(e) => (Err(e), None),
+        };
+
+        let report = RunReport::build(
+            run_id,
+            &self.workflow_path,
+            self.dry_run,
+            started_at,
+            start_time,
+            planner.as_ref(),
+            &monitor,
+            result.as_ref().err().map(|e| e.to_string()),
+        );
+        self.report = Some(report.clone());
+        self.timeline = Some(timeline);
 
+        if let Some(history) = self.history.as_ref().filter(|_| !self.dry_run) {
+            if let Err(e) = history.append(&RunRecord::from_report(&report)) {
+                warn!("Failed to record run history: {}", e);
+            }
+        }
+
+        self.emit(EngineEvent::WorkflowFinished {
+            success: result.is_ok(),
+            duration_ms: start_time.elapsed().as_millis(),
+        });
+
+        result.map(|()| report)
+    }
+
+    /// Returns the report of the most recent run, successful or not.
+    pub fn report(&self) -> Option<&RunReport> {
+        self.report.as_ref()
+    }
+
+    /// Returns the step and resource timeline of the most recent run.
+    ///
+    /// Use [`ExecutionTimeline::to_chrome_trace`] to export it for a trace
+    /// viewer.
+    pub fn timeline(&self) -> Option<&ExecutionTimeline> {
+        self.timeline.as_ref()
+    }
+
+    /// Sets up environments, restores state and builds the execution plan.
+    async fn prepare(
+        &mut self,
+    ) -> Result<(WorkflowState, ExecutionPlanner), Box<dyn Error + Send + Sync>> {
         // Generate workflow path if not set
         if self.workflow_path.is_empty() {
             self.workflow_path = "workflow.yaml".to_string();
         }
 
-        // Setup conda environments for all tools (skip in dry run)
-        if !self.dry_run {
-            self.setup_environments()?;
+        // Tools already installed here may replace conda environments
+        if self.executor.shares_environments() {
+            let found = resolve_system_tools(&self.workflow, &self.tool_registry, self.tool_policy);
+            self.use_system_tools(found);
+        }
+
+        // Setup conda environments for all tools (skip in dry run, and when
+        // steps run elsewhere)
+        if !self.dry_run && !self.executor.shares_environments() {
+            info!(
+                "Steps run through the {} executor; conda environments must exist there",
+                self.executor.name()
+            );
+        } else if !self.dry_run {
+            let mut options = self.env_options.clone();
+            if options.lock_dir.is_none() {
+                options.lock_dir = Some(lock_dir(Path::new(&self.workflow_path)));
+            }
+            let failed = self.setup_environments(options).await?;
+
+            if self.tool_policy == ToolPolicy::PreferConda && !failed.is_empty() {
+                let registry = Arc::clone(&self.tool_registry);
+                let candidates = failed.clone();
+                let found = tokio::task::spawn_blocking(move || {
+                    candidates
+                        .into_iter()
+                        .filter_map(|tool| usable_system_tool(&tool, &registry).map(|found| (tool, found)))
+                        .collect()
+                })
+                .await?;
+                self.use_system_tools(found);
+            }
+
+            // Fail before any step starts rather than when the tool is needed
+            let missing: Vec<&String> = failed
+                .iter()
+                .filter(|tool| !self.system_tools.contains_key(*tool))
+                .collect();
+            if !missing.is_empty() {
+                return Err(format!(
+                    "No usable environment or binary on PATH for {:?}; see the problems above, \
+                     or run `rustrunner env doctor`",
+                    missing
+                )
+                .into());
+            }
+        }
+
+        for (tool, found) in &self.system_tools {
+            let steps: Vec<&str> = self
+                .workflow
+                .steps
+                .iter()
+                .filter(|step| &step.tool == tool && step.conda.is_none() && step.container.is_none())
+                .map(|step| step.id.as_str())
+                .collect();
+            info!("Using {} {} for steps: {:?}", tool, found, steps);
         }
 
         // Load or create state
@@ -139,7 +490,6 @@ impl Engine {
             WorkflowState::new(&self.workflow_path)
         });
 
-
         // Verify completed steps still have outputs
         let steps_to_rerun: Vec<String> = self
             .workflow
@@ -161,14 +511,6 @@ impl Engine {
             state.completed_steps.remove(&step_id);
         }
 
-        // Initialize monitoring
-        let mut timeline = ExecutionTimeline::new();
-
-        info!(
-            "Starting execution (max parallel: {}, dry run: {})",
-            self.max_parallel, self.dry_run
-        );
-
         // Create planner
         let mut planner = if state.is_resume() {
             ExecutionPlanner::from_state(
@@ -187,230 +529,556 @@ impl Engine {
             )?
         };
 
-        // Load environment mappings
-        let env_map = ToolEnvMap::load();
+        planner.set_max_threads(self.executor.max_threads());
+
+        // Estimate step durations from earlier runs
+        if let Some(history) = &self.history {
+            match history.expected_durations(planner.workflow()) {
+                Ok(durations) => planner.set_expected_durations(durations),
+                Err(e) => warn!("Failed to read run history: {}", e),
+            }
+        }
+
+        if let Some((path, total_ms)) = planner.critical_path() {
+            info!(
+                "Estimated critical path: {} ({:.1}s)",
+                path.join(" -> "),
+                total_ms as f64 / 1000.0
+            );
+        }
 
-        // Create channel for step completion
-        let (tx, rx): (
-            Sender<(String, Result<(), String>)>,
-            Receiver<(String, Result<(), String>)>,
-        ) = channel();
+        Ok((state, planner))
+    }
 
-        // Start resource monitoring
-        let monitor_running = Arc::new(AtomicBool::new(true));
-        let monitor_flag = Arc::clone(&monitor_running);
+    /// Runs the planned steps to completion; see [`Engine::run_async`].
+    async fn execute(
+        &mut self,
+        run_id: &str,
+        planner: &mut ExecutionPlanner,
+        state: &mut WorkflowState,
+        monitor: &mut ResourceMonitor,
+        timeline: &mut ExecutionTimeline,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let execution_start = Instant::now();
 
-        let monitor_handle = thread::spawn(move || {
-            let mut monitor = ResourceMonitor::new();
-            while monitor_flag.load(Ordering::Relaxed) {
-                monitor.sample();
-                thread::sleep(MONITOR_SAMPLE_INTERVAL);
-            }
-            monitor
+        info!(
+            "Starting execution (max parallel: {}, executor: {}, dry run: {})",
+            self.max_parallel,
+            self.executor.name(),
+            self.dry_run
+        );
+
+        self.emit(EngineEvent::WorkflowStarted {
+            total_steps: planner.progress().1,
         });
 
-        let mut running_count = 0;
+        // Report steps carried over from a previous run
+        let mut skipped: Vec<String> = planner
+            .get_metrics()
+            .iter()
+            .filter(|(_, metrics)| metrics.status == StepStatus::Skipped)
+            .map(|(id, _)| id.clone())
+            .collect();
+        skipped.sort();
+
+        for step_id in skipped {
+            self.emit(EngineEvent::StepSkipped {
+                step_id,
+                reason: "completed in a previous run".to_string(),
+            });
+        }
+
+        // Settings shared by all steps; scripts go into this run's directory
+        let script_dir = std::env::current_dir()?.join(scripts::script_dir(&self.runs_dir, run_id));
+        let context = Arc::new(
+            StepContext::new(script_dir)
+                .with_tool_env_map(ToolEnvMap::load().as_map().clone())
+                .with_tool_registry(Arc::clone(&self.tool_registry))
+                .with_working_dir(self.working_dir.clone())
+                .with_keep_scripts(self.keep_scripts)
+                .with_shell(self.workflow.shell.clone())
+                .with_container_runtime(self.container_runtime.clone())
+                .with_executor(Arc::clone(&self.executor)),
+        );
+
+        let mut tasks: JoinSet<StepResult> = JoinSet::new();
+
+        let mut monitor_tick = interval(MONITOR_SAMPLE_INTERVAL);
+        monitor_tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut pause_tick = interval(PAUSE_CHECK_INTERVAL);
+        pause_tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut progress_tick = interval(PROGRESS_INTERVAL);
+        progress_tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let mut paused_by_control = false;
+        let mut paused = false;
 
         // Main execution loop
         loop {
+            // Check for pause signal
+            let pause_requested = paused_by_control || self.pause_flag_active();
+            if pause_requested != paused {
+                paused = pause_requested;
+                if paused {
+                    info!("Execution paused - waiting for resume signal");
+                    self.emit(EngineEvent::Paused);
+                } else {
+                    info!("Resumed");
+                    self.emit(EngineEvent::Resumed);
+                }
+            }
+
             // Schedule ready steps
-            while running_count < self.max_parallel {
+            while !paused && tasks.len() < self.max_parallel {
                 let ready_steps = planner.get_ready_steps();
                 if ready_steps.is_empty() {
                     break;
                 }
 
                 for step in ready_steps {
-                    if running_count >= self.max_parallel {
+                    if tasks.len() >= self.max_parallel {
                         break;
                     }
 
-                    // Check for pause signal
-                    if let Some(ref pause_path) = self.pause_flag_path {
-                        self.check_pause_flag(pause_path);
-                    }
-
                     info!("Starting step: {}", step.id);
                     timeline.add_event(step.id.clone(), EventType::Started);
                     planner.mark_step_running(&step.id);
+                    self.emit(EngineEvent::StepStarted {
+                        step_id: step.id.clone(),
+                    });
 
                     if self.dry_run {
                         // Dry run output
                         println!();
                         println!("[DRY RUN] Step: {}", step.id);
                         println!("  Tool: {}", step.tool);
-                        println!("  Command: {}", step.command);
+                        if let Some(ref image) = step.container {
+                            println!("  Container: {}", image);
+                        }
+                        match self.system_tools.get(&step.tool) {
+                            Some(found) if step.conda.is_none() && step.container.is_none() => {
+                                println!("  Binary: {}", found)
+                            }
+                            _ => {}
+                        }
+                        match &step.script {
+                            Some(script) => {
+                                println!("  Script ({:?}):", script.language);
+                                for line in script.code.lines() {
+                                    println!("    {}", line);
+                                }
+                            }
+                            None => println!("  Command: {}", step.command),
+                        }
                         println!("  Input: {:?}", step.input);
                         println!("  Output: {:?}", step.output);
                         println!("  Threads: {}", step.threads);
 
                         timeline.add_event(step.id.clone(), EventType::Completed);
                         planner.mark_step_completed(&step.id);
+                        self.emit(EngineEvent::StepCompleted {
+                            step_id: step.id.clone(),
+                            duration_ms: 0,
+                        });
                         continue;
                     }
 
-                    // Spawn worker thread
-                    let tx = tx.clone();
-                    let step_clone = step.clone();
-                    let env_map_clone = env_map.as_map().clone();
-                    let working_dir_clone = self.working_dir.clone();
-
-                    thread::spawn(move || {
-                        let result = execute_step(&step_clone, &env_map_clone, &working_dir_clone)
-                            .map_err(|e| e.to_string());
+                    // Spawn step task
+                    let context = Arc::clone(&context);
 
-                        if let Err(e) = tx.send((step_clone.id.clone(), result)) {
-                            error!("Failed to send completion signal: {}", e);
-                        }
+                    tasks.spawn(async move {
+                        let result = execute_step(&step, &context).await;
+                        (step.id, result)
                     });
-
-                    running_count += 1;
                 }
             }
 
             // Check for completion
-            if running_count == 0 && !planner.has_work_remaining() {
+            if tasks.is_empty() && !planner.has_work_remaining() {
                 break;
             }
 
-            // Wait for step completion (skip in dry run)
-            if running_count > 0 && !self.dry_run {
-                let (step_id, result) = rx.recv().map_err(|e| {
-                    format!("Failed to receive step completion: {}", e)
-                })?;
-
-                running_count -= 1;
-
-                match result {
-                    Ok(()) => {
-                        info!("Step '{}' completed successfully", step_id);
-                        planner.mark_step_completed(&step_id);
-                        timeline.add_event(step_id.clone(), EventType::Completed);
-                        state.mark_completed(&step_id);
-                        state.save()?;
+            // Nothing running, nothing ready and not paused: the remaining
+            // steps can never be scheduled (e.g. they need more threads than
+            // this machine has)
+            if tasks.is_empty() && !paused {
+                let (completed, total) = planner.progress();
+                return Err(format!(
+                    "No runnable steps remain ({} of {} completed); \
+                     check step thread requirements against available CPUs",
+                    completed, total
+                )
+                .into());
+            }
+
+            tokio::select! {
+                Some(joined) = tasks.join_next(), if !tasks.is_empty() => {
+                    let (step_id, result) = joined.map_err(|e| {
+                        format!("Step task failed unexpectedly: {}", e)
+                    })?;
+
+                    match result {
+                        Ok(output) => {
+                            info!("Step '{}' completed successfully", step_id);
+                            planner.mark_step_completed(&step_id);
+                            planner.set_exit_code(&step_id, Some(0));
+                            planner.set_log_tail(&step_id, output.stdout_tail, output.stderr_tail);
+                            planner.set_resources(&step_id, output.resources);
+                            timeline.add_event(step_id.clone(), EventType::Completed);
+                            state.mark_completed(&step_id);
+                            state.save().map_err(|e| e.to_string())?;
+                            self.emit(EngineEvent::StepCompleted {
+                                duration_ms: step_duration(planner, &step_id),
+                                step_id,
+                            });
+                        }
+                        Err(e) => {
+                            error!("Step '{}' failed: {}", step_id, e);
+                            planner.mark_step_failed(&step_id, e.clone());
+                            timeline.add_event(step_id.clone(), EventType::Failed);
+                            state.mark_failed_with_error(&e);
+                            state.save().map_err(|e| e.to_string())?;
+                            self.emit(EngineEvent::StepFailed {
+                                duration_ms: step_duration(planner, &step_id),
+                                step_id: step_id.clone(),
+                                error: e.to_string(),
+                            });
+
+                            Self::abort_running(&mut tasks).await;
+                            return Err(format!(
+                                "Workflow failed at step '{}': {}",
+                                step_id,
+                                e.details()
+                            )
+                            .into());
+                        }
                     }
-                    Err(e) => {
-                        error!("Step '{}' failed: {}", step_id, e);
-                        planner.mark_step_failed(&step_id, e.clone());
-                        timeline.add_event(step_id.clone(), EventType::Failed);
-                        state.mark_failed(&step_id);
-                        state.save()?;
-
-                        monitor_running.store(false, Ordering::Relaxed);
-                        return Err(format!(
-                            "Workflow failed at step '{}': {}",
-                            step_id, e
-                        )
-                        .into());
+                }
+                _ = monitor_tick.tick() => {
+                    let sample_count = monitor.get_samples().len();
+                    monitor.sample();
+
+                    if let Some(sample) = monitor.get_samples().get(sample_count) {
+                        timeline.add_sample(sample.cpu_usage, sample.memory_mb);
+                        let event = EngineEvent::ResourceSample {
+                            cpu_usage: sample.cpu_usage,
+                            memory_mb: sample.memory_mb,
+                        };
+                        self.emit(event);
+                    }
+                }
+                _ = pause_tick.tick() => {
+                    // Wakes the loop so the pause flag is re-checked
+                }
+                _ = progress_tick.tick(), if !self.dry_run && !tasks.is_empty() => {
+                    let (completed, total) = planner.progress();
+                    let running = planner.running_count();
+                    let eta_ms = planner.estimate_remaining_ms();
+                    match eta_ms {
+                        Some(ms) => info!(
+                            "Progress: {}/{} steps, {} running, ETA ~{}",
+                            completed, total, running, format_eta(ms)
+                        ),
+                        None => info!("Progress: {}/{} steps, {} running", completed, total, running),
+                    }
+                    self.emit(EngineEvent::Progress {
+                        completed,
+                        total,
+                        running,
+                        elapsed_ms: execution_start.elapsed().as_millis(),
+                        eta_ms,
+                    });
+                }
+                Some(control) = self.control_rx.recv() => {
+                    match control {
+                        EngineControl::Pause => paused_by_control = true,
+                        EngineControl::Resume => paused_by_control = false,
+                        EngineControl::Cancel => {
+                            warn!("Cancellation requested - stopping running steps");
+                            Self::abort_running(&mut tasks).await;
+                            return Err("Workflow cancelled".into());
+                        }
                     }
                 }
             }
         }
 
-        // Stop monitoring
-        monitor_running.store(false, Ordering::Relaxed);
-        let final_monitor = monitor_handle
-            .join()
-            .map_err(|_| "Monitor thread panicked")?;
-
-        let total_time = start_time.elapsed();
-
-        // Print summary
-        println!();
-        println!("Workflow completed successfully");
-        println!("Total execution time: {:.2?}", total_time);
-        println!();
-        println!("{}", final_monitor.get_summary());
-
         Ok(())
     }
 
-    /// Checks if pause flag exists and waits for it to be removed.
-    fn check_pause_flag(&self, pause_flag_path: &str) {
-        let pause_path = Path::new(pause_flag_path);
+    /// Aborts all running step tasks and waits for them to wind down.
+    ///
+    /// Aborting drops each step's future, which kills its process group.
+    async fn abort_running(tasks: &mut JoinSet<StepResult>) {
+        tasks.abort_all();
+        while tasks.join_next().await.is_some() {}
+    }
 
-        if pause_path.exists() {
-            info!("Execution paused - waiting for resume signal");
+    /// Returns true if the pause flag file currently exists.
+    fn pause_flag_active(&self) -> bool {
+        self.pause_flag_path
+            .as_deref()
+            .is_some_and(|path| Path::new(path).exists())
+    }
 
-            while pause_path.exists() {
-                thread::sleep(PAUSE_CHECK_INTERVAL);
-            }
+    /// Runs conda tools found on PATH from there instead of their
+    /// environments.
+    fn use_system_tools(&mut self, found: BTreeMap<String, SystemTool>) {
+        if found.is_empty() {
+            return;
+        }
 
-            info!("Resumed");
+        let mut registry = ToolRegistry::clone(&self.tool_registry);
+        for tool in found.keys() {
+            registry.insert(
+                tool.clone(),
+                ToolInfo {
+                    mode: ToolMode::System,
+                    ..Default::default()
+                },
+            );
         }
+        self.tool_registry = Arc::new(registry);
+        self.system_tools.extend(found);
     }
 
     /// Sets up conda environments for all tools in the workflow.
     ///
-    /// For each unique tool in the workflow:
+    /// For each environment the workflow needs (see
+    /// [`required_environments`]):
     /// 1. Skips system tools (bash, cat, etc.)
-    /// 2. Checks if tool is already in env_map
-    /// 3. Creates a new conda environment if needed
-    /// 4. Updates env_map with the new mapping
-    fn setup_environments(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // Collect unique tools from workflow
-        let tools: HashSet<String> = self
-            .workflow
-            .steps
-            .iter()
-            .map(|step| step.tool.clone())
-            .collect();
+    /// 2. Creates the environment if needed, from its lockfile in locked mode
+    /// 3. Writes a lockfile for it if there is none yet
+    /// 4. Updates env_map with new tool mappings
+    /// 5. Checks that each tool's environment contains the tool
+    ///
+    /// Before anything is created, a pre-flight check (see
+    /// [`preflight`]) makes sure micromamba runs, the root prefix is
+    /// writable and `env_map.json` parses, and the packages of tool
+    /// environments still to be created are looked up (see
+    /// [`Engine::check_packages`]), unless installing offline or from
+    /// lockfiles.
+    ///
+    /// Environments are set up in parallel, up to `max_parallel` at a time,
+    /// each on tokio's blocking pool and reported with
+    /// [`EngineEvent::EnvironmentStarted`] and `EnvironmentReady` or
+    /// `EnvironmentFailed`. Each environment and `env_map.json` are locked
+    /// while they change, so concurrent runs don't race on them.
+    ///
+    /// Failing to set up a tool's environment is only a warning, unless in
+    /// locked mode or with the `conda-only` policy; conda spec environments
+    /// must always be created. Returns the tools whose environments
+    /// couldn't be set up.
+    async fn setup_environments(
+        &mut self,
+        options: EnvOptions,
+    ) -> Result<Vec<String>, Box<dyn Error + Send + Sync>> {
+        let base_dir = self.working_dir.clone();
+        let mut required = required_environments(
+            &self.workflow,
+            base_dir.as_deref(),
+            &ToolEnvMap::load(),
+            &self.tool_registry,
+        )?;
+        let mut failed = Vec::new();
+
+        if required.is_empty() {
+            info!("No conda tools required - using system tools only");
+            return Ok(failed);
+        }
 
-        // Filter out system tools
-        let conda_tools: Vec<&String> = tools
-            .iter()
-            .filter(|tool| !SYSTEM_TOOLS.contains(&tool.as_str()))
-            .collect();
+        // Pre-flight: can environments be created at all?
+        let checked = required.clone();
+        let diagnosis = tokio::task::spawn_blocking(move || preflight(&checked)).await?;
+        if !diagnosis.is_healthy() {
+            return Err(format!("Environment pre-flight check failed:\n{}", diagnosis).into());
+        }
 
-        if conda_tools.is_empty() {
-            info!("No conda tools required - using system tools only");
-            return Ok(());
+        let strict = options.locked || self.tool_policy == ToolPolicy::CondaOnly;
+        if !options.locked && !options.offline {
+            self.check_packages(&mut required, &options, strict).await?;
+            if required.is_empty() {
+                return Ok(failed);
+            }
         }
 
-        info!("Setting up environments for {} tools: {:?}", conda_tools.len(), conda_tools);
+        let names: Vec<&str> = required.iter().map(|env| env.name.as_str()).collect();
+        let limit = self.max_parallel.max(1);
+        info!(
+            "Setting up {} environments ({} at a time): {:?}",
+            required.len(),
+            limit,
+            names
+        );
 
-        // Load existing env_map
-        let mut env_map = ToolEnvMap::load();
+        let options = Arc::new(options);
+        let mut pending = required.into_iter();
+        let mut tasks: JoinSet<(RequiredEnv, Duration, Result<(), String>)> = JoinSet::new();
+        let mut error = None;
 
-        for tool in conda_tools {
-            // Check if we already have a mapping for this tool
-            if env_map.get(tool).is_some() {
-                info!("Tool '{}' already has environment mapping", tool);
-            } else {
-                // No mapping - create environment with same name as tool
-                info!("Creating environment for tool: {}", tool);
+        loop {
+            while error.is_none() && tasks.len() < limit {
+                let Some(env) = pending.next() else { break };
+
+                info!("Setting up environment '{}' for steps: {:?}", env.name, env.steps);
+                self.emit(EngineEvent::EnvironmentStarted {
+                    env_name: env.name.clone(),
+                    steps: env.steps.clone(),
+                });
+
+                let base_dir = base_dir.clone();
+                let options = Arc::clone(&options);
+                tasks.spawn_blocking(move || {
+                    let started = Instant::now();
+                    let result = Self::prepare_environment(&env, base_dir.as_deref(), &options);
+                    (env, started.elapsed(), result)
+                });
             }
 
-            // Create the environment (will skip if already exists)
-            // Environment name = tool name for simplicity
-            let env_name = tool.clone();
+            let Some(joined) = tasks.join_next().await else { break };
+            let (env, elapsed, result) = joined?;
 
-            match create_env(&env_name, &[tool.clone()]) {
+            match result {
                 Ok(()) => {
-                    // Update env_map if not already present
-                    if env_map.get(tool).is_none() {
-                        env_map.set(tool, &env_name);
-                    }
-                    info!("Environment '{}' ready", env_name);
+                    info!("Environment '{}' ready", env.name);
+                    self.emit(EngineEvent::EnvironmentReady {
+                        env_name: env.name,
+                        duration_ms: elapsed.as_millis(),
+                    });
                 }
                 Err(e) => {
-                    warn!(
-                        "Failed to create environment for '{}': {}. Will try to continue.",
-                        tool, e
-                    );
+                    self.emit(EngineEvent::EnvironmentFailed {
+                        env_name: env.name.clone(),
+                        error: e.clone(),
+                    });
+                    match env.source {
+                        EnvSource::Tool { tool, .. } if !strict => {
+                            warn!("Steps {:?}: {}", env.steps, e);
+                            failed.push(tool);
+                        }
+                        _ => {
+                            // Let the environments being set up finish; their
+                            // micromamba processes can't be interrupted
+                            if error.is_none() && !tasks.is_empty() {
+                                info!("Waiting for {} environments still being set up", tasks.len());
+                            }
+                            error.get_or_insert(format!("Steps {:?}: {}", env.steps, e));
+                        }
+                    }
+                }
+            }
+        }
+
+        match error {
+            Some(error) => Err(error.into()),
+            None => Ok(failed),
+        }
+    }
+
+    /// Makes sure the package of each tool environment still to be created
+    /// exists, so that a typo in a step's `tool:` fails before anything is
+    /// installed, with suggestions for what was meant.
+    ///
+    /// Unless `strict`, a tool without a package that is found on PATH runs
+    /// from there instead, and its environment is dropped from `required`.
+    async fn check_packages(
+        &mut self,
+        required: &mut Vec<RequiredEnv>,
+        options: &EnvOptions,
+        strict: bool,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let to_check: Vec<RequiredEnv> = required
+            .iter()
+            .filter(|env| {
+                matches!(env.source, EnvSource::Tool { .. }) && !env_prefix(&env.name).exists()
+            })
+            .cloned()
+            .collect();
+        if to_check.is_empty() {
+            return Ok(());
+        }
+
+        let registry = Arc::clone(&self.tool_registry);
+        let options = options.clone();
+        let (unknown, found) = tokio::task::spawn_blocking(move || {
+            let mut unknown = Vec::new();
+            let mut found = BTreeMap::new();
+            for env in to_check {
+                let EnvSource::Tool { ref tool, ref package } = env.source else {
+                    continue;
+                };
+                let Err(problem) = check_package(package, &options) else {
+                    continue;
+                };
+                match usable_system_tool(tool, &registry).filter(|_| !strict) {
+                    Some(system_tool) => {
+                        warn!("Steps {:?}: {}", env.steps, problem);
+                        found.insert(tool.clone(), system_tool);
+                    }
+                    None => unknown.push((env, problem)),
                 }
             }
+            (unknown, found)
+        })
+        .await?;
+
+        required.retain(|env| {
+            !matches!(env.source, EnvSource::Tool { ref tool, .. } if found.contains_key(tool))
+        });
+        self.use_system_tools(found);
+
+        if unknown.is_empty() {
+            return Ok(());
         }
+        let mut message = String::from("Unknown conda packages:");
+        for (env, problem) in &unknown {
+            message.push_str(&format!("\n  Steps {:?}: {}", env.steps, problem));
+        }
+        Err(message.into())
+    }
 
-        // Save updated env_map
-        if let Err(e) = env_map.save() {
+    /// Creates one environment, maps its tool in `env_map.json` and checks
+    /// that it contains the tool.
+    ///
+    /// This blocks on micromamba.
+    fn prepare_environment(
+        env: &RequiredEnv,
+        base_dir: Option<&Path>,
+        options: &EnvOptions,
+    ) -> Result<(), String> {
+        env.prepare(base_dir, options)
+            .map_err(|e| format!("Failed to create environment '{}': {}", env.name, e))?;
+
+        let EnvSource::Tool { ref tool, .. } = env.source else {
+            return Ok(());
+        };
+
+        let mapped = ToolEnvMap::update(|env_map| {
+            if env_map.get(tool).is_none() {
+                env_map.set(tool, &env.name);
+            }
+        });
+        if let Err(e) = mapped {
             warn!("Failed to save environment map: {}", e);
         }
 
-        Ok(())
+        // An environment that was created but can't run its tool counts as
+        // failed too
+        check_tool_env(&env_prefix(&env.name), &env.name, tool)
+            .map(|_| ())
+            .map_err(|problem| problem.to_string())
     }
 }
 
+/// Returns the recorded duration of a finished step in milliseconds.
+fn step_duration(planner: &ExecutionPlanner, step_id: &str) -> u128 {
+    planner
+        .get_metrics()
+        .get(step_id)
+        .and_then(|metrics| metrics.duration_ms)
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -516,22 +1184,22 @@ mod tests {
     #[test]
     fn test_pause_flag_check() {
         let workflow = create_test_workflow();
-        let engine = Engine::new(workflow);
+        let mut engine = Engine::new(workflow);
 
         let temp_dir = tempdir().unwrap();
         let pause_path = temp_dir.path().join("pause.flag");
 
-        // Verify no pause file => no blocking
-        assert!(!pause_path.exists());
+        // No pause path configured => never paused
+        assert!(!engine.pause_flag_active());
+
+        engine.set_pause_flag_path(pause_path.to_str().unwrap());
+        assert!(!engine.pause_flag_active());
 
         // Create and remove to test detection
         fs::write(&pause_path, "paused").unwrap();
-        assert!(pause_path.exists());
+        assert!(engine.pause_flag_active());
         fs::remove_file(&pause_path).unwrap();
-        assert!(!pause_path.exists());
-
-        // Now check_pause_flag should not block since file doesn't exist
-        engine.check_pause_flag(pause_path.to_str().unwrap());
+        assert!(!engine.pause_flag_active());
     }
 
     #[test]
@@ -547,17 +1215,171 @@ mod tests {
         assert_eq!(engine.workflow_path, "workflow.yaml");
     }
 
-    #[test]
-    fn test_setup_environments_system_tools_only() {
+    #[tokio::test]
+    async fn test_setup_environments_system_tools_only() {
         let mut workflow = Workflow::new();
         workflow.add_step(
             Step::new("bash_step", "bash", "echo test")
         ).unwrap();
 
-        let engine = Engine::new(workflow);
-
         // Should not error for system tools only
-        let result = engine.setup_environments();
+        let mut engine = Engine::new(workflow);
+        let result = engine.setup_environments(EnvOptions::default()).await;
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_system_tools_need_no_environment() {
+        let workflow = Workflow::from_steps(vec![
+            Step::new("sort", "samtools", "samtools sort"),
+            Step::new("qc", "fastqc", "fastqc"),
+        ]);
+        let mut engine = Engine::new(workflow);
+        engine.use_system_tools(BTreeMap::from([(
+            "samtools".to_string(),
+            SystemTool {
+                path: PathBuf::from("/usr/bin/samtools"),
+                version: Some("1.17".to_string()),
+            },
+        )]));
+
+        let envs = engine.environments().unwrap();
+        let names: Vec<&str> = envs.iter().map(|env| env.name.as_str()).collect();
+        assert_eq!(names, vec!["fastqc"]);
+        assert!(engine.tool_registry.is_system_tool("samtools"));
+    }
+
+    #[tokio::test]
+    async fn test_setup_environments_missing_env_file() {
+        let temp_dir = tempdir().unwrap();
+        let mut workflow = Workflow::new();
+        workflow.add_step(
+            Step::new("call", "bcftools", "bcftools call in.bcf")
+                .with_conda(crate::environment::CondaSpec::from_file("envs/calling.yml"))
+        ).unwrap();
+
+        let mut engine = Engine::new(workflow);
+        engine.set_working_dir(temp_dir.path());
+        let error = engine.setup_environments(EnvOptions::default()).await.unwrap_err().to_string();
+        assert!(error.contains("Step 'call'"), "{}", error);
+        assert!(error.contains("calling.yml"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_run_async_dry_run() {
+        let workflow = create_test_workflow();
+        let mut engine = Engine::new(workflow);
+        engine.set_dry_run(true);
+        engine.set_workflow_path("test_async.yaml");
+
+        let result = engine.run_async().await;
+        assert!(result.is_ok(), "Async dry run should succeed: {:?}", result.err());
+    }
+
+    #[test]
+    fn test_run_keeps_timeline() {
+        let mut engine = Engine::new(create_test_workflow());
+        engine.set_dry_run(true);
+        engine.set_workflow_path("test_timeline.yaml");
+
+        assert!(engine.timeline().is_none());
+        engine.run().unwrap();
+
+        let timeline = engine.timeline().unwrap();
+        assert_eq!(timeline.get_durations().len(), 2);
+        assert!(timeline.to_chrome_trace().contains("\"traceEvents\""));
+    }
+
+    #[test]
+    fn test_observer_receives_dry_run_events() {
+        let mut engine = Engine::new(create_test_workflow());
+        engine.set_dry_run(true);
+        engine.set_workflow_path("test_events.yaml");
+
+        let mut events = engine.subscribe();
+        engine.run().unwrap();
+
+        let mut received = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+
+        assert_eq!(received.first(), Some(&EngineEvent::WorkflowStarted { total_steps: 2 }));
+        assert!(received.contains(&EngineEvent::StepStarted {
+            step_id: "step1".to_string()
+        }));
+        assert!(received.iter().any(|e| matches!(
+            e,
+            EngineEvent::StepCompleted { step_id, .. } if step_id == "step2"
+        )));
+        assert!(matches!(
+            received.last(),
+            Some(EngineEvent::WorkflowFinished { success: true, .. })
+        ));
+    }
+
+    #[test]
+    fn test_dry_run_returns_report() {
+        let mut engine = Engine::new(create_test_workflow());
+        engine.set_dry_run(true);
+        engine.set_workflow_path("test_report.yaml");
+
+        let report = engine.run().unwrap();
+
+        assert!(report.success);
+        assert!(report.dry_run);
+        assert_eq!(report.workflow_path, "test_report.yaml");
+        assert_eq!(report.steps.len(), 2);
+        assert!(report
+            .steps
+            .iter()
+            .all(|s| s.status == crate::monitoring::StepOutcome::Completed));
+        assert_eq!(engine.report(), Some(&report));
+    }
+
+    #[test]
+    fn test_run_async_future_is_send() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let mut engine = Engine::new(create_test_workflow());
+        let future = engine.run_async();
+        assert_send(&future);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_running_steps() {
+        let mut workflow = Workflow::new();
+        workflow.add_step(Step::new("long", "bash", "sleep 30")).unwrap();
+
+        let temp_dir = tempdir().unwrap();
+        let mut engine = Engine::new(workflow);
+        engine.set_working_dir(temp_dir.path().to_path_buf());
+        engine.set_workflow_path("test_cancel.yaml");
+        let history = RunHistory::new(temp_dir.path().join("history.jsonl"));
+        engine.set_history(Some(history.clone()));
+        engine.set_runs_dir(temp_dir.path().join("runs"));
+
+        let handle = engine.handle();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            handle.cancel();
+        });
+
+        let started = Instant::now();
+        let result = engine.run_async().await;
+
+        assert!(result.unwrap_err().to_string().contains("cancelled"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        // Cancelled runs are still recorded
+        let runs = history.load().unwrap();
+        assert_eq!(runs.len(), 1);
+        assert!(!runs[0].success);
+        assert_eq!(runs[0].workflow_path, "test_cancel.yaml");
+
+        // The interrupted step's script is kept in the run directory
+        let script = scripts::script_dir(&temp_dir.path().join("runs"), &runs[0].run_id)
+            .join("long.sh");
+        assert!(script.exists());
+    }
 }
@@ -0,0 +1,167 @@
+//! Step Execution Errors
+//!
+//! Structured description of why a workflow step failed, kept alongside
+//! the step's metrics, in the state file and in run reports.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::monitoring::ProcessUsage;
+
+/// Error returned when a workflow step fails.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepError {
+    /// ID of the step that failed
+    pub step_id: String,
+
+    /// Human-readable description of the failure
+    pub message: String,
+
+    /// Exit code of the step's process, if it exited normally
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+
+    /// Signal that terminated the step's process, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signal: Option<i32>,
+
+    /// Command text after placeholder substitution
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub command: String,
+
+    /// Script the command was run from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script_path: Option<PathBuf>,
+
+    /// Last lines the step wrote to stderr
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stderr_tail: Vec<String>,
+
+    /// Resources used by the step's process tree before it failed
+    #[serde(default, skip_serializing_if = "ProcessUsage::is_empty")]
+    pub resources: ProcessUsage,
+}
+
+impl StepError {
+    /// Creates a new error for a step.
+    pub fn new(step_id: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            step_id: step_id.into(),
+            message: message.into(),
+            exit_code: None,
+            signal: None,
+            command: String::new(),
+            script_path: None,
+            stderr_tail: Vec::new(),
+            resources: ProcessUsage::default(),
+        }
+    }
+
+    /// Sets the process exit code.
+    pub fn with_exit_code(mut self, exit_code: Option<i32>) -> Self {
+        self.exit_code = exit_code;
+        self
+    }
+
+    /// Sets the signal that terminated the process.
+    pub fn with_signal(mut self, signal: Option<i32>) -> Self {
+        self.signal = signal;
+        self
+    }
+
+    /// Sets the command text that was run.
+    pub fn with_command(mut self, command: impl Into<String>) -> Self {
+        self.command = command.into();
+        self
+    }
+
+    /// Sets the path of the generated script.
+    pub fn with_script_path(mut self, script_path: impl Into<PathBuf>) -> Self {
+        self.script_path = Some(script_path.into());
+        self
+    }
+
+    /// Sets the trailing stderr lines.
+    pub fn with_stderr_tail(mut self, stderr_tail: Vec<String>) -> Self {
+        self.stderr_tail = stderr_tail;
+        self
+    }
+
+    /// Sets the resources used by the step.
+    pub fn with_resources(mut self, resources: ProcessUsage) -> Self {
+        self.resources = resources;
+        self
+    }
+
+    /// Returns a multi-line description with the command, script and
+    /// trailing stderr, for error output and the GUI.
+    pub fn details(&self) -> String {
+        let mut details = self.message.clone();
+
+        if !self.command.is_empty() {
+            details.push_str(&format!("\n  command: {}", self.command.trim()));
+        }
+        if let Some(ref script_path) = self.script_path {
+            details.push_str(&format!("\n  script: {}", script_path.display()));
+        }
+        if !self.stderr_tail.is_empty() {
+            details.push_str("\n  stderr (last lines):");
+            for line in &self.stderr_tail {
+                details.push_str(&format!("\n    {}", line));
+            }
+        }
+
+        details
+    }
+}
+
+impl fmt::Display for StepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for StepError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_error_display() {
+        let error = StepError::new("align", "Step 'align' failed").with_exit_code(Some(2));
+
+        assert_eq!(error.to_string(), "Step 'align' failed");
+        assert_eq!(error.exit_code, Some(2));
+        assert_eq!(error.step_id, "align");
+    }
+
+    #[test]
+    fn test_step_error_serialization_skips_missing_exit_code() {
+        let json = serde_json::to_string(&StepError::new("a", "boom")).unwrap();
+        assert!(!json.contains("exit_code"));
+        assert!(!json.contains("signal"));
+        assert!(!json.contains("script_path"));
+    }
+
+    #[test]
+    fn test_step_error_details() {
+        let error = StepError::new("align", "Step 'align' failed with exit code 1")
+            .with_exit_code(Some(1))
+            .with_command("bwa mem ref.fa reads.fq\n")
+            .with_script_path("/tmp/step_align.sh")
+            .with_stderr_tail(vec!["[E::bwa_idx_load] fail to locate the index".to_string()]);
+
+        let details = error.details();
+        assert!(details.starts_with("Step 'align' failed with exit code 1"));
+        assert!(details.contains("command: bwa mem ref.fa reads.fq\n  script:"));
+        assert!(details.contains("script: /tmp/step_align.sh"));
+        assert!(details.contains("    [E::bwa_idx_load] fail to locate the index"));
+
+        let roundtrip: StepError =
+            serde_json::from_str(&serde_json::to_string(&error).unwrap()).unwrap();
+        assert_eq!(roundtrip, error);
+    }
+}
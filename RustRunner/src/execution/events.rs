@@ -0,0 +1,470 @@
+//! Engine Events and Observers
+//!
+//! Typed progress events emitted by the [`Engine`](super::Engine) while a
+//! workflow runs, and the [`EngineObserver`] trait for consuming them.
+//!
+//! Built-in observers:
+//! - [`ConsoleObserver`]: Human-readable progress lines on stdout
+//! - [`JsonLinesObserver`]: One JSON object per event, for dashboards and the GUI
+//! - [`TimelineObserver`]: Records step events into an [`ExecutionTimeline`]
+//! - [`ChannelObserver`]: Forwards events to a tokio channel
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use rustrunner::execution::{Engine, JsonLinesObserver};
+//! use rustrunner::load_workflow;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let workflow = load_workflow("pipeline.yaml")?;
+//!     let mut engine = Engine::new(workflow);
+//!     engine.add_observer(JsonLinesObserver::new(std::io::stderr()));
+//!
+//!     engine.run()?;
+//!     Ok(())
+//! }
+//! ```
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use log::warn;
+use serde::Serialize;
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::monitoring::{EventType, ExecutionTimeline};
+
+/// A progress event emitted by the engine.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EngineEvent {
+    /// Setting up a conda environment started
+    EnvironmentStarted {
+        env_name: String,
+        /// Steps running in the environment
+        steps: Vec<String>,
+    },
+    /// A conda environment is ready
+    EnvironmentReady { env_name: String, duration_ms: u128 },
+    /// A conda environment couldn't be set up
+    EnvironmentFailed { env_name: String, error: String },
+    /// Execution is about to start
+    WorkflowStarted {
+        /// Number of steps in the (expanded) workflow
+        total_steps: usize,
+    },
+    /// A step was launched
+    StepStarted { step_id: String },
+    /// A step finished successfully
+    StepCompleted { step_id: String, duration_ms: u128 },
+    /// A step failed
+    StepFailed {
+        step_id: String,
+        error: String,
+        duration_ms: u128,
+    },
+    /// A step was not run (e.g. completed in a previous run)
+    StepSkipped { step_id: String, reason: String },
+    /// A resource usage sample was taken
+    ResourceSample { cpu_usage: f32, memory_mb: u64 },
+    /// Periodic progress update with an estimated time to completion
+    Progress {
+        /// Steps finished so far (completed or skipped)
+        completed: usize,
+        /// Total number of steps
+        total: usize,
+        /// Steps currently running
+        running: usize,
+        /// Time since execution started
+        elapsed_ms: u128,
+        /// Estimated time until all steps finish, when it can be estimated
+        #[serde(skip_serializing_if = "Option::is_none")]
+        eta_ms: Option<u64>,
+    },
+    /// Scheduling of new steps was paused
+    Paused,
+    /// Scheduling resumed after a pause
+    Resumed,
+    /// Execution ended, successfully or not
+    WorkflowFinished { success: bool, duration_ms: u128 },
+}
+
+impl EngineEvent {
+    /// Returns the step this event refers to, if any.
+    pub fn step_id(&self) -> Option<&str> {
+        match self {
+            Self::StepStarted { step_id }
+            | Self::StepCompleted { step_id, .. }
+            | Self::StepFailed { step_id, .. }
+            | Self::StepSkipped { step_id, .. } => Some(step_id),
+            _ => None,
+        }
+    }
+}
+
+/// Receives events from a running engine.
+///
+/// Observers are called synchronously from the engine loop, so
+/// implementations should return quickly and hand slow work off elsewhere.
+pub trait EngineObserver: Send {
+    /// Called once for every event, in order.
+    fn on_event(&mut self, event: &EngineEvent);
+}
+
+/// Any `FnMut(&EngineEvent)` closure can be used as an observer.
+impl<F> EngineObserver for F
+where
+    F: FnMut(&EngineEvent) + Send,
+{
+    fn on_event(&mut self, event: &EngineEvent) {
+        self(event)
+    }
+}
+
+/// Prints concise, colored progress lines to stdout.
+#[derive(Debug, Default)]
+pub struct ConsoleObserver {
+    total_steps: usize,
+    finished_steps: usize,
+}
+
+impl ConsoleObserver {
+    /// Creates a new console observer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn progress(&self) -> String {
+        format!("[{}/{}]", self.finished_steps, self.total_steps)
+    }
+}
+
+impl EngineObserver for ConsoleObserver {
+    fn on_event(&mut self, event: &EngineEvent) {
+        match event {
+            EngineEvent::EnvironmentStarted { env_name, .. } => {
+                println!("{} {}", "setting up".cyan(), env_name);
+            }
+            EngineEvent::EnvironmentReady {
+                env_name,
+                duration_ms,
+            } => {
+                println!(
+                    "{} {} ({:.1}s)",
+                    "ready".green(),
+                    env_name,
+                    *duration_ms as f64 / 1000.0
+                );
+            }
+            EngineEvent::EnvironmentFailed { env_name, error } => {
+                println!("{} {}: {}", "failed".red(), env_name, error);
+            }
+            EngineEvent::WorkflowStarted { total_steps } => {
+                self.total_steps = *total_steps;
+            }
+            EngineEvent::StepStarted { step_id } => {
+                println!("{} {} {}", self.progress(), "started".cyan(), step_id);
+            }
+            EngineEvent::StepCompleted {
+                step_id,
+                duration_ms,
+            } => {
+                self.finished_steps += 1;
+                println!(
+                    "{} {} {} ({:.1}s)",
+                    self.progress(),
+                    "done".green(),
+                    step_id,
+                    *duration_ms as f64 / 1000.0
+                );
+            }
+            EngineEvent::StepFailed { step_id, error, .. } => {
+                println!("{} {} {}: {}", self.progress(), "failed".red(), step_id, error);
+            }
+            EngineEvent::StepSkipped { step_id, reason } => {
+                self.finished_steps += 1;
+                println!("{} {} {} ({})", self.progress(), "skipped".yellow(), step_id, reason);
+            }
+            EngineEvent::Progress {
+                completed,
+                total,
+                running,
+                eta_ms,
+                ..
+            } => {
+                println!(
+                    "[{}/{}] {} running{}",
+                    completed,
+                    total,
+                    running,
+                    eta_ms
+                        .map(|ms| format!(", ETA ~{}", format_eta(ms)))
+                        .unwrap_or_default()
+                );
+            }
+            EngineEvent::Paused => println!("{}", "paused".yellow()),
+            EngineEvent::Resumed => println!("{}", "resumed".green()),
+            EngineEvent::ResourceSample { .. } | EngineEvent::WorkflowFinished { .. } => {}
+        }
+    }
+}
+
+/// Formats a duration estimate as e.g. `45s`, `1m 20s` or `2h 05m`.
+pub(crate) fn format_eta(ms: u64) -> String {
+    let secs = (ms.saturating_add(500) / 1000).max(1);
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {:02}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+/// A single line written by [`JsonLinesObserver`].
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    event: &'a EngineEvent,
+}
+
+/// Writes every event as one JSON object per line.
+///
+/// Each line carries a UTC `timestamp` and an `event` tag, e.g.
+/// `{"timestamp":"...","event":"step_completed","step_id":"align","duration_ms":1200}`.
+pub struct JsonLinesObserver<W: Write + Send> {
+    writer: W,
+}
+
+impl<W: Write + Send> JsonLinesObserver<W> {
+    /// Creates an observer writing to the given sink.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write + Send> EngineObserver for JsonLinesObserver<W> {
+    fn on_event(&mut self, event: &EngineEvent) {
+        let line = JsonLine {
+            timestamp: Utc::now(),
+            event,
+        };
+
+        let result = serde_json::to_writer(&mut self.writer, &line)
+            .map_err(std::io::Error::from)
+            .and_then(|()| writeln!(self.writer))
+            .and_then(|()| self.writer.flush());
+
+        if let Err(e) = result {
+            warn!("Failed to write event: {}", e);
+        }
+    }
+}
+
+/// Records step start/finish events and resource samples into a shared
+/// [`ExecutionTimeline`].
+#[derive(Debug, Clone, Default)]
+pub struct TimelineObserver {
+    timeline: Arc<Mutex<ExecutionTimeline>>,
+}
+
+impl TimelineObserver {
+    /// Creates an observer with an empty timeline starting now.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a snapshot of the recorded timeline.
+    pub fn timeline(&self) -> ExecutionTimeline {
+        self.timeline
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+impl EngineObserver for TimelineObserver {
+    fn on_event(&mut self, event: &EngineEvent) {
+        let event_type = match event {
+            EngineEvent::ResourceSample {
+                cpu_usage,
+                memory_mb,
+            } => {
+                self.timeline
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .add_sample(*cpu_usage, *memory_mb);
+                return;
+            }
+            EngineEvent::StepStarted { .. } => EventType::Started,
+            EngineEvent::StepCompleted { .. } => EventType::Completed,
+            EngineEvent::StepFailed { .. } => EventType::Failed,
+            _ => return,
+        };
+
+        if let Some(step_id) = event.step_id() {
+            self.timeline
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .add_event(step_id.to_string(), event_type);
+        }
+    }
+}
+
+/// Forwards events to a tokio channel.
+///
+/// Events are dropped silently once the receiver is gone.
+#[derive(Debug, Clone)]
+pub struct ChannelObserver {
+    sender: UnboundedSender<EngineEvent>,
+}
+
+impl ChannelObserver {
+    /// Creates an observer sending to the given channel.
+    pub fn new(sender: UnboundedSender<EngineEvent>) -> Self {
+        Self { sender }
+    }
+}
+
+impl EngineObserver for ChannelObserver {
+    fn on_event(&mut self, event: &EngineEvent) {
+        let _ = self.sender.send(event.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn started(id: &str) -> EngineEvent {
+        EngineEvent::StepStarted {
+            step_id: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_event_step_id() {
+        assert_eq!(started("a").step_id(), Some("a"));
+        assert_eq!(EngineEvent::Paused.step_id(), None);
+    }
+
+    #[test]
+    fn test_event_serialization_tag() {
+        let json = serde_json::to_string(&EngineEvent::StepCompleted {
+            step_id: "align".to_string(),
+            duration_ms: 1200,
+        })
+        .unwrap();
+
+        assert!(json.contains("\"event\":\"step_completed\""));
+        assert!(json.contains("\"step_id\":\"align\""));
+        assert!(json.contains("\"duration_ms\":1200"));
+    }
+
+    #[test]
+    fn test_environment_event_serialization() {
+        let json = serde_json::to_string(&EngineEvent::EnvironmentStarted {
+            env_name: "align".to_string(),
+            steps: vec!["map".to_string(), "sort".to_string()],
+        })
+        .unwrap();
+
+        assert!(json.contains("\"event\":\"environment_started\""));
+        assert!(json.contains("\"env_name\":\"align\""));
+        assert!(json.contains("\"steps\":[\"map\",\"sort\"]"));
+        assert_eq!(
+            EngineEvent::EnvironmentReady {
+                env_name: "align".to_string(),
+                duration_ms: 10
+            }
+            .step_id(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_progress_serialization() {
+        let progress = |eta_ms| EngineEvent::Progress {
+            completed: 2,
+            total: 5,
+            running: 1,
+            elapsed_ms: 3000,
+            eta_ms,
+        };
+
+        let json = serde_json::to_string(&progress(Some(4500))).unwrap();
+        assert!(json.contains("\"event\":\"progress\""));
+        assert!(json.contains("\"eta_ms\":4500"));
+
+        let json = serde_json::to_string(&progress(None)).unwrap();
+        assert!(!json.contains("eta_ms"));
+    }
+
+    #[test]
+    fn test_format_eta() {
+        assert_eq!(format_eta(400), "1s");
+        assert_eq!(format_eta(45_000), "45s");
+        assert_eq!(format_eta(80_000), "1m 20s");
+        assert_eq!(format_eta(7_500_000), "2h 05m");
+    }
+
+    #[test]
+    fn test_json_lines_observer() {
+        let mut observer = JsonLinesObserver::new(Vec::new());
+        observer.on_event(&started("a"));
+        observer.on_event(&EngineEvent::Paused);
+
+        let output = String::from_utf8(observer.writer).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(first["event"], "step_started");
+        assert_eq!(first["step_id"], "a");
+        assert!(first["timestamp"].is_string());
+
+        let second: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(second["event"], "paused");
+    }
+
+    #[test]
+    fn test_timeline_observer() {
+        let mut observer = TimelineObserver::new();
+        observer.on_event(&started("a"));
+        observer.on_event(&EngineEvent::ResourceSample {
+            cpu_usage: 1.0,
+            memory_mb: 10,
+        });
+        observer.on_event(&EngineEvent::StepCompleted {
+            step_id: "a".to_string(),
+            duration_ms: 5,
+        });
+
+        let timeline = observer.timeline();
+        assert_eq!(timeline.get_events().len(), 2);
+        assert_eq!(timeline.get_samples().len(), 1);
+        assert!(timeline.get_durations().contains_key("a"));
+    }
+
+    #[test]
+    fn test_channel_observer() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut observer = ChannelObserver::new(tx);
+
+        observer.on_event(&started("a"));
+        assert_eq!(rx.try_recv().unwrap(), started("a"));
+    }
+
+    #[test]
+    fn test_closure_observer() {
+        let mut seen = Vec::new();
+        {
+            let mut observer = |event: &EngineEvent| seen.push(event.clone());
+            observer.on_event(&EngineEvent::Resumed);
+        }
+        assert_eq!(seen, vec![EngineEvent::Resumed]);
+    }
+}
@@ -0,0 +1,348 @@
+//! Step Executors
+//!
+//! An executor decides where a step's launch command runs:
+//!
+//! - [`LocalExecutor`](super::local::LocalExecutor): a child process on this
+//!   machine (the default)
+//! - [`SlurmExecutor`](super::slurm::SlurmExecutor): a batch job on a SLURM
+//!   cluster
+//! - [`SshExecutor`](super::remote::SshExecutor): a process on a remote host
+//!   reached over SSH
+//!
+//! Every executor works like a batch scheduler: a [`Job`] is submitted,
+//! waited on until it has finished and its [`JobOutcome`] collected. By
+//! default waiting polls the job; executors that can be notified when a
+//! job ends override [`Executor::wait`]. A job can be cancelled at any
+//! time. [`run_job`] drives a job through these stages and cancels it if
+//! the step's future is dropped, so the engine cancels steps on every
+//! executor by aborting their tasks.
+
+use std::error::Error;
+use std::fmt::Debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::process::{Output, Stdio};
+use std::time::Duration;
+
+use log::{debug, warn};
+use tokio::process::Command;
+
+use crate::monitoring::ProcessUsage;
+
+use super::scripts::ScriptLaunch;
+
+/// Future returned by [`Executor`] methods.
+pub type ExecutorFuture<'a, T> =
+    Pin<Box<dyn Future<Output = Result<T, Box<dyn Error + Send + Sync>>> + Send + 'a>>;
+
+/// A step's launch command and the resources it asks for.
+#[derive(Debug, Clone)]
+pub struct Job {
+    /// How the step's script is launched
+    pub launch: ScriptLaunch,
+    /// Number of CPUs the step uses
+    pub threads: usize,
+    /// Memory the step needs, e.g. `16G`
+    pub memory: Option<String>,
+    /// Maximum run time
+    pub timeout: Option<Duration>,
+    /// Input files, relative to the working directory unless absolute
+    pub inputs: Vec<String>,
+    /// Output files, relative to the working directory unless absolute
+    pub outputs: Vec<String>,
+}
+
+impl Job {
+    /// Returns the ID of the step the job runs.
+    pub fn step_id(&self) -> &str {
+        &self.launch.step_id
+    }
+}
+
+/// Executor-specific ID of a submitted job, e.g. a SLURM job ID.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JobId(pub String);
+
+impl std::fmt::Display for JobId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Where a submitted job is in its life cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    /// Waiting for resources
+    Queued,
+    /// Running
+    Running,
+    /// Finished; its outcome can be collected
+    Finished,
+}
+
+/// How a job ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    /// Exited with a code
+    Exited(i32),
+    /// Killed by a signal
+    Signaled(i32),
+    /// Killed after exceeding its time limit
+    TimedOut,
+    /// Ended by the executor, e.g. a SLURM `OUT_OF_MEMORY` or `NODE_FAIL`
+    Failed(String),
+}
+
+/// Result of a finished job.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JobOutcome {
+    /// How the job ended
+    pub status: JobStatus,
+    /// Everything the job wrote to stdout
+    pub stdout: String,
+    /// Everything the job wrote to stderr
+    pub stderr: String,
+    /// Resources used by the job, if the executor measures them
+    pub resources: ProcessUsage,
+}
+
+impl JobOutcome {
+    /// Returns `true` if the job exited with code 0.
+    pub fn success(&self) -> bool {
+        self.status == JobStatus::Exited(0)
+    }
+}
+
+/// Launches step jobs and reports on them.
+pub trait Executor: Debug + Send + Sync {
+    /// Short name shown in logs, e.g. `local` or `slurm`.
+    fn name(&self) -> &str;
+
+    /// Starts a job.
+    fn submit(&self, job: Job) -> ExecutorFuture<'_, JobId>;
+
+    /// Returns the current state of a submitted job.
+    fn poll<'a>(&'a self, id: &'a JobId) -> ExecutorFuture<'a, JobState>;
+
+    /// Stops a job. Best effort; called when a step is cancelled.
+    ///
+    /// Runs inside `Drop` on a runtime thread, so it must not wait for
+    /// helper commands; see [`spawn_cleanup`].
+    fn cancel(&self, id: &JobId);
+
+    /// Returns the outcome of a finished job and releases it.
+    fn collect<'a>(&'a self, id: &'a JobId) -> ExecutorFuture<'a, JobOutcome>;
+
+    /// Waits until a submitted job has finished.
+    ///
+    /// The default polls the job every [`Executor::poll_interval`].
+    fn wait<'a>(&'a self, id: &'a JobId) -> ExecutorFuture<'a, ()> {
+        Box::pin(async move {
+            while self.poll(id).await? != JobState::Finished {
+                tokio::time::sleep(self.poll_interval()).await;
+            }
+            Ok(())
+        })
+    }
+
+    /// How long to wait between polls.
+    fn poll_interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    /// Threads that running steps may use in total, or `None` if the
+    /// executor allocates CPUs itself.
+    fn max_threads(&self) -> Option<usize> {
+        Some(num_cpus::get())
+    }
+
+    /// Whether steps see the conda environments of this machine, so that
+    /// the engine should create them before the run.
+    fn shares_environments(&self) -> bool {
+        true
+    }
+}
+
+/// Submits a job, waits for it to finish and collects its outcome.
+///
+/// Dropping the returned future cancels the job.
+pub async fn run_job(
+    executor: &dyn Executor,
+    job: Job,
+) -> Result<JobOutcome, Box<dyn Error + Send + Sync>> {
+    let step_id = job.step_id().to_string();
+    let id = executor.submit(job).await?;
+    debug!("Submitted step '{}' as {} job {}", step_id, executor.name(), id);
+
+    let mut guard = CancelGuard {
+        executor,
+        id: Some(&id),
+    };
+
+    executor.wait(&id).await?;
+
+    let outcome = executor.collect(&id).await;
+    guard.id = None;
+    outcome
+}
+
+/// Runs a helper command (a scheduler or transfer tool) to completion.
+pub(super) async fn run_tool(cmd: &mut Command) -> Result<Output, Box<dyn Error + Send + Sync>> {
+    let program = cmd.as_std().get_program().to_string_lossy().into_owned();
+    cmd.stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {}: {}", program, e).into())
+}
+
+/// Starts a cleanup command (e.g. `scancel`) without waiting for it.
+///
+/// The command is started right away, so it completes even if rustrunner
+/// exits next; a background thread reaps it and logs a failure.
+pub fn spawn_cleanup(mut cmd: std::process::Command) {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+
+    match child {
+        Ok(mut child) => {
+            std::thread::spawn(move || match child.wait() {
+                Ok(status) if status.success() => {}
+                Ok(status) => warn!("{} failed: {}", program, status),
+                Err(e) => warn!("Failed to wait for {}: {}", program, e),
+            });
+        }
+        Err(e) => warn!("Failed to run {}: {}", program, e),
+    }
+}
+
+/// Turns a failed helper command into an error.
+pub(super) fn check_output(name: &str, output: &Output) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(format!("{} failed: {}", name, stderr.trim()).into())
+}
+
+/// Quotes a word for a POSIX shell.
+pub fn shell_quote(word: &str) -> String {
+    format!("'{}'", word.replace('\'', r"'\''"))
+}
+
+/// Cancels a job when dropped, unless it finished.
+struct CancelGuard<'a> {
+    executor: &'a dyn Executor,
+    id: Option<&'a JobId>,
+}
+
+impl Drop for CancelGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            debug!("Cancelling {} job {}", self.executor.name(), id);
+            self.executor.cancel(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Executor finishing every job after a number of polls.
+    #[derive(Debug, Default)]
+    struct CountingExecutor {
+        finish_after: usize,
+        polls: Mutex<usize>,
+        cancelled: Mutex<Vec<String>>,
+    }
+
+    impl Executor for CountingExecutor {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn submit(&self, job: Job) -> ExecutorFuture<'_, JobId> {
+            Box::pin(async move { Ok(JobId(job.launch.step_id)) })
+        }
+
+        fn poll<'a>(&'a self, _id: &'a JobId) -> ExecutorFuture<'a, JobState> {
+            Box::pin(async move {
+                let mut polls = self.polls.lock().unwrap();
+                *polls += 1;
+                Ok(if *polls < self.finish_after {
+                    JobState::Queued
+                } else {
+                    JobState::Finished
+                })
+            })
+        }
+
+        fn cancel(&self, id: &JobId) {
+            self.cancelled.lock().unwrap().push(id.0.clone());
+        }
+
+        fn collect<'a>(&'a self, _id: &'a JobId) -> ExecutorFuture<'a, JobOutcome> {
+            Box::pin(async move {
+                Ok(JobOutcome {
+                    status: JobStatus::Exited(0),
+                    stdout: "done\n".to_string(),
+                    stderr: String::new(),
+                    resources: ProcessUsage::default(),
+                })
+            })
+        }
+
+        fn poll_interval(&self) -> Duration {
+            Duration::from_millis(1)
+        }
+    }
+
+    fn job(step_id: &str) -> Job {
+        let launch = ScriptLaunch {
+            step_id: step_id.to_string(),
+            script_path: format!("/s/{}.sh", step_id).into(),
+            program: "bash".to_string(),
+            args: vec![format!("/s/{}.sh", step_id)],
+            working_dir: None,
+            env: Default::default(),
+        };
+        Job {
+            launch,
+            threads: 1,
+            memory: None,
+            timeout: None,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_job_polls_until_finished() {
+        let executor = CountingExecutor {
+            finish_after: 3,
+            ..Default::default()
+        };
+        let outcome = run_job(&executor, job("a")).await.unwrap();
+
+        assert!(outcome.success());
+        assert_eq!(*executor.polls.lock().unwrap(), 3);
+        assert!(executor.cancelled.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_dropped_job_is_cancelled() {
+        let executor = CountingExecutor {
+            finish_after: usize::MAX,
+            ..Default::default()
+        };
+
+        let result = tokio::time::timeout(Duration::from_millis(20), run_job(&executor, job("b"))).await;
+        assert!(result.is_err());
+        assert_eq!(*executor.cancelled.lock().unwrap(), vec!["b"]);
+    }
+}
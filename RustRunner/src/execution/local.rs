@@ -0,0 +1,368 @@
+//! Local Executor
+//!
+//! Runs step jobs as child processes on this machine. Each job gets its own
+//! process group so that cancelling or timing out a step kills the tool and
+//! everything it started, not just the launching shell or `micromamba run`.
+//! Output is streamed to the debug log while the job runs, and the process
+//! tree is sampled for CPU time, peak memory and I/O.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::{debug, warn};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::monitoring::ProcessTreeMonitor;
+
+use super::executor::{Executor, ExecutorFuture, Job, JobId, JobOutcome, JobState, JobStatus};
+
+/// Interval between resource samples of a running step's process tree.
+const RESOURCE_SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Result of a job's task.
+type JobResult = Result<JobOutcome, Box<dyn Error + Send + Sync>>;
+
+/// A submitted job's task.
+#[derive(Debug)]
+struct LocalJob {
+    task: JoinHandle<JobResult>,
+    /// Set to `true` when the task has finished
+    finished: watch::Receiver<bool>,
+}
+
+/// Runs jobs as child processes on this machine.
+#[derive(Debug, Default)]
+pub struct LocalExecutor {
+    next_id: AtomicU64,
+    jobs: Mutex<HashMap<JobId, LocalJob>>,
+}
+
+impl LocalExecutor {
+    /// Creates a local executor.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Executor for LocalExecutor {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn submit(&self, job: Job) -> ExecutorFuture<'_, JobId> {
+        Box::pin(async move {
+            let (child, guard) = spawn_command(Command::from(job.launch.command()))?;
+            let id = JobId(self.next_id.fetch_add(1, Ordering::Relaxed).to_string());
+
+            let step_id = job.launch.step_id;
+            let (done, finished) = watch::channel(false);
+            let task = tokio::spawn(async move {
+                let result = wait_child(child, guard, &step_id, job.timeout).await;
+                let _ = done.send(true);
+                result
+            });
+            self.jobs
+                .lock()
+                .unwrap()
+                .insert(id.clone(), LocalJob { task, finished });
+            Ok(id)
+        })
+    }
+
+    fn poll<'a>(&'a self, id: &'a JobId) -> ExecutorFuture<'a, JobState> {
+        Box::pin(async move {
+            match self.jobs.lock().unwrap().get(id) {
+                Some(job) if job.task.is_finished() => Ok(JobState::Finished),
+                Some(_) => Ok(JobState::Running),
+                None => Err(format!("Unknown local job {}", id).into()),
+            }
+        })
+    }
+
+    fn wait<'a>(&'a self, id: &'a JobId) -> ExecutorFuture<'a, ()> {
+        Box::pin(async move {
+            let mut finished = self
+                .jobs
+                .lock()
+                .unwrap()
+                .get(id)
+                .map(|job| job.finished.clone())
+                .ok_or_else(|| format!("Unknown local job {}", id))?;
+
+            // The sender is dropped without sending if the job is cancelled
+            finished
+                .wait_for(|done| *done)
+                .await
+                .map_err(|_| format!("Local job {} was cancelled", id))?;
+            Ok(())
+        })
+    }
+
+    fn cancel(&self, id: &JobId) {
+        // Dropping the task's future kills the child and its process group
+        if let Some(job) = self.jobs.lock().unwrap().remove(id) {
+            job.task.abort();
+        }
+    }
+
+    fn collect<'a>(&'a self, id: &'a JobId) -> ExecutorFuture<'a, JobOutcome> {
+        Box::pin(async move {
+            let job = self
+                .jobs
+                .lock()
+                .unwrap()
+                .remove(id)
+                .ok_or_else(|| format!("Unknown local job {}", id))?;
+            job.task.await?
+        })
+    }
+}
+
+/// Spawns a command in its own process group.
+fn spawn_command(
+    mut cmd: Command,
+) -> Result<(Child, ProcessGroupGuard), Box<dyn Error + Send + Sync>> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    let child = cmd.spawn()?;
+    let guard = ProcessGroupGuard::new(child.id());
+    Ok((child, guard))
+}
+
+/// Waits for a spawned job to finish, killing it after `timeout`.
+///
+/// Stdout and stderr are streamed line by line to the debug log while the
+/// process runs, and collected for error reporting.
+async fn wait_child(
+    mut child: Child,
+    mut guard: ProcessGroupGuard,
+    step_name: &str,
+    timeout: Option<Duration>,
+) -> JobResult {
+    let mut tree = ProcessTreeMonitor::new(child.id().ok_or("Failed to get process ID")?);
+
+    let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
+
+    let wait = async {
+        match timeout {
+            Some(limit) => match tokio::time::timeout(limit, child.wait()).await {
+                Ok(status) => status.map(Some),
+                Err(_) => {
+                    // Kill the whole group so grandchildren release the pipes
+                    guard.kill();
+                    child.kill().await.map(|_| None)
+                }
+            },
+            None => child.wait().await.map(Some),
+        }
+    };
+
+    // Sample until the process exits; the first tick fires immediately
+    let wait_and_sample = async {
+        let mut ticker = tokio::time::interval(RESOURCE_SAMPLE_INTERVAL);
+        tokio::pin!(wait);
+        loop {
+            tokio::select! {
+                status = &mut wait => break status,
+                _ = ticker.tick() => tree.sample(),
+            }
+        }
+    };
+
+    let (status, stdout, stderr) = tokio::join!(
+        wait_and_sample,
+        collect_lines(stdout, step_name),
+        collect_lines(stderr, step_name),
+    );
+
+    guard.disarm();
+
+    Ok(JobOutcome {
+        status: match status? {
+            Some(status) => job_status(&status),
+            None => JobStatus::TimedOut,
+        },
+        stdout,
+        stderr,
+        resources: tree.usage(),
+    })
+}
+
+/// Converts a process exit status.
+fn job_status(status: &ExitStatus) -> JobStatus {
+    match (status.code(), exit_signal(status)) {
+        (Some(code), _) => JobStatus::Exited(code),
+        (None, Some(signal)) => JobStatus::Signaled(signal),
+        (None, None) => JobStatus::Failed(status.to_string()),
+    }
+}
+
+/// Returns the signal that terminated a process, if any.
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+/// Returns the signal that terminated a process, if any.
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+/// Reads a child pipe to completion, logging each line as it arrives.
+async fn collect_lines<R: AsyncRead + Unpin>(reader: R, step_name: &str) -> String {
+    let mut lines = BufReader::new(reader).lines();
+    let mut collected = String::new();
+
+    loop {
+        match lines.next_line().await {
+            Ok(Some(line)) => {
+                debug!("[{}] {}", step_name, line);
+                collected.push_str(&line);
+                collected.push('\n');
+            }
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Failed to read output of step '{}': {}", step_name, e);
+                break;
+            }
+        }
+    }
+
+    collected
+}
+
+/// Kills a step's process group when dropped.
+///
+/// `kill_on_drop` only reaches the direct child (bash or micromamba), so this
+/// guard makes sure the actual tool processes die with a cancelled step.
+struct ProcessGroupGuard {
+    pgid: Option<u32>,
+}
+
+impl ProcessGroupGuard {
+    fn new(pgid: Option<u32>) -> Self {
+        Self { pgid }
+    }
+
+    /// Kills the process group immediately.
+    fn kill(&mut self) {
+        if let Some(pgid) = self.pgid.take() {
+            kill_process_group(pgid);
+        }
+    }
+
+    /// Leaves the process group alone on drop (the step finished normally).
+    fn disarm(&mut self) {
+        self.pgid = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+/// Sends SIGKILL to every process in a process group.
+fn kill_process_group(pgid: u32) {
+    #[cfg(unix)]
+    {
+        let result = std::process::Command::new("kill")
+            .arg("-KILL")
+            .arg("--")
+            .arg(format!("-{}", pgid))
+            .stderr(Stdio::null())
+            .status();
+
+        if let Err(e) = result {
+            warn!("Failed to kill process group {}: {}", pgid, e);
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = pgid;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::executor::run_job;
+    use crate::execution::ScriptLaunch;
+
+    fn job(step_id: &str, script: &str, timeout: Option<Duration>) -> Job {
+        Job {
+            launch: ScriptLaunch {
+                step_id: step_id.to_string(),
+                script_path: format!("{}.sh", step_id).into(),
+                program: "bash".to_string(),
+                args: vec!["-c".to_string(), script.to_string()],
+                working_dir: None,
+                env: [("GREETING".to_string(), "hello".to_string())].into(),
+            },
+            threads: 1,
+            memory: None,
+            timeout,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_local_job_outcome() {
+        let executor = LocalExecutor::new();
+
+        let outcome = run_job(&executor, job("a", "echo $GREETING; echo err >&2", None))
+            .await
+            .unwrap();
+        assert!(outcome.success());
+        assert_eq!(outcome.stdout, "hello\n");
+        assert_eq!(outcome.stderr, "err\n");
+
+        let outcome = run_job(&executor, job("b", "exit 4", None)).await.unwrap();
+        assert_eq!(outcome.status, JobStatus::Exited(4));
+
+        let outcome = run_job(&executor, job("c", "sleep 30", Some(Duration::from_millis(100))))
+            .await
+            .unwrap();
+        assert_eq!(outcome.status, JobStatus::TimedOut);
+        assert!(executor.jobs.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_local_job_wait() {
+        let executor = LocalExecutor::new();
+        let id = executor.submit(job("w", "sleep 0.1", None)).await.unwrap();
+
+        executor.wait(&id).await.unwrap();
+        assert_eq!(executor.poll(&id).await.unwrap(), JobState::Finished);
+        assert!(executor.collect(&id).await.unwrap().success());
+    }
+
+    #[tokio::test]
+    async fn test_local_job_cancel() {
+        let executor = LocalExecutor::new();
+        let id = executor.submit(job("d", "sleep 30", None)).await.unwrap();
+        assert_eq!(executor.poll(&id).await.unwrap(), JobState::Running);
+
+        executor.cancel(&id);
+        assert!(executor.poll(&id).await.is_err());
+        assert!(executor.wait(&id).await.is_err());
+        assert!(executor.collect(&id).await.is_err());
+    }
+}
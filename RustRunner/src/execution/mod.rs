@@ -6,10 +6,41 @@
 //!
 //! # Architecture
 //!
+//! - [`backend`]: What a step's script runs inside (host, conda, container)
+//! - [`container`]: Podman, docker and apptainer command lines
 //! - [`engine`]: Main execution engine orchestrating workflow runs
+//! - [`error`]: Structured step failure information
+//! - [`events`]: Progress events and observers for embedding the engine
+//! - [`executor`]: Where step jobs run: submit, wait, cancel and collect
+//! - [`local`]: Child processes on this machine
+//! - [`remote`]: Steps on another host over SSH
+//! - [`slurm`]: Batch jobs on a SLURM cluster
+//! - [`scripts`]: Per-run step scripts and their launch records
 //! - [`step`]: Individual step execution logic
 
+pub mod backend;
+pub mod container;
 pub mod engine;
+pub mod error;
+pub mod events;
+pub mod executor;
+pub mod local;
+pub mod remote;
+pub mod scripts;
+pub mod slurm;
 pub mod step;
 
-pub use engine::Engine;
+pub use backend::Backend;
+pub use container::{ContainerEngine, ContainerRuntime};
+pub use engine::{Engine, EngineControl, EngineHandle};
+pub use error::StepError;
+pub use events::{
+    ChannelObserver, ConsoleObserver, EngineEvent, EngineObserver, JsonLinesObserver,
+    TimelineObserver,
+};
+pub use executor::{Executor, Job, JobOutcome, JobStatus};
+pub use local::LocalExecutor;
+pub use remote::SshExecutor;
+pub use scripts::ScriptLaunch;
+pub use slurm::SlurmExecutor;
+pub use step::{StepContext, StepOutput};
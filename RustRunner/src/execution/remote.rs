@@ -0,0 +1,503 @@
+//! Remote Executor
+//!
+//! Runs steps on another host over SSH, for workflows whose data lives on a
+//! server while the GUI runs on a laptop. For each step:
+//!
+//! 1. the step's script, and an inline script's JSON sidecar, are copied to
+//!    `<dir>/.rustrunner/runs/<run-id>/scripts/` on the host, mirroring the
+//!    local run directory, with local paths in them rewritten to the remote
+//!    ones
+//! 2. relative input files are copied below the remote working directory
+//! 3. the launch command runs as `ssh <host> 'cd <dir> && exec bash <script>'`
+//! 4. after a successful run, relative output files are copied back
+//! 5. the staged scripts are removed from the host
+//!
+//! Absolute input and output paths are left alone and must exist on the
+//! host. Files are copied with `scp`. Programs given by absolute local
+//! paths, such as the bundled micromamba, are looked up on the host's PATH,
+//! and conda environments must exist there under the same names.
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use log::debug;
+use tokio::process::Command;
+
+use crate::workflow::fingerprint::fingerprint;
+use crate::workflow::RemoteConfig;
+
+use super::executor::{
+    check_output, run_tool, shell_quote, spawn_cleanup, Executor, ExecutorFuture, Job, JobId,
+    JobOutcome, JobState,
+};
+use super::local::LocalExecutor;
+use super::scripts::{self, ScriptLaunch, RUNS_DIR};
+
+/// Options for every `ssh` and `scp` call: never prompt for a password.
+const SSH_OPTIONS: &[&str] = &["-o", "BatchMode=yes", "-o", "ConnectTimeout=30"];
+
+/// Runs steps on a remote host over SSH.
+#[derive(Debug)]
+pub struct SshExecutor {
+    remote: RemoteConfig,
+    ssh: PathBuf,
+    scp: PathBuf,
+    local: LocalExecutor,
+    jobs: Mutex<HashMap<JobId, RemoteJob>>,
+}
+
+/// A step running on the host.
+#[derive(Debug)]
+struct RemoteJob {
+    /// Local working directory outputs are copied back to
+    local_dir: PathBuf,
+    /// Output files to retrieve
+    outputs: Vec<String>,
+    /// Path of the step script on the host
+    script: String,
+    /// Files staged into the run's script directory on the host
+    staged: Vec<PathBuf>,
+}
+
+impl SshExecutor {
+    /// Creates an executor for a host, using `ssh` and `scp` from PATH.
+    pub fn new(remote: RemoteConfig) -> Self {
+        Self {
+            remote,
+            ssh: PathBuf::from("ssh"),
+            scp: PathBuf::from("scp"),
+            local: LocalExecutor::new(),
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Uses a specific `ssh` program.
+    pub fn with_ssh_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.ssh = program.into();
+        self
+    }
+
+    /// Uses a specific `scp` program.
+    pub fn with_scp_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.scp = program.into();
+        self
+    }
+
+    /// Returns the host and directory steps run in.
+    pub fn remote(&self) -> &RemoteConfig {
+        &self.remote
+    }
+
+    /// Returns the directory on the host that a run's scripts are copied to.
+    ///
+    /// Mirrors the local `.rustrunner/runs/<run-id>/scripts`, so concurrent
+    /// runs never share script files. Scripts outside a run directory get
+    /// a run ID derived from their local directory.
+    fn remote_script_dir(&self, script_dir: &Path) -> PathBuf {
+        let run_dir = script_dir.parent().and_then(Path::file_name);
+        let run_id = match (script_dir.file_name(), run_dir) {
+            (Some(name), Some(run_id)) if name == "scripts" => {
+                run_id.to_string_lossy().into_owned()
+            }
+            _ => fingerprint([script_dir.to_string_lossy()]),
+        };
+        scripts::script_dir(&self.remote.dir.join(RUNS_DIR), &run_id)
+    }
+
+    /// Returns `host:path` for `scp`.
+    fn remote_path(&self, path: &Path) -> String {
+        format!("{}:{}", self.remote.host, path.display())
+    }
+
+    /// Rewrites local paths in a value to their counterparts on the host.
+    ///
+    /// The script directory is mapped first, since it usually lies within
+    /// the working directory.
+    fn map_paths(&self, value: &str, local_dir: &Path, script_dir: &Path) -> String {
+        value
+            .replace(
+                &*script_dir.to_string_lossy(),
+                &self.remote_script_dir(script_dir).to_string_lossy(),
+            )
+            .replace(&*local_dir.to_string_lossy(), &self.remote.dir.to_string_lossy())
+    }
+
+    /// Builds an `ssh` command running a shell command on the host.
+    fn ssh_command(&self, command: &str) -> std::process::Command {
+        let mut cmd = std::process::Command::new(&self.ssh);
+        cmd.args(SSH_OPTIONS).arg(&self.remote.host).arg(command);
+        cmd
+    }
+
+    /// Runs a shell command on the host.
+    async fn run_remote(&self, command: &str) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let output = run_tool(&mut Command::from(self.ssh_command(command))).await?;
+        check_output("ssh", &output)
+    }
+
+    /// Copies files or directories with `scp`.
+    async fn copy(
+        &self,
+        sources: &[impl AsRef<std::ffi::OsStr>],
+        target: impl AsRef<std::ffi::OsStr>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let output = run_tool(
+            Command::new(&self.scp)
+                .args(SSH_OPTIONS)
+                .args(["-q", "-r", "-p"])
+                .args(sources)
+                .arg(target),
+        )
+        .await?;
+        check_output("scp", &output)
+    }
+
+    /// Copies a job's scripts and inputs to the host.
+    ///
+    /// Returns the command running the job there and the staged scripts.
+    async fn stage(
+        &self,
+        job: &Job,
+        local_dir: &Path,
+    ) -> Result<(String, Vec<PathBuf>), Box<dyn Error + Send + Sync>> {
+        let launch = &job.launch;
+        let script_dir = launch
+            .script_path
+            .parent()
+            .ok_or("Step script has no directory")?;
+        let remote_script_dir = self.remote_script_dir(script_dir);
+        let map = |value: &str| self.map_paths(value, local_dir, script_dir);
+
+        // Directories for the scripts and for relative inputs and outputs
+        let mut dirs = vec![remote_script_dir.clone()];
+        for file in job.inputs.iter().chain(&job.outputs) {
+            match relative(file).and_then(Path::parent) {
+                Some(parent) if !parent.as_os_str().is_empty() => dirs.push(self.remote.dir.join(parent)),
+                _ => {}
+            }
+        }
+        dirs.sort();
+        dirs.dedup();
+        let dirs: Vec<String> = dirs.iter().map(|d| shell_quote(&d.to_string_lossy())).collect();
+        self.run_remote(&format!("mkdir -p {}", dirs.join(" "))).await?;
+
+        // Scripts referenced by the launch command, with their paths mapped
+        let mut staged = Vec::new();
+        for value in launch.args.iter().chain(launch.env.values()) {
+            let path = Path::new(value);
+            let name = path.file_name().ok_or("Invalid script path")?;
+            let target = remote_script_dir.join(name);
+            if path.parent() != Some(script_dir) || !path.is_file() || staged.contains(&target) {
+                continue;
+            }
+            let copy = path.with_extension("remote");
+            fs::write(&copy, map(&fs::read_to_string(path)?))?;
+            let result = self.copy(&[&copy], self.remote_path(&target)).await;
+            let _ = fs::remove_file(&copy);
+            result?;
+            staged.push(target);
+        }
+
+        // Relative inputs; missing ones may already be on the host
+        for input in job.inputs.iter().filter_map(|input| relative(input)) {
+            let local = local_dir.join(input);
+            if !local.exists() {
+                debug!("Input {} not found locally; expecting it on the host", input.display());
+                continue;
+            }
+            self.copy(&[&local], self.remote_path(&self.remote.dir.join(input))).await?;
+        }
+
+        // cd && export ... && exec program args
+        let program = Path::new(&launch.program);
+        let program = match program.file_name() {
+            Some(name) if program.is_absolute() => name.to_string_lossy().into_owned(),
+            _ => launch.program.clone(),
+        };
+        let mut command = format!("cd {} &&", shell_quote(&self.remote.dir.to_string_lossy()));
+        for (key, value) in &launch.env {
+            // The host's micromamba uses its own root prefix
+            if key == "MAMBA_ROOT_PREFIX" {
+                continue;
+            }
+            command.push_str(&format!(" export {}={} &&", key, shell_quote(&map(value))));
+        }
+        command.push_str(" exec ");
+        command.push_str(&shell_quote(&program));
+        for arg in &launch.args {
+            command.push(' ');
+            command.push_str(&shell_quote(&map(arg)));
+        }
+
+        Ok((command, staged))
+    }
+
+    /// Copies a successful job's relative outputs back from the host.
+    async fn retrieve_outputs(
+        &self,
+        job: &RemoteJob,
+        success: bool,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if !success {
+            return Ok(());
+        }
+
+        for output in job.outputs.iter().filter_map(|output| relative(output)) {
+            let source = self.remote_path(&self.remote.dir.join(output));
+            self.copy(&[source], job.local_dir.join(output)).await.map_err(|e| {
+                format!(
+                    "Failed to retrieve output '{}' from {}: {}",
+                    output.display(),
+                    self.remote.host,
+                    e
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Removes a job's staged scripts from the host.
+    async fn unstage(&self, job: &RemoteJob) {
+        if let Err(e) = self.run_remote(&unstage_command(job)).await {
+            debug!("Failed to remove scripts from {}: {}", self.remote.host, e);
+        }
+    }
+}
+
+impl Executor for SshExecutor {
+    fn name(&self) -> &str {
+        "ssh"
+    }
+
+    fn submit(&self, job: Job) -> ExecutorFuture<'_, JobId> {
+        Box::pin(async move {
+            let local_dir = match job.launch.working_dir {
+                Some(ref dir) => dir.clone(),
+                None => std::env::current_dir()?,
+            };
+            let (command, staged) = self.stage(&job, &local_dir).await?;
+            debug!("Running step '{}' on {}: {}", job.step_id(), self.remote.host, command);
+
+            let script_dir = job.launch.script_path.parent().unwrap_or(Path::new(""));
+            let remote_job = RemoteJob {
+                script: self.map_paths(&job.launch.script_path.to_string_lossy(), &local_dir, script_dir),
+                local_dir,
+                outputs: job.outputs.clone(),
+                staged,
+            };
+
+            let ssh = self.ssh_command(&command);
+            let launch = ScriptLaunch {
+                program: self.ssh.to_string_lossy().into_owned(),
+                args: ssh.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect(),
+                working_dir: None,
+                env: BTreeMap::new(),
+                ..job.launch
+            };
+            let id = self.local.submit(Job { launch, ..job }).await?;
+
+            self.jobs.lock().unwrap().insert(id.clone(), remote_job);
+            Ok(id)
+        })
+    }
+
+    fn poll<'a>(&'a self, id: &'a JobId) -> ExecutorFuture<'a, JobState> {
+        self.local.poll(id)
+    }
+
+    fn wait<'a>(&'a self, id: &'a JobId) -> ExecutorFuture<'a, ()> {
+        self.local.wait(id)
+    }
+
+    fn cancel(&self, id: &JobId) {
+        // Closing the connection doesn't reliably stop the remote process
+        self.local.cancel(id);
+        let Some(job) = self.jobs.lock().unwrap().remove(id) else {
+            return;
+        };
+
+        debug!("Stopping job {} on {}", id, self.remote.host);
+        let command = format!("pkill -f {}; {}", shell_quote(&job.script), unstage_command(&job));
+        spawn_cleanup(self.ssh_command(&command));
+    }
+
+    fn collect<'a>(&'a self, id: &'a JobId) -> ExecutorFuture<'a, JobOutcome> {
+        Box::pin(async move {
+            let outcome = self.local.collect(id).await?;
+            let job = self
+                .jobs
+                .lock()
+                .unwrap()
+                .remove(id)
+                .ok_or_else(|| format!("Unknown remote job {}", id))?;
+
+            let retrieved = self.retrieve_outputs(&job, outcome.success()).await;
+            self.unstage(&job).await;
+            retrieved.map(|()| outcome)
+        })
+    }
+
+    fn max_threads(&self) -> Option<usize> {
+        // The host's CPUs aren't known here
+        None
+    }
+
+    fn shares_environments(&self) -> bool {
+        false
+    }
+}
+
+/// Returns a shell command removing a job's staged scripts, and its run's
+/// script directory once no other step uses it.
+fn unstage_command(job: &RemoteJob) -> String {
+    let Some(script_dir) = job.staged.first().and_then(|path| path.parent()) else {
+        return "true".to_string();
+    };
+    let files: Vec<String> = job
+        .staged
+        .iter()
+        .map(|path| shell_quote(&path.to_string_lossy()))
+        .collect();
+    let dirs = [script_dir, script_dir.parent().unwrap_or(script_dir)]
+        .map(|dir| shell_quote(&dir.to_string_lossy()));
+
+    format!("rm -f {}; rmdir {} 2>/dev/null; true", files.join(" "), dirs.join(" "))
+}
+
+/// Returns a file path if it is relative to the working directory.
+fn relative(file: &str) -> Option<&Path> {
+    let path = Path::new(file);
+    path.is_relative().then_some(path)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::execution::step::{execute_step, StepContext};
+    use crate::workflow::Step;
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    /// Writes stub `ssh` and `scp` commands acting on the local machine.
+    ///
+    /// `ssh` runs its command with `sh`; `scp` drops `host:` prefixes and
+    /// copies with `cp`.
+    fn stub_ssh(dir: &Path) -> SshExecutor {
+        let stubs = [
+            (
+                "ssh",
+                r#"while [ "$1" = -o ]; do shift 2; done
+echo "ssh $*" >> "$STUB/calls.log"
+shift
+exec sh -c "$*""#,
+            ),
+            (
+                "scp",
+                r#"files=""
+while [ $# -gt 0 ]; do
+  case "$1" in
+    -o) shift ;;
+    -*) ;;
+    *) files="$files ${1#*:}" ;;
+  esac
+  shift
+done
+echo "scp$files" >> "$STUB/calls.log"
+exec cp -r $files"#,
+            ),
+        ];
+
+        for (name, body) in stubs {
+            let path = dir.join(name);
+            fs::write(&path, format!("#!/bin/sh\nSTUB={}\n{}\n", dir.display(), body)).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let remote_dir = dir.join("server");
+        fs::create_dir_all(&remote_dir).unwrap();
+        SshExecutor::new(RemoteConfig::new("lab", remote_dir))
+            .with_ssh_program(dir.join("ssh"))
+            .with_scp_program(dir.join("scp"))
+    }
+
+    #[test]
+    fn test_map_paths() {
+        let executor = SshExecutor::new(RemoteConfig::new("lab", "/srv/project"));
+        let local = Path::new("/home/me/project");
+        let scripts = Path::new("/home/me/project/.rustrunner/runs/r1/scripts");
+
+        assert_eq!(
+            executor.map_paths("/home/me/project/.rustrunner/runs/r1/scripts/a.sh", local, scripts),
+            "/srv/project/.rustrunner/runs/r1/scripts/a.sh"
+        );
+        assert_eq!(
+            executor.map_paths("/home/me/project:/home/me/project", local, scripts),
+            "/srv/project:/srv/project"
+        );
+        assert_eq!(executor.map_paths("reads.fq", local, scripts), "reads.fq");
+
+        // Each run stages into its own directory
+        let other = Path::new("/home/me/project/.rustrunner/runs/r2/scripts");
+        assert_ne!(executor.remote_script_dir(scripts), executor.remote_script_dir(other));
+    }
+
+    #[tokio::test]
+    async fn test_execute_step_on_remote_host() {
+        let temp_dir = tempdir().unwrap();
+        let executor = stub_ssh(temp_dir.path());
+        let server = executor.remote().dir.clone();
+
+        let local = temp_dir.path().join("laptop");
+        fs::create_dir_all(&local).unwrap();
+        fs::write(local.join("reads.txt"), "a\nb\n").unwrap();
+
+        let context = StepContext::new(local.join(".rustrunner/runs/r1/scripts"))
+            .with_working_dir(Some(local.clone()))
+            .with_executor(Arc::new(executor));
+        let step = Step::new("count", "bash", "pwd; echo $0; wc -l < {input} > {output}")
+            .with_input("reads.txt")
+            .with_output("counts/reads.txt");
+
+        let output = execute_step(&step, &context).await.unwrap();
+
+        // The step ran the run's staged script in the remote directory on
+        // the staged input...
+        let script = server.join(".rustrunner/runs/r1/scripts/count.sh");
+        assert_eq!(
+            output.stdout_tail,
+            vec![server.display().to_string(), script.display().to_string()]
+        );
+        assert_eq!(fs::read_to_string(server.join("reads.txt")).unwrap(), "a\nb\n");
+
+        // ...and its output was copied back
+        assert_eq!(fs::read_to_string(local.join("counts/reads.txt")).unwrap().trim(), "2");
+
+        // ...and the run's scripts were removed from the host
+        assert!(!server.join(".rustrunner/runs/r1").exists());
+
+        let calls = fs::read_to_string(temp_dir.path().join("calls.log")).unwrap();
+        assert!(calls.contains("ssh lab mkdir -p"), "{}", calls);
+    }
+
+    #[tokio::test]
+    async fn test_failed_remote_step_keeps_outputs_remote() {
+        let temp_dir = tempdir().unwrap();
+        let executor = stub_ssh(temp_dir.path());
+        let server = executor.remote().dir.clone();
+
+        let local = temp_dir.path().join("laptop");
+        let context = StepContext::new(local.join(".rustrunner/runs/r1/scripts"))
+            .with_working_dir(Some(local.clone()))
+            .with_executor(Arc::new(executor));
+        let step = Step::new("fail", "bash", "echo partial > {output}; exit 2").with_output("out.txt");
+
+        let error = execute_step(&step, &context).await.unwrap_err();
+        assert_eq!(error.exit_code, Some(2));
+        assert!(server.join("out.txt").exists());
+        assert!(!local.join("out.txt").exists());
+    }
+}
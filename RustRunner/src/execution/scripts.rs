@@ -0,0 +1,245 @@
+//! Step Scripts
+//!
+//! Every run writes its generated step scripts into its own directory,
+//! `.rustrunner/runs/<run-id>/scripts/`, so concurrent workflows never
+//! share script files. Next to each `<step>.sh` a `<step>.json` launch
+//! record stores the exact program, arguments, working directory and
+//! environment variables the step was started with, which lets
+//! `rustrunner rerun-step` re-execute the script by hand.
+//!
+//! Scripts of successful steps are removed unless the engine is asked to
+//! keep them; scripts of failed steps are always kept.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+/// Default directory holding one subdirectory per run.
+pub const RUNS_DIR: &str = ".rustrunner/runs";
+
+/// Returns the directory of a run.
+pub fn run_dir(runs_dir: &Path, run_id: &str) -> PathBuf {
+    runs_dir.join(run_id)
+}
+
+/// Returns the script directory of a run.
+pub fn script_dir(runs_dir: &Path, run_id: &str) -> PathBuf {
+    run_dir(runs_dir, run_id).join("scripts")
+}
+
+/// Returns the script path for a step within a script directory.
+pub fn script_path(script_dir: &Path, step_id: &str, extension: &str) -> PathBuf {
+    script_dir.join(format!("{}.{}", step_id, extension))
+}
+
+/// Removes a run's script directory (and the run directory) if empty.
+pub fn remove_if_empty(runs_dir: &Path, run_id: &str) {
+    // Fails harmlessly when the directory still holds kept scripts
+    let _ = fs::remove_dir(script_dir(runs_dir, run_id));
+    let _ = fs::remove_dir(run_dir(runs_dir, run_id));
+}
+
+/// How a step's script was launched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptLaunch {
+    /// ID of the step
+    pub step_id: String,
+
+    /// The generated script
+    pub script_path: PathBuf,
+
+    /// Program that ran the script (bash, micromamba, ...)
+    pub program: String,
+
+    /// Arguments passed to the program
+    pub args: Vec<String>,
+
+    /// Directory the step ran in, if not the current one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<PathBuf>,
+
+    /// Environment variables set for the step
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+impl ScriptLaunch {
+    /// Records how a command will launch a step's script.
+    pub fn from_command(step_id: &str, script_path: &Path, cmd: &Command) -> Self {
+        let cmd = cmd.as_std();
+        let lossy = |s: &std::ffi::OsStr| s.to_string_lossy().into_owned();
+
+        Self {
+            step_id: step_id.to_string(),
+            script_path: script_path.to_path_buf(),
+            program: lossy(cmd.get_program()),
+            args: cmd.get_args().map(lossy).collect(),
+            working_dir: cmd.get_current_dir().map(Path::to_path_buf),
+            env: cmd
+                .get_envs()
+                .filter_map(|(key, value)| Some((lossy(key), lossy(value?))))
+                .collect(),
+        }
+    }
+
+    /// Path of the launch record belonging to a script.
+    pub fn record_path(script_path: &Path) -> PathBuf {
+        script_path.with_extension("json")
+    }
+
+    /// Writes the launch record next to the script.
+    pub fn save(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(Self::record_path(&self.script_path), json)?;
+        Ok(())
+    }
+
+    /// Loads a launch record.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Finds the launch record of a step.
+    ///
+    /// Looks in the given run, or in the most recent run that kept a
+    /// script for the step.
+    pub fn find(
+        runs_dir: &Path,
+        step_id: &str,
+        run_id: Option<&str>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let record_name = format!("{}.json", step_id);
+
+        if let Some(run_id) = run_id {
+            let path = script_dir(runs_dir, run_id).join(&record_name);
+            if !path.exists() {
+                return Err(format!("Run '{}' has no script for step '{}'", run_id, step_id).into());
+            }
+            return Self::load(&path);
+        }
+
+        let mut runs: Vec<String> = fs::read_dir(runs_dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // Run IDs start with a timestamp, so they sort chronologically
+        runs.sort();
+
+        runs.iter()
+            .rev()
+            .map(|run| script_dir(runs_dir, run).join(&record_name))
+            .find(|path| path.exists())
+            .map(|path| Self::load(&path))
+            .unwrap_or_else(|| {
+                Err(format!(
+                    "No kept script found for step '{}' in {}",
+                    step_id,
+                    runs_dir.display()
+                )
+                .into())
+            })
+    }
+
+    /// Builds a command that re-runs the script exactly as it was launched.
+    pub fn command(&self) -> std::process::Command {
+        let mut cmd = std::process::Command::new(&self.program);
+        cmd.args(&self.args).envs(&self.env);
+
+        if let Some(ref dir) = self.working_dir {
+            cmd.current_dir(dir);
+        }
+
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_run_directories() {
+        let runs_dir = Path::new(RUNS_DIR);
+        assert_eq!(run_dir(runs_dir, "r1"), PathBuf::from(".rustrunner/runs/r1"));
+        assert_eq!(
+            script_dir(runs_dir, "r1"),
+            PathBuf::from(".rustrunner/runs/r1/scripts")
+        );
+        assert_eq!(
+            script_path(Path::new("/s"), "align", "sh"),
+            PathBuf::from("/s/align.sh")
+        );
+    }
+
+    #[test]
+    fn test_launch_from_command() {
+        let mut cmd = Command::new("bash");
+        cmd.arg("/s/align.sh").current_dir("/data").env("MAMBA_ROOT_PREFIX", "/mamba");
+
+        let launch = ScriptLaunch::from_command("align", Path::new("/s/align.sh"), &cmd);
+
+        assert_eq!(launch.program, "bash");
+        assert_eq!(launch.args, vec!["/s/align.sh"]);
+        assert_eq!(launch.working_dir, Some(PathBuf::from("/data")));
+        assert_eq!(launch.env["MAMBA_ROOT_PREFIX"], "/mamba");
+    }
+
+    #[test]
+    fn test_find_latest_run() {
+        let temp_dir = tempdir().unwrap();
+        let runs_dir = temp_dir.path();
+
+        for run_id in ["20260101-000000-000", "20260102-000000-000"] {
+            let dir = script_dir(runs_dir, run_id);
+            fs::create_dir_all(&dir).unwrap();
+            let script = script_path(&dir, "align", "sh");
+            let launch = ScriptLaunch::from_command("align", &script, &Command::new("bash"));
+            launch.save().unwrap();
+        }
+        remove_if_empty(runs_dir, "20260101-000000-000");
+
+        let latest = ScriptLaunch::find(runs_dir, "align", None).unwrap();
+        assert!(latest.script_path.to_string_lossy().contains("20260102"));
+
+        let earlier = ScriptLaunch::find(runs_dir, "align", Some("20260101-000000-000")).unwrap();
+        assert!(earlier.script_path.to_string_lossy().contains("20260101"));
+
+        assert!(ScriptLaunch::find(runs_dir, "sort", None).is_err());
+        assert!(ScriptLaunch::find(runs_dir, "align", Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_launch_save_and_rerun() {
+        let temp_dir = tempdir().unwrap();
+        let script = temp_dir.path().join("hello.sh");
+        fs::write(&script, "echo rerun > out.txt\n").unwrap();
+
+        let mut cmd = Command::new("bash");
+        cmd.arg(&script).current_dir(temp_dir.path());
+        let launch = ScriptLaunch::from_command("hello", &script, &cmd);
+        launch.save().unwrap();
+
+        let record = ScriptLaunch::record_path(&script);
+        assert_eq!(record, temp_dir.path().join("hello.json"));
+
+        let loaded = ScriptLaunch::load(&record).unwrap();
+        assert_eq!(loaded, launch);
+
+        let status = loaded.command().status().unwrap();
+        assert!(status.success());
+        let out = fs::read_to_string(temp_dir.path().join("out.txt")).unwrap();
+        assert_eq!(out.trim(), "rerun");
+    }
+}
@@ -0,0 +1,551 @@
+//! SLURM Executor
+//!
+//! Submits each step as a batch job to a SLURM cluster. The step's launch
+//! command (bash, `micromamba run` or a container CLI) is wrapped in a
+//! batch script next to the step script, with the step's resources mapped
+//! to job options:
+//!
+//! | Step field | `sbatch` option     |
+//! |------------|---------------------|
+//! | `threads`  | `--cpus-per-task`   |
+//! | `memory`   | `--mem`             |
+//! | `timeout`  | `--time`            |
+//!
+//! Jobs are submitted with `sbatch --parsable`, watched with `squeue`,
+//! their exit status read from `sacct` and cancelled with `scancel`. The
+//! scripts, working directory and conda environments must be on a file
+//! system shared with the compute nodes. SLURM jobs report no resource
+//! usage to the run report.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use log::debug;
+use tokio::process::Command;
+
+use crate::monitoring::ProcessUsage;
+
+use super::executor::{
+    check_output, run_tool, shell_quote, spawn_cleanup, Executor, ExecutorFuture, Job, JobId,
+    JobOutcome, JobState, JobStatus,
+};
+
+/// Default interval between `squeue` polls.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Default time to wait for `sacct` to record a finished job's state.
+///
+/// `squeue` can drop a job before the accounting database has caught up.
+pub const DEFAULT_ACCOUNTING_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Longest delay between two `sacct` queries for the same job.
+const MAX_ACCOUNTING_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Submits steps as SLURM batch jobs.
+#[derive(Debug)]
+pub struct SlurmExecutor {
+    bin_dir: Option<PathBuf>,
+    partition: Option<String>,
+    sbatch_args: Vec<String>,
+    poll_interval: Duration,
+    accounting_timeout: Duration,
+    jobs: Mutex<HashMap<JobId, PathBuf>>,
+}
+
+impl Default for SlurmExecutor {
+    fn default() -> Self {
+        Self {
+            bin_dir: None,
+            partition: None,
+            sbatch_args: Vec::new(),
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            accounting_timeout: DEFAULT_ACCOUNTING_TIMEOUT,
+            jobs: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl SlurmExecutor {
+    /// Creates an executor using the SLURM commands on PATH.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses the SLURM commands in a specific directory.
+    pub fn with_bin_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.bin_dir = Some(dir.into());
+        self
+    }
+
+    /// Submits jobs to a partition.
+    pub fn with_partition(mut self, partition: impl Into<String>) -> Self {
+        self.partition = Some(partition.into());
+        self
+    }
+
+    /// Adds an option to every job, e.g. `--account=lab` or `--qos=long`.
+    pub fn with_sbatch_arg(mut self, arg: impl Into<String>) -> Self {
+        self.sbatch_args.push(arg.into());
+        self
+    }
+
+    /// Sets how often running jobs are polled.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Sets how long to wait for `sacct` to record a finished job's state.
+    pub fn with_accounting_timeout(mut self, timeout: Duration) -> Self {
+        self.accounting_timeout = timeout;
+        self
+    }
+
+    /// Returns the path of a SLURM command.
+    fn program(&self, name: &str) -> PathBuf {
+        match self.bin_dir {
+            Some(ref dir) => dir.join(name),
+            None => PathBuf::from(name),
+        }
+    }
+
+    /// Renders the batch script submitting a job.
+    ///
+    /// Output goes to `<script>.out` and `<script>.err` next to the step
+    /// script.
+    pub fn batch_script(&self, job: &Job) -> String {
+        let script_path = &job.launch.script_path;
+        let mut options = vec![
+            format!("--job-name=rr-{}", job.step_id()),
+            format!("--output={}", script_path.with_extension("out").display()),
+            format!("--error={}", script_path.with_extension("err").display()),
+            format!("--cpus-per-task={}", job.threads.max(1)),
+        ];
+        if let Some(ref memory) = job.memory {
+            options.push(format!("--mem={}", memory));
+        }
+        if let Some(timeout) = job.timeout {
+            options.push(format!("--time={}", time_limit(timeout)));
+        }
+        if let Some(ref partition) = self.partition {
+            options.push(format!("--partition={}", partition));
+        }
+        options.extend(self.sbatch_args.iter().cloned());
+
+        let mut script = String::from("#!/bin/bash\n");
+        for option in options {
+            script.push_str(&format!("#SBATCH {}\n", option));
+        }
+        script.push('\n');
+
+        if let Some(ref dir) = job.launch.working_dir {
+            script.push_str(&format!("cd {} || exit 1\n", shell_quote(&dir.to_string_lossy())));
+        }
+        for (key, value) in &job.launch.env {
+            script.push_str(&format!("export {}={}\n", key, shell_quote(value)));
+        }
+
+        let command: Vec<String> = std::iter::once(job.launch.program.as_str())
+            .chain(job.launch.args.iter().map(String::as_str))
+            .map(shell_quote)
+            .collect();
+        script.push_str(&format!("exec {}\n", command.join(" ")));
+        script
+    }
+
+    /// Reads a job's state and exit code from the accounting database.
+    async fn accounting(&self, id: &JobId) -> Result<Option<Accounting>, Box<dyn Error + Send + Sync>> {
+        let output = run_tool(Command::new(self.program("sacct"))
+            .args(["-n", "-P", "-X", "-o", "State,ExitCode", "-j"])
+            .arg(&id.0))
+        .await?;
+        check_output("sacct", &output)?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout.lines().find_map(Accounting::parse))
+    }
+
+    /// Returns how a finished job ended.
+    ///
+    /// Queries `sacct` until it reports a final state, backing off from the
+    /// poll interval up to [`MAX_ACCOUNTING_RETRY_DELAY`], and gives up
+    /// after the accounting timeout.
+    async fn final_status(&self, id: &JobId) -> Result<JobStatus, Box<dyn Error + Send + Sync>> {
+        let deadline = Instant::now() + self.accounting_timeout;
+        let mut delay = self.poll_interval.min(MAX_ACCOUNTING_RETRY_DELAY);
+
+        loop {
+            let accounting = self.accounting(id).await?;
+            if let Some(status) = accounting.as_ref().and_then(Accounting::status) {
+                return Ok(status);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(format!(
+                    "SLURM job {} has no final state in sacct after {}s",
+                    id,
+                    self.accounting_timeout.as_secs()
+                )
+                .into());
+            }
+
+            debug!(
+                "SLURM job {} not final in sacct yet ({}), retrying",
+                id,
+                accounting.map_or("no record".to_string(), |a| a.state)
+            );
+            tokio::time::sleep(delay.min(deadline - now)).await;
+            delay = (delay * 2).min(MAX_ACCOUNTING_RETRY_DELAY);
+        }
+    }
+}
+
+impl Executor for SlurmExecutor {
+    fn name(&self) -> &str {
+        "slurm"
+    }
+
+    fn submit(&self, job: Job) -> ExecutorFuture<'_, JobId> {
+        Box::pin(async move {
+            let batch_path = job.launch.script_path.with_extension("sbatch");
+            fs::write(&batch_path, self.batch_script(&job))?;
+
+            let output = run_tool(Command::new(self.program("sbatch"))
+                .arg("--parsable")
+                .arg(&batch_path))
+            .await?;
+            check_output("sbatch", &output)?;
+
+            // `--parsable` prints `<id>` or `<id>;<cluster>`
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let id = stdout
+                .trim()
+                .split(';')
+                .next()
+                .filter(|id| !id.is_empty())
+                .ok_or("sbatch printed no job ID")?;
+
+            let id = JobId(id.to_string());
+            self.jobs.lock().unwrap().insert(id.clone(), batch_path);
+            Ok(id)
+        })
+    }
+
+    fn poll<'a>(&'a self, id: &'a JobId) -> ExecutorFuture<'a, JobState> {
+        Box::pin(async move {
+            let output = run_tool(Command::new(self.program("squeue"))
+                .args(["-h", "-o", "%T", "-j"])
+                .arg(&id.0))
+            .await?;
+
+            if output.status.success() {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                return Ok(match stdout.lines().next().map(str::trim) {
+                    Some("PENDING" | "CONFIGURING" | "REQUEUED") => JobState::Queued,
+                    Some(state) if !state.is_empty() => JobState::Running,
+                    _ => JobState::Finished,
+                });
+            }
+
+            // squeue rejects the IDs of jobs that ended a while ago
+            debug!("squeue failed for job {}; asking sacct", id);
+            Ok(match self.accounting(id).await? {
+                Some(accounting) if accounting.status().is_none() => JobState::Running,
+                _ => JobState::Finished,
+            })
+        })
+    }
+
+    fn cancel(&self, id: &JobId) {
+        let batch_path = self.jobs.lock().unwrap().remove(id);
+        let mut scancel = std::process::Command::new(self.program("scancel"));
+        scancel.arg(&id.0);
+        spawn_cleanup(scancel);
+
+        if let Some(path) = batch_path {
+            remove_job_files(&path);
+        }
+    }
+
+    fn collect<'a>(&'a self, id: &'a JobId) -> ExecutorFuture<'a, JobOutcome> {
+        Box::pin(async move {
+            let batch_path = self
+                .jobs
+                .lock()
+                .unwrap()
+                .remove(id)
+                .ok_or_else(|| format!("Unknown SLURM job {}", id))?;
+
+            let status = self.final_status(id).await;
+
+            let read = |extension| fs::read_to_string(batch_path.with_extension(extension)).unwrap_or_default();
+            let outcome = status.map(|status| JobOutcome {
+                status,
+                stdout: read("out"),
+                stderr: read("err"),
+                resources: ProcessUsage::default(),
+            });
+
+            remove_job_files(&batch_path);
+            outcome
+        })
+    }
+
+    fn poll_interval(&self) -> Duration {
+        self.poll_interval
+    }
+
+    fn max_threads(&self) -> Option<usize> {
+        // The scheduler allocates CPUs on the compute nodes
+        None
+    }
+}
+
+/// A job's line in `sacct -P -o State,ExitCode`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Accounting {
+    /// Job state, e.g. `COMPLETED` or `OUT_OF_MEMORY`
+    state: String,
+    /// Exit code of the batch script
+    exit_code: i32,
+    /// Signal that killed the batch script, or 0
+    signal: i32,
+}
+
+impl Accounting {
+    /// Parses a line such as `FAILED|1:0` or `CANCELLED by 1000|0:15`.
+    fn parse(line: &str) -> Option<Self> {
+        let (state, exit) = line.trim().split_once('|')?;
+        let (code, signal) = exit.split_once(':').unwrap_or((exit, "0"));
+
+        Some(Self {
+            state: state.split_whitespace().next()?.to_string(),
+            exit_code: code.trim().parse().ok()?,
+            signal: signal.trim().parse().unwrap_or(0),
+        })
+    }
+
+    /// Returns how the job ended, or `None` if it hasn't yet.
+    fn status(&self) -> Option<JobStatus> {
+        match self.state.as_str() {
+            "PENDING" | "CONFIGURING" | "RUNNING" | "COMPLETING" | "REQUEUED" | "RESIZING"
+            | "SUSPENDED" => None,
+            "COMPLETED" => Some(JobStatus::Exited(self.exit_code)),
+            "FAILED" if self.signal > 0 => Some(JobStatus::Signaled(self.signal)),
+            "FAILED" if self.exit_code != 0 => Some(JobStatus::Exited(self.exit_code)),
+            "TIMEOUT" => Some(JobStatus::TimedOut),
+            state => Some(JobStatus::Failed(state.to_string())),
+        }
+    }
+}
+
+/// Formats a time limit as `hours:minutes:seconds`, rounded up to a minute.
+fn time_limit(timeout: Duration) -> String {
+    let minutes = ((timeout.as_secs() + 59) / 60).max(1);
+    format!("{}:{:02}:00", minutes / 60, minutes % 60)
+}
+
+/// Removes a job's batch script and output files.
+fn remove_job_files(batch_path: &Path) {
+    for extension in ["sbatch", "out", "err"] {
+        let _ = fs::remove_file(batch_path.with_extension(extension));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::execution::step::{execute_step, StepContext};
+    use crate::execution::ScriptLaunch;
+    use crate::workflow::Step;
+    use std::os::unix::fs::PermissionsExt;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    /// Writes stub `sbatch`, `squeue`, `sacct` and `scancel` scripts.
+    ///
+    /// `sbatch` runs the batch script right away and records its exit code;
+    /// `squeue` reports the job as running once, then as gone. If a
+    /// `sacct_lag` file exists, `sacct` lists nothing on its first call.
+    fn stub_scheduler(dir: &Path) {
+        let stubs = [
+            (
+                "sbatch",
+                r#"echo "sbatch $*" >> "$STUB/calls.log"
+batch="$2"
+out=$(sed -n 's/^#SBATCH --output=//p' "$batch")
+err=$(sed -n 's/^#SBATCH --error=//p' "$batch")
+bash "$batch" > "$out" 2> "$err"
+echo $? > "$STUB/exit"
+echo "4242;cluster""#,
+            ),
+            (
+                "squeue",
+                r#"echo "squeue $*" >> "$STUB/calls.log"
+[ -e "$STUB/polled" ] && exit 0
+touch "$STUB/polled"
+echo RUNNING"#,
+            ),
+            (
+                "sacct",
+                r#"if [ -e "$STUB/sacct_lag" ] && [ ! -e "$STUB/sacct_seen" ]; then
+  touch "$STUB/sacct_seen"
+  exit 0
+fi
+code=$(cat "$STUB/exit")
+if [ "$code" = 0 ]; then echo "COMPLETED|0:0"; else echo "FAILED|$code:0"; fi"#,
+            ),
+            ("scancel", r#"echo "scancel $*" >> "$STUB/calls.log""#),
+        ];
+
+        for (name, body) in stubs {
+            let path = dir.join(name);
+            let script = format!("#!/bin/sh\nSTUB={}\n{}\n", dir.display(), body);
+            fs::write(&path, script).unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_batch_script() {
+        let job = Job {
+            launch: ScriptLaunch {
+                step_id: "align".to_string(),
+                script_path: PathBuf::from("/runs/r1/scripts/align.sh"),
+                program: "micromamba".to_string(),
+                args: vec!["run".to_string(), "-n".to_string(), "bwa".to_string()],
+                working_dir: Some(PathBuf::from("/data/project")),
+                env: [("NOTE".to_string(), "it's".to_string())].into(),
+            },
+            threads: 8,
+            memory: Some("16G".to_string()),
+            timeout: Some(Duration::from_secs(5400)),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        };
+
+        let script = SlurmExecutor::new().with_partition("short").batch_script(&job);
+
+        assert!(script.starts_with("#!/bin/bash\n#SBATCH --job-name=rr-align\n"));
+        for line in [
+            "#SBATCH --output=/runs/r1/scripts/align.out",
+            "#SBATCH --cpus-per-task=8",
+            "#SBATCH --mem=16G",
+            "#SBATCH --time=1:30:00",
+            "#SBATCH --partition=short",
+            "cd '/data/project' || exit 1",
+            r"export NOTE='it'\''s'",
+            "exec 'micromamba' 'run' '-n' 'bwa'",
+        ] {
+            assert!(script.lines().any(|l| l == line), "missing {}:\n{}", line, script);
+        }
+    }
+
+    #[test]
+    fn test_accounting_status() {
+        let status = |line: &str| Accounting::parse(line).unwrap().status();
+
+        assert_eq!(status("COMPLETED|0:0"), Some(JobStatus::Exited(0)));
+        assert_eq!(status("FAILED|3:0"), Some(JobStatus::Exited(3)));
+        assert_eq!(status("FAILED|0:9"), Some(JobStatus::Signaled(9)));
+        assert_eq!(status("TIMEOUT|0:0"), Some(JobStatus::TimedOut));
+        assert_eq!(status("OUT_OF_MEMORY|0:125"), Some(JobStatus::Failed("OUT_OF_MEMORY".to_string())));
+        assert_eq!(status("CANCELLED by 1000|0:15"), Some(JobStatus::Failed("CANCELLED".to_string())));
+        assert_eq!(status("RUNNING|0:0"), None);
+        assert!(Accounting::parse("").is_none());
+    }
+
+    #[test]
+    fn test_time_limit() {
+        assert_eq!(time_limit(Duration::from_secs(1)), "0:01:00");
+        assert_eq!(time_limit(Duration::from_secs(61)), "0:02:00");
+        assert_eq!(time_limit(Duration::from_secs(36000)), "10:00:00");
+    }
+
+    #[tokio::test]
+    async fn test_execute_step_on_slurm() {
+        let temp_dir = tempdir().unwrap();
+        stub_scheduler(temp_dir.path());
+        let executor = SlurmExecutor::new()
+            .with_bin_dir(temp_dir.path())
+            .with_poll_interval(Duration::from_millis(10));
+        let context = StepContext::new(temp_dir.path().join("scripts"))
+            .with_working_dir(Some(temp_dir.path().to_path_buf()))
+            .with_executor(Arc::new(executor));
+
+        let step = Step::new("count", "bash", "echo counted; echo note >&2").with_threads(4);
+        let output = execute_step(&step, &context).await.unwrap();
+        assert_eq!(output.stdout_tail, vec!["counted"]);
+        assert_eq!(output.stderr_tail, vec!["note"]);
+        assert!(!temp_dir.path().join("scripts/count.out").exists());
+
+        let calls = fs::read_to_string(temp_dir.path().join("calls.log")).unwrap();
+        assert!(calls.contains("sbatch --parsable"), "{}", calls);
+        assert!(calls.contains("squeue -h -o %T -j 4242"), "{}", calls);
+
+        let step = Step::new("broken", "bash", "exit 3");
+        let error = execute_step(&step, &context).await.unwrap_err();
+        assert_eq!(error.exit_code, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_collect_waits_for_sacct() {
+        let temp_dir = tempdir().unwrap();
+        stub_scheduler(temp_dir.path());
+        fs::write(temp_dir.path().join("sacct_lag"), "").unwrap();
+        let executor = SlurmExecutor::new()
+            .with_bin_dir(temp_dir.path())
+            .with_poll_interval(Duration::from_millis(10));
+        let context = StepContext::new(temp_dir.path().join("scripts"))
+            .with_working_dir(Some(temp_dir.path().to_path_buf()))
+            .with_executor(Arc::new(executor));
+
+        let step = Step::new("late", "bash", "echo done");
+        let output = execute_step(&step, &context).await.unwrap();
+        assert_eq!(output.stdout_tail, vec!["done"]);
+        assert!(temp_dir.path().join("sacct_seen").exists());
+    }
+
+    #[tokio::test]
+    async fn test_collect_gives_up_without_sacct_record() {
+        let temp_dir = tempdir().unwrap();
+        stub_scheduler(temp_dir.path());
+        fs::write(temp_dir.path().join("sacct"), "#!/bin/sh\nexit 0\n").unwrap();
+        let executor = SlurmExecutor::new()
+            .with_bin_dir(temp_dir.path())
+            .with_poll_interval(Duration::from_millis(10))
+            .with_accounting_timeout(Duration::from_millis(100));
+        let context = StepContext::new(temp_dir.path().join("scripts"))
+            .with_working_dir(Some(temp_dir.path().to_path_buf()))
+            .with_executor(Arc::new(executor));
+
+        let step = Step::new("lost", "bash", "true");
+        let error = execute_step(&step, &context).await.unwrap_err();
+        assert!(error.to_string().contains("no final state"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_calls_scancel() {
+        let temp_dir = tempdir().unwrap();
+        stub_scheduler(temp_dir.path());
+        let executor = SlurmExecutor::new().with_bin_dir(temp_dir.path());
+
+        executor.cancel(&JobId("77".to_string()));
+
+        // scancel runs in the background
+        let log = temp_dir.path().join("calls.log");
+        for _ in 0..100 {
+            if fs::read_to_string(&log).is_ok_and(|calls| !calls.is_empty()) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let calls = fs::read_to_string(&log).unwrap();
+        assert_eq!(calls.trim(), "scancel 77");
+    }
+}
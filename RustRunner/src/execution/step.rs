@@ -3,34 +3,125 @@
 //! Handles the execution of a single workflow step including:
 //! - Command placeholder substitution
 //! - Script generation
-//! - Environment activation (conda/system)
+//! - Backend selection (host, conda or container, see [`super::backend`])
 //! - Output directory creation
+//! - Handing the launch command to an executor (see [`super::executor`]) and
+//!   turning its outcome into a [`StepOutput`] or [`StepError`]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 use log::{debug, error, warn};
 
-use crate::environment::conda::{MICROMAMBA_PATH, MAMBA_ROOT_PREFIX};
-use crate::workflow::Step;
+use crate::environment::ToolRegistry;
+use crate::monitoring::ProcessUsage;
+use crate::workflow::script::{StepData, STEP_JSON_ENV};
+use crate::workflow::{ShellConfig, Step};
+
+use super::backend::{Backend, ScriptRun};
+use super::container::ContainerRuntime;
+use super::error::StepError;
+use super::executor::{run_job, Executor, Job, JobStatus};
+use super::local::LocalExecutor;
+use super::scripts::{self, ScriptLaunch};
+
+/// Number of trailing output lines kept for reports and error messages.
+pub const LOG_TAIL_LINES: usize = 20;
+
+/// Output of a successfully completed step.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StepOutput {
+    /// Last lines the step wrote to stdout
+    pub stdout_tail: Vec<String>,
+    /// Last lines the step wrote to stderr
+    pub stderr_tail: Vec<String>,
+    /// Resources used by the step's process tree
+    pub resources: ProcessUsage,
+}
+
+/// Settings shared by all steps of a run.
+#[derive(Debug, Clone, Default)]
+pub struct StepContext {
+    /// Mapping of tool names to conda environment names
+    pub tool_env_map: HashMap<String, String>,
+    /// Which tools run from PATH rather than a conda environment
+    pub tool_registry: Arc<ToolRegistry>,
+    /// Working directory for relative paths
+    pub working_dir: Option<PathBuf>,
+    /// Directory the step scripts are written to
+    pub script_dir: PathBuf,
+    /// Keep scripts of successful steps (failed steps always keep theirs)
+    pub keep_scripts: bool,
+    /// Workflow-level shell settings; steps may override them
+    pub shell: ShellConfig,
+    /// Runtime for steps with a container image (detected from PATH if unset)
+    pub container_runtime: Option<ContainerRuntime>,
+    /// Executor launching the steps (local processes if unset)
+    pub executor: Option<Arc<dyn Executor>>,
+}
+
+impl StepContext {
+    /// Creates a context writing scripts to the given directory.
+    pub fn new(script_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            script_dir: script_dir.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the tool to conda environment mapping.
+    pub fn with_tool_env_map(mut self, tool_env_map: HashMap<String, String>) -> Self {
+        self.tool_env_map = tool_env_map;
+        self
+    }
+
+    /// Sets the tool registry.
+    pub fn with_tool_registry(mut self, tool_registry: Arc<ToolRegistry>) -> Self {
+        self.tool_registry = tool_registry;
+        self
+    }
+
+    /// Sets the working directory.
+    pub fn with_working_dir(mut self, working_dir: Option<PathBuf>) -> Self {
+        self.working_dir = working_dir;
+        self
+    }
+
+    /// Keeps the scripts of successful steps.
+    pub fn with_keep_scripts(mut self, keep_scripts: bool) -> Self {
+        self.keep_scripts = keep_scripts;
+        self
+    }
+
+    /// Sets the workflow-level shell settings.
+    pub fn with_shell(mut self, shell: ShellConfig) -> Self {
+        self.shell = shell;
+        self
+    }
 
-/// Tools available in standard system PATH that don't require conda.
-const SYSTEM_TOOLS: &[&str] = &[
-    "bash", "sh", "echo", "cat", "cp", "mv", "rm", "mkdir", "sleep", "touch", "ls", "grep", "sed",
-    "awk", "head", "tail", "sort", "uniq", "wc", "cut", "tr", "tee", "curl", "wget", "gzip", 
-    "gunzip", "tar", "zip", "unzip", "bc", "date", "find", "xargs", "diff", "comm", "paste",
-    "rev", "fold", "printf", "test", "true", "false",
-];
+    /// Sets the container runtime.
+    pub fn with_container_runtime(mut self, runtime: Option<ContainerRuntime>) -> Self {
+        self.container_runtime = runtime;
+        self
+    }
+
+    /// Sets the executor launching the steps.
+    pub fn with_executor(mut self, executor: Arc<dyn Executor>) -> Self {
+        self.executor = Some(executor);
+        self
+    }
+}
 
 /// Executes a single workflow step.
 ///
 /// This function handles:
 /// - Command placeholder resolution ({input}, {output})
-/// - Temporary script generation
+/// - Script generation in the run's script directory
 /// - Conda environment activation for bioinformatics tools
 /// - Working directory management
 /// - Output capture and error handling
@@ -38,88 +129,189 @@ const SYSTEM_TOOLS: &[&str] = &[
 /// # Arguments
 ///
 /// * `step` - The workflow step to execute
-/// * `tool_env_map` - Mapping of tool names to conda environment names
-/// * `working_dir` - Optional working directory for relative paths
+/// * `context` - Environment mapping, working directory and script settings
 ///
 /// # Returns
 ///
-/// * `Ok(())` - Step completed successfully
-/// * `Err` - Step failed with descriptive error
+/// * `Ok(StepOutput)` - Step completed successfully
+/// * `Err(StepError)` - Step failed, timed out, or could not be started
+///
+/// # Cancellation
+///
+/// Dropping the returned future cancels the step's job (killing a local
+/// step's process group), so the engine can cancel running steps simply by
+/// aborting their tasks.
 ///
 /// # Placeholder Substitution
 ///
 /// The following placeholders are supported:
 /// - `{input}` / `{inputs}` - Space-separated input files
 /// - `{output}` / `{outputs}` - Space-separated output files
-pub fn execute_step(
-    step: &Step,
-    tool_env_map: &HashMap<String, String>,
-    working_dir: &Option<PathBuf>,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
+///
+/// Inline scripts get no substitution; their inputs, outputs and params are
+/// written to a JSON sidecar instead (see [`crate::workflow::script`]).
+pub async fn execute_step(step: &Step, context: &StepContext) -> Result<StepOutput, StepError> {
     let step_name = &step.id;
+    let working_dir = &context.working_dir;
+    let failed = |e: Box<dyn Error + Send + Sync>| StepError::new(step_name, e.to_string());
 
     // Parse comma-separated file lists
     let input_files = parse_file_list(&step.input);
     let output_files = parse_file_list(&step.output);
 
     // Create output directories
-    ensure_output_directories(&output_files, working_dir)?;
-
-    // Resolve placeholders
-    let inputs_str = input_files.join(" ");
-    let outputs_str = output_files.join(" ");
+    ensure_output_directories(&output_files, working_dir).map_err(failed)?;
+
+    // Resolve placeholders, or write an inline script's sidecar
+    let sidecar_path = context.script_dir.join(format!("{}.step.json", step_name));
+    let shell = step.shell_with(&context.shell);
+    let command_text = match &step.script {
+        Some(script) => {
+            let data = StepData {
+                step_id: step_name.clone(),
+                input: input_files,
+                output: output_files,
+                threads: step.threads,
+                params: step.params.clone(),
+            };
+            write_step_data(&sidecar_path, &data).map_err(failed)?;
+            script.render(&data, &sidecar_path)
+        }
+        None => {
+            let inputs_str = input_files.join(" ");
+            let outputs_str = output_files.join(" ");
+
+            step.command
+                .replace("{input}", &inputs_str)
+                .replace("{output}", &outputs_str)
+                .replace("{inputs}", &inputs_str)
+                .replace("{outputs}", &outputs_str)
+        }
+    };
 
-    let command_text = step
-        .command
-        .replace("{input}", &inputs_str)
-        .replace("{output}", &outputs_str)
-        .replace("{inputs}", &inputs_str)
-        .replace("{outputs}", &outputs_str);
+    let failed = |e: Box<dyn Error + Send + Sync>| failed(e).with_command(&command_text);
 
     // Create execution script
-    let script_path = create_execution_script(step_name, &command_text)?;
+    let script_path = create_execution_script(&context.script_dir, step_name, &shell, &command_text)
+        .map_err(failed)?;
 
-    // Execute based on tool type
-    let output = if is_system_tool(&step.tool) {
-        execute_with_bash(&script_path, working_dir)?
-    } else {
-        execute_with_conda(&script_path, &step.tool, tool_env_map, working_dir)?
+    let failed = |message: String| {
+        StepError::new(step_name, message)
+            .with_command(&command_text)
+            .with_script_path(&script_path)
     };
 
-    // Clean up script
-    if let Err(e) = fs::remove_file(&script_path) {
-        warn!("Failed to clean up script {}: {}", script_path.display(), e);
+    // Build the command for the step's backend
+    let backend = Backend::resolve(step, context).map_err(|e| failed(e.to_string()))?;
+    let mut env = BTreeMap::new();
+    if step.script.is_some() {
+        env.insert(STEP_JSON_ENV.to_string(), sidecar_path.to_string_lossy().into_owned());
     }
+    let inputs = parse_file_list(&step.input);
+    let outputs = parse_file_list(&step.output);
+    let run = ScriptRun {
+        step_id: step_name,
+        shell: &shell,
+        script_path: &script_path,
+        script_dir: &context.script_dir,
+        working_dir: working_dir.as_deref(),
+        env,
+        files: [inputs.as_slice(), outputs.as_slice()].concat(),
+    };
+    let (cmd, mut container) = backend.command(&run);
 
-    // Process result
-    if output.status.success() {
-        debug!("Step '{}' completed successfully", step_name);
+    let launch = ScriptLaunch::from_command(step_name, &script_path, &cmd);
+    if let Err(e) = launch.save() {
+        warn!("Failed to record launch of step '{}': {}", step_name, e);
+    }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        if !stdout.trim().is_empty() {
-            debug!("Step '{}' output:\n{}", step_name, stdout);
-        }
+    let job = Job {
+        launch,
+        threads: step.threads,
+        memory: step.memory.clone(),
+        timeout: step.timeout.map(Duration::from_secs),
+        inputs,
+        outputs,
+    };
+    let output = match context.executor {
+        Some(ref executor) => run_job(executor.as_ref(), job).await,
+        None => run_job(&LocalExecutor::new(), job).await,
+    }
+    .map_err(|e| failed(e.to_string()))?;
 
-        Ok(())
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
+    // A container that exited removed itself; a timed-out one is removed on drop
+    if let Some(guard) = container.as_mut().filter(|_| output.status != JobStatus::TimedOut) {
+        guard.disarm();
+    }
 
-        error!(
-            "Step '{}' failed with exit code: {:?}",
-            step_name,
-            output.status.code()
-        );
+    let stderr_tail = tail_lines(&output.stderr, LOG_TAIL_LINES);
+    let (exit_code, signal, message) = match output.status {
+        JobStatus::Exited(0) => {
+            debug!("Step '{}' completed successfully", step_name);
+
+            // Clean up script; failed steps keep theirs for inspection
+            if !context.keep_scripts {
+                let sidecar = step.script.is_some().then_some(sidecar_path);
+                for path in [ScriptLaunch::record_path(&script_path), script_path.clone()]
+                    .into_iter()
+                    .chain(sidecar)
+                {
+                    if let Err(e) = fs::remove_file(&path) {
+                        warn!("Failed to clean up script {}: {}", path.display(), e);
+                    }
+                }
+            }
 
-        if !stderr.trim().is_empty() {
-            error!("stderr:\n{}", stderr);
+            return Ok(StepOutput {
+                stdout_tail: tail_lines(&output.stdout, LOG_TAIL_LINES),
+                stderr_tail,
+                resources: output.resources,
+            });
         }
-        if !stdout.trim().is_empty() {
-            debug!("stdout:\n{}", stdout);
+        JobStatus::TimedOut => {
+            let message = format!(
+                "Step '{}' timed out after {}s",
+                step_name,
+                step.timeout.unwrap_or_default()
+            );
+            error!("{}", message);
+            return Err(failed(message)
+                .with_stderr_tail(stderr_tail)
+                .with_resources(output.resources));
         }
+        JobStatus::Exited(code) => (
+            Some(code),
+            None,
+            format!("Step '{}' failed with exit code {}", step_name, code),
+        ),
+        JobStatus::Signaled(signal) => (
+            None,
+            Some(signal),
+            format!("Step '{}' was killed by signal {}", step_name, signal),
+        ),
+        JobStatus::Failed(ref reason) => (None, None, format!("Step '{}' failed: {}", step_name, reason)),
+    };
+    error!("{}", message);
 
-        Err(format!("Step '{}' failed. See logs for details.", step_name).into())
+    if !output.stderr.trim().is_empty() {
+        error!("stderr:\n{}", output.stderr);
+    }
+    if !output.stdout.trim().is_empty() {
+        debug!("stdout:\n{}", output.stdout);
     }
+
+    Err(failed(message)
+        .with_exit_code(exit_code)
+        .with_signal(signal)
+        .with_stderr_tail(stderr_tail)
+        .with_resources(output.resources))
+}
+
+/// Returns the last `count` lines of a block of text.
+fn tail_lines(text: &str, count: usize) -> Vec<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines.len().saturating_sub(count);
+    lines[start..].iter().map(|line| line.to_string()).collect()
 }
 
 /// Parses comma-separated file strings into a vector.
@@ -156,20 +348,29 @@ fn ensure_output_directories(
     Ok(())
 }
 
-/// Creates a temporary bash script for step execution.
+/// Writes the JSON sidecar describing a step for its inline script.
+fn write_step_data(path: &Path, data: &StepData) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, data.to_json())?;
+    Ok(())
+}
+
+/// Creates the script for a step in the run's script directory.
+///
+/// The command is wrapped in the shell's strict mode, prologue and epilogue.
 fn create_execution_script(
+    script_dir: &Path,
     step_id: &str,
+    shell: &ShellConfig,
     command_text: &str,
 ) -> Result<PathBuf, Box<dyn Error + Send + Sync>> {
-    let script_dir = std::env::temp_dir().join("rustrunner_scripts");
-    fs::create_dir_all(&script_dir)?;
+    fs::create_dir_all(script_dir)?;
 
-    let script_path = script_dir.join(format!("step_{}.sh", step_id));
+    let script_path = scripts::script_path(script_dir, step_id, shell.script_extension());
     let mut file = File::create(&script_path)?;
-
-    writeln!(file, "#!/bin/bash")?;
-    writeln!(file, "set -e")?;
-    writeln!(file, "{}", command_text)?;
+    file.write_all(shell.render_script(command_text).as_bytes())?;
 
     #[cfg(unix)]
     {
@@ -180,58 +381,6 @@ fn create_execution_script(
     Ok(script_path)
 }
 
-/// Checks if a tool is a system tool (doesn't require conda).
-fn is_system_tool(tool: &str) -> bool {
-    SYSTEM_TOOLS.contains(&tool)
-}
-
-/// Executes a script directly with bash.
-fn execute_with_bash(
-    script_path: &PathBuf,
-    working_dir: &Option<PathBuf>,
-) -> Result<std::process::Output, Box<dyn Error + Send + Sync>> {
-    let mut cmd = Command::new("bash");
-    cmd.arg(script_path);
-
-    if let Some(dir) = working_dir {
-        cmd.current_dir(dir);
-        debug!("Executing in directory: {}", dir.display());
-    }
-
-    Ok(cmd.output()?)
-}
-
-/// Executes a script within a conda environment.
-fn execute_with_conda(
-    script_path: &PathBuf,
-    tool: &str,
-    tool_env_map: &HashMap<String, String>,
-    working_dir: &Option<PathBuf>,
-) -> Result<std::process::Output, Box<dyn Error + Send + Sync>> {
-    let env_name = tool_env_map.get(tool).ok_or_else(|| {
-        format!(
-            "No conda environment configured for tool '{}'. \
-             Create one with: micromamba create -n {} {} -c bioconda -c conda-forge",
-            tool, tool, tool
-        )
-    })?;
-
-    let mut cmd = Command::new(&*MICROMAMBA_PATH);
-    cmd.env("MAMBA_ROOT_PREFIX", &*MAMBA_ROOT_PREFIX);
-    cmd.arg("run").arg("-n").arg(env_name).arg("bash").arg(script_path);
-
-    if let Some(dir) = working_dir {
-        cmd.current_dir(dir);
-        debug!(
-            "Executing in directory: {} (conda env: {})",
-            dir.display(),
-            env_name
-        );
-    }
-
-    Ok(cmd.output()?)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -243,12 +392,20 @@ mod tests {
         assert_eq!(result, vec!["file1.txt", "file2.txt"]);
     }
 
+    #[test]
+    fn test_tail_lines() {
+        assert_eq!(tail_lines("a\nb\nc\n", 2), vec!["b", "c"]);
+        assert_eq!(tail_lines("a\n", 5), vec!["a"]);
+        assert!(tail_lines("", 5).is_empty());
+    }
+
     #[test]
     fn test_is_system_tool() {
-        assert!(is_system_tool("bash"));
-        assert!(is_system_tool("echo"));
-        assert!(!is_system_tool("bowtie2"));
-        assert!(!is_system_tool("samtools"));
+        let registry = StepContext::default().tool_registry;
+        assert!(registry.is_system_tool("bash"));
+        assert!(registry.is_system_tool("echo"));
+        assert!(!registry.is_system_tool("bowtie2"));
+        assert!(!registry.is_system_tool("samtools"));
     }
 
     #[test]
@@ -296,48 +453,57 @@ mod tests {
 
     #[test]
     fn test_is_system_tool_variations() {
-        assert!(is_system_tool("bash"));
-        assert!(is_system_tool("grep"));
-        assert!(is_system_tool("awk"));
-        assert!(is_system_tool("sed"));
-        assert!(is_system_tool("sort"));
-        assert!(is_system_tool("wc"));
-        assert!(is_system_tool("cat"));
-        assert!(is_system_tool("head"));
-        assert!(is_system_tool("tail"));
-        assert!(!is_system_tool("bowtie2"));
-        assert!(!is_system_tool("samtools"));
-        assert!(!is_system_tool("BASH")); // Case sensitive
+        let registry = StepContext::default().tool_registry;
+        assert!(registry.is_system_tool("bash"));
+        assert!(registry.is_system_tool("grep"));
+        assert!(registry.is_system_tool("awk"));
+        assert!(registry.is_system_tool("sed"));
+        assert!(registry.is_system_tool("sort"));
+        assert!(registry.is_system_tool("wc"));
+        assert!(registry.is_system_tool("cat"));
+        assert!(registry.is_system_tool("head"));
+        assert!(registry.is_system_tool("tail"));
+        assert!(!registry.is_system_tool("bowtie2"));
+        assert!(!registry.is_system_tool("samtools"));
+        assert!(!registry.is_system_tool("BASH")); // Case sensitive
     }
 
     #[test]
     fn test_create_execution_script() {
-        let script = create_execution_script("test_step", "echo 'hello world'");
+        let temp_dir = tempfile::tempdir().unwrap();
+        let script = create_execution_script(
+            temp_dir.path(),
+            "test_step",
+            &ShellConfig::default(),
+            "echo 'hello world'",
+        );
         assert!(script.is_ok());
 
         let script_path = script.unwrap();
         assert!(script_path.exists());
 
         let content = std::fs::read_to_string(&script_path).unwrap();
-        assert!(content.contains("#!/bin/bash"));
-        assert!(content.contains("set -e"));
+        assert!(content.contains("#!/usr/bin/env bash"));
+        assert!(content.contains("set -euo pipefail"));
         assert!(content.contains("echo 'hello world'"));
-
-        // Cleanup
-        std::fs::remove_file(script_path).unwrap();
+        assert_eq!(script_path, temp_dir.path().join("test_step.sh"));
     }
 
     #[test]
     fn test_create_execution_script_multiline_command() {
-        let script = create_execution_script("multi", "echo line1\necho line2");
+        let temp_dir = tempfile::tempdir().unwrap();
+        let script = create_execution_script(
+            temp_dir.path(),
+            "multi",
+            &ShellConfig::default(),
+            "echo line1\necho line2",
+        );
         assert!(script.is_ok());
 
         let script_path = script.unwrap();
         let content = std::fs::read_to_string(&script_path).unwrap();
         assert!(content.contains("echo line1"));
         assert!(content.contains("echo line2"));
-
-        std::fs::remove_file(script_path).unwrap();
     }
 
     #[test]
@@ -348,7 +514,7 @@ mod tests {
         let nested_file = "subdir1/subdir2/output.txt";
 
         let result = ensure_output_directories(
-            &vec![nested_file.to_string()],
+            &[nested_file.to_string()],
             &Some(temp_dir.path().to_path_buf())
         );
 
@@ -359,7 +525,7 @@ mod tests {
     #[test]
     fn test_ensure_output_directories_empty() {
         let result = ensure_output_directories(
-            &vec!["".to_string()],
+            &["".to_string()],
             &None
         );
 
@@ -374,7 +540,7 @@ mod tests {
         let output = temp_dir.path().join("newdir/output.txt");
 
         let result = ensure_output_directories(
-            &vec![output.to_str().unwrap().to_string()],
+            &[output.to_str().unwrap().to_string()],
             &None
         );
 
@@ -382,20 +548,181 @@ mod tests {
         assert!(temp_dir.path().join("newdir").exists());
     }
 
-    #[test]
-    fn test_execute_step_simple_bash() {
+    #[tokio::test]
+    async fn test_execute_step_simple_bash() {
         use tempfile::tempdir;
 
         let temp_dir = tempdir().unwrap();
         let output_file = temp_dir.path().join("out.txt");
 
-        let step = Step::new("test_exec", "bash", &format!("echo hello > {}", output_file.display()))
+        let step = Step::new("test_exec", "bash", format!("echo hello > {}", output_file.display()))
             .with_output(output_file.to_str().unwrap());
 
-        let env_map = HashMap::new();
-        let result = execute_step(&step, &env_map, &None);
+        let context = StepContext::new(temp_dir.path().join("scripts"));
+        let result = execute_step(&step, &context).await;
 
         assert!(result.is_ok());
         assert!(output_file.exists());
+
+        let step = Step::new("test_echo", "bash", "echo one; echo two");
+        let output = execute_step(&step, &context).await.unwrap();
+        assert_eq!(output.stdout_tail, vec!["one", "two"]);
+
+        // Scripts of successful steps are removed unless kept
+        assert!(!context.script_dir.join("test_echo.sh").exists());
+        let context = context.with_keep_scripts(true);
+        execute_step(&step, &context).await.unwrap();
+        assert!(context.script_dir.join("test_echo.sh").exists());
+        assert!(context.script_dir.join("test_echo.json").exists());
+    }
+
+    #[tokio::test]
+    async fn test_execute_step_failure() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let step = Step::new("test_fail", "bash", "echo oops >&2; exit 3");
+
+        let context = StepContext::new(temp_dir.path());
+        let error = execute_step(&step, &context).await.unwrap_err();
+
+        assert_eq!(error.step_id, "test_fail");
+        assert_eq!(error.exit_code, Some(3));
+        assert_eq!(error.signal, None);
+        assert_eq!(error.stderr_tail, vec!["oops"]);
+        assert_eq!(error.command, "echo oops >&2; exit 3");
+        assert_eq!(error.to_string(), "Step 'test_fail' failed with exit code 3");
+
+        // The script and its launch record are kept for inspection
+        let script_path = error.script_path.unwrap();
+        assert_eq!(script_path, temp_dir.path().join("test_fail.sh"));
+        assert!(script_path.exists());
+
+        let launch = ScriptLaunch::load(&ScriptLaunch::record_path(&script_path)).unwrap();
+        assert_eq!(launch.program, "bash");
+        assert!(!launch.command().status().unwrap().success());
+    }
+
+    #[tokio::test]
+    async fn test_execute_step_strict_mode_catches_pipe_failures() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let step = Step::new("test_pipe", "bash", "false | cat");
+        let context = StepContext::new(temp_dir.path());
+
+        assert!(execute_step(&step, &context).await.is_err());
+
+        let relaxed = step.with_shell(ShellConfig {
+            strict: Some(false),
+            ..ShellConfig::default()
+        });
+        assert!(execute_step(&relaxed, &context).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_execute_step_prologue_and_interpreter() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let context = StepContext::new(temp_dir.path()).with_shell(ShellConfig {
+            prologue: Some("GREETING=hello".to_string()),
+            epilogue: Some("echo bye".to_string()),
+            ..ShellConfig::default()
+        });
+
+        let step = Step::new("test_prologue", "bash", "echo $GREETING");
+        let output = execute_step(&step, &context).await.unwrap();
+        assert_eq!(output.stdout_tail, vec!["hello", "bye"]);
+
+        // Per-step overrides replace single fields of the workflow settings
+        let step = Step::new("test_sh", "bash", "echo $GREETING from sh")
+            .with_shell(ShellConfig::with_interpreter("sh"));
+        let output = execute_step(&step, &context).await.unwrap();
+        assert_eq!(output.stdout_tail, vec!["hello from sh", "bye"]);
+    }
+
+    #[tokio::test]
+    async fn test_execute_step_inline_bash_script() {
+        use crate::workflow::{InlineScript, ScriptLanguage};
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let context = StepContext::new(temp_dir.path()).with_keep_scripts(true);
+        let step = Step::new("inline", "bash", "")
+            .with_input("a.txt,b.txt")
+            .with_param("mode", "fast")
+            .with_script(InlineScript::new(
+                ScriptLanguage::Bash,
+                "grep -c '\"mode\": \"fast\"' \"$RUSTRUNNER_STEP_JSON\"",
+            ));
+
+        let output = execute_step(&step, &context).await.unwrap();
+        assert_eq!(output.stdout_tail, vec!["1"]);
+
+        let sidecar = fs::read_to_string(temp_dir.path().join("inline.step.json")).unwrap();
+        let data: StepData = serde_json::from_str(&sidecar).unwrap();
+        assert_eq!(data.input, vec!["a.txt", "b.txt"]);
+        assert_eq!(data.params["mode"], "fast");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_step_in_container() {
+        use super::super::container::ContainerEngine;
+        use std::os::unix::fs::PermissionsExt;
+
+        // Fake docker CLI: logs its arguments and runs everything after the image
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log = temp_dir.path().join("docker.log");
+        let stub = temp_dir.path().join("docker");
+        fs::write(
+            &stub,
+            format!(
+                "#!/bin/sh\necho \"$@\" >> {}\nwhile [ \"$1\" != stub/tool:1.0 ]; do shift; done\nshift\nexec \"$@\"\n",
+                log.display()
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&stub, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let data_dir = temp_dir.path().join("data");
+        let output_file = data_dir.join("out.txt");
+        let step = Step::new("in_image", "samtools", "echo inside > {output}; echo done")
+            .with_output(output_file.to_str().unwrap())
+            .with_container("stub/tool:1.0");
+        let context = StepContext::new(temp_dir.path().join("scripts"))
+            .with_working_dir(Some(temp_dir.path().to_path_buf()))
+            .with_container_runtime(Some(
+                ContainerRuntime::new(ContainerEngine::Docker).with_program(&stub),
+            ));
+
+        let output = execute_step(&step, &context).await.unwrap();
+        assert_eq!(output.stdout_tail, vec!["done"]);
+        assert_eq!(fs::read_to_string(&output_file).unwrap(), "inside\n");
+
+        let args = fs::read_to_string(&log).unwrap();
+        assert!(args.starts_with("run --rm --name rustrunner-in_image-"), "{}", args);
+        assert!(args.contains(&format!("-v {0}:{0}", temp_dir.path().display())), "{}", args);
+        assert!(args.contains(&format!("-w {}", temp_dir.path().display())), "{}", args);
+        assert!(args.contains("stub/tool:1.0 bash "), "{}", args);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_step_killed_by_signal() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let step = Step::new("test_signal", "bash", "kill -9 $$");
+
+        let error = execute_step(&step, &StepContext::new(temp_dir.path())).await.unwrap_err();
+
+        assert_eq!(error.exit_code, None);
+        assert_eq!(error.signal, Some(9));
+        assert!(error.to_string().contains("killed by signal 9"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_step_timeout() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let step = Step::new("test_timeout", "bash", "sleep 30").with_timeout(1);
+
+        let started = std::time::Instant::now();
+        let result = execute_step(&step, &StepContext::new(temp_dir.path())).await;
+
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(10));
     }
 }
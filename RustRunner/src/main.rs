@@ -19,16 +19,84 @@
 //!
 //! # Set maximum parallel jobs
 //! rustrunner workflow.yaml --parallel 8
+//!
+//! # Stream progress events as JSON lines
+//! rustrunner workflow.yaml --events events.jsonl
+//!
+//! # Write a run report (JSON, YAML or standalone HTML by extension)
+//! rustrunner workflow.yaml --report report.json
+//!
+//! # Export the execution timeline for chrome://tracing or Perfetto
+//! rustrunner workflow.yaml --trace trace.json
+//!
+//! # List past runs and compare two of them
+//! rustrunner history
+//! rustrunner history compare 20240131-142501 20240201-090000
+//!
+//! # Re-run the kept script of a failed step by hand
+//! rustrunner rerun-step align
+//!
+//! # Lock the workflow's conda environments, then reproduce them exactly
+//! rustrunner env export workflow.yaml
+//! rustrunner workflow.yaml --locked
+//!
+//! # Carry environments to a machine without internet access
+//! rustrunner env bundle workflow.yaml
+//! rustrunner env unbundle workflow-envs.tar.gz
+//!
+//! # Install from a local channel
+//! rustrunner workflow.yaml --channel file:///data/conda-channel --offline
+//!
+//! # Use samtools & co. from PATH where installed instead of conda
+//! rustrunner workflow.yaml --tool-policy prefer-system
+//!
+//! # Run container steps with apptainer
+//! rustrunner workflow.yaml --container-runtime apptainer
+//!
+//! # Submit every step as a SLURM job
+//! rustrunner workflow.yaml --executor slurm --partition short
+//!
+//! # Check that micromamba and the mapped environments work
+//! rustrunner env doctor
+//!
+//! # Manage environments: create one for bwa and samtools, drop unused ones
+//! rustrunner env create bwa samtools --name align
+//! rustrunner env list
+//! rustrunner env prune workflow.yaml other.yaml --yes
+//!
+//! # Show how each tool is run (or --json for the GUI's tool palette)
+//! rustrunner tools
+//!
+//! # Run every step on a lab server over SSH
+//! rustrunner workflow.yaml --remote lab-server:/data/projects/rnaseq
 //! ```
 
+use std::collections::BTreeSet;
 use std::env;
-use std::path::PathBuf;
+use std::fs::File;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
 
 use log::{error, info, warn};
 
-use rustrunner::execution::Engine;
+use rustrunner::environment::conda::{
+    create_env_from_lock, export_lock, package_cache_dir, search_packages, update_env,
+};
+use rustrunner::environment::doctor;
+use rustrunner::environment::manage::{self, env_names};
+use rustrunner::environment::registry::USER_REGISTRY_PATH;
+use rustrunner::environment::{
+    bundle, lock, EnvOptions, Lockfile, RequiredEnv, ToolEnvMap, ToolMode, ToolPolicy,
+    ToolRegistry, MAMBA_ROOT_PREFIX,
+};
+use rustrunner::execution::scripts::RUNS_DIR;
+use rustrunner::execution::{
+    ContainerRuntime, Engine, EngineHandle, JsonLinesObserver, ScriptLaunch, SlurmExecutor,
+    SshExecutor,
+};
+use rustrunner::monitoring::{RunComparison, RunHistory};
 use rustrunner::workflow::parser::load_workflow;
+use rustrunner::workflow::RemoteConfig;
 use rustrunner::{APP_NAME, VERSION};
 
 /// Default workflow file used when none is specified.
@@ -43,9 +111,21 @@ struct Config {
     workflow_path: String,
     pause_flag_path: Option<String>,
     dry_run: bool,
+    keep_scripts: bool,
+    locked: bool,
+    offline: bool,
+    channels: Vec<String>,
+    container_runtime: Option<ContainerRuntime>,
+    slurm: bool,
+    partition: Option<String>,
+    remote: Option<RemoteConfig>,
+    tool_policy: ToolPolicy,
     working_dir: Option<PathBuf>,
     max_parallel: usize,
     verbose: bool,
+    events_path: Option<String>,
+    report_path: Option<String>,
+    trace_path: Option<String>,
 }
 
 impl Default for Config {
@@ -54,9 +134,21 @@ impl Default for Config {
             workflow_path: DEFAULT_WORKFLOW.to_string(),
             pause_flag_path: None,
             dry_run: false,
+            keep_scripts: false,
+            locked: false,
+            offline: false,
+            channels: Vec::new(),
+            container_runtime: None,
+            slurm: false,
+            partition: None,
+            remote: None,
+            tool_policy: ToolPolicy::default(),
             working_dir: None,
             max_parallel: DEFAULT_MAX_PARALLEL,
             verbose: false,
+            events_path: None,
+            report_path: None,
+            trace_path: None,
         }
     }
 }
@@ -90,6 +182,44 @@ fn print_banner() {
 /// Prints usage information.
 fn print_usage() {
     println!("Usage: rustrunner [OPTIONS] <WORKFLOW_FILE> [PAUSE_FLAG_PATH]");
+    println!("       rustrunner history [--workflow PATH] [--limit N]");
+    println!("       rustrunner history compare <RUN_ID> <RUN_ID>");
+    println!("       rustrunner rerun-step <STEP_ID> [--run RUN_ID]");
+    println!("       rustrunner env export [WORKFLOW_FILE] [--output DIR] [--working-dir PATH]");
+    println!("       rustrunner env bundle [WORKFLOW_FILE] [--output PATH] [--working-dir PATH]");
+    println!("       rustrunner env unbundle <BUNDLE> [--dest DIR]");
+    println!("       rustrunner env doctor");
+    println!("       rustrunner env list [--json]");
+    println!("       rustrunner env create <TOOL>... [--name ENV] [--channel URL] [--offline]");
+    println!("       rustrunner env remove <ENV>...");
+    println!("       rustrunner env update <ENV>... [--channel URL] [--offline]");
+    println!("       rustrunner env prune <WORKFLOW_FILE>... [--working-dir PATH] [--yes]");
+    println!("       rustrunner env search <QUERY> [--channel URL] [--offline] [--json]");
+    println!("       rustrunner tools [--json]");
+    println!();
+    println!("Commands:");
+    println!("  history             List past runs recorded in .rustrunner/history.jsonl");
+    println!("  history compare     Compare the step timings of two runs (IDs may be prefixes)");
+    println!("  rerun-step          Re-run a step's kept script in the same environment");
+    println!("                      (latest run that kept it, unless --run is given)");
+    println!("  env export          Write explicit lockfiles for the workflow's conda environments");
+    println!("                      (default: <workflow>.lock/ next to the workflow)");
+    println!("  env bundle          Pack the workflow's lockfiles and cached packages into a tarball");
+    println!("                      (default: <workflow>-envs.tar.gz)");
+    println!("  env unbundle        Install a bundle's environments offline, writing its lockfiles");
+    println!("                      into --dest (default: current directory)");
+    println!("  env doctor          Check micromamba, its root prefix, env_map.json and each mapped");
+    println!("                      environment, suggesting a fix for every problem");
+    println!("  env list            List the environments in ~/.rustrunner/micromamba and their tools");
+    println!("  env create          Create an environment with the tools' packages and map the tools");
+    println!("                      to it in env_map.json (default name: the first tool)");
+    println!("  env remove          Remove environments and unmap their tools");
+    println!("  env update          Update all packages of environments");
+    println!("  env prune           List the environments none of the workflows use; remove them");
+    println!("                      with --yes (they may belong to other projects)");
+    println!("  env search          Search the channels (default: bioconda, conda-forge) for packages");
+    println!("  tools               List the tool registry: how each tool is run and which conda");
+    println!("                      package, image and default threads it uses");
     println!();
     println!("Arguments:");
     println!("  <WORKFLOW_FILE>     Path to workflow YAML file");
@@ -97,8 +227,27 @@ fn print_usage() {
     println!();
     println!("Options:");
     println!("  --dry-run           Preview commands without execution");
+    println!("  --keep-scripts      Keep step scripts in .rustrunner/runs/<run-id>/scripts");
+    println!("  --locked            Create conda environments from lockfiles instead of solving");
+    println!("  --channel URL       Install packages from this channel instead of the defaults");
+    println!("                      (repeatable; local directories become file:// URLs)");
+    println!("  --offline           Create environments without network access");
+    println!("  --tool-policy POLICY");
+    println!("                      Where conda tools come from: prefer-system (use tools on");
+    println!("                      PATH), prefer-conda (default; PATH only if an environment");
+    println!("                      can't be created) or conda-only");
+    println!("  --container-runtime NAME|PATH");
+    println!("                      Run container steps with podman, docker or apptainer");
+    println!("                      (default: first found on PATH)");
+    println!("  --executor NAME     Where steps run: local (default) or slurm");
+    println!("  --partition NAME    SLURM partition to submit to");
+    println!("  --remote HOST:DIR   Run steps over SSH in DIR on HOST (overrides the");
+    println!("                      workflow's remote section)");
     println!("  --working-dir PATH  Set working directory for file operations");
     println!("  --parallel N        Maximum parallel jobs (default: {})", DEFAULT_MAX_PARALLEL);
+    println!("  --events PATH       Write progress events as JSON lines to a file");
+    println!("  --report PATH       Write a run report (.json, .yaml, .yml or .html)");
+    println!("  --trace PATH        Write the timeline as Chrome Trace Event JSON");
     println!("  --verbose           Enable debug logging");
     println!("  --help              Show this help message");
     println!("  --version           Show version information");
@@ -130,6 +279,63 @@ fn parse_arguments(args: &[String]) -> Result<Config, String> {
             "--dry-run" => {
                 config.dry_run = true;
             }
+            "--keep-scripts" => {
+                config.keep_scripts = true;
+            }
+            "--locked" => {
+                config.locked = true;
+            }
+            "--offline" => {
+                config.offline = true;
+            }
+            "--channel" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--channel requires a channel argument".to_string());
+                }
+                config.channels.push(args[i].clone());
+            }
+            "--container-runtime" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--container-runtime requires a runtime argument".to_string());
+                }
+                config.container_runtime = Some(ContainerRuntime::parse(&args[i])?);
+            }
+            "--executor" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--executor requires a name argument".to_string());
+                }
+                config.slurm = match args[i].as_str() {
+                    "local" => false,
+                    "slurm" => true,
+                    other => {
+                        return Err(format!("Unknown executor '{}' (expected local or slurm)", other))
+                    }
+                };
+            }
+            "--partition" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--partition requires a name argument".to_string());
+                }
+                config.partition = Some(args[i].clone());
+            }
+            "--tool-policy" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--tool-policy requires a policy argument".to_string());
+                }
+                config.tool_policy = ToolPolicy::parse(&args[i])?;
+            }
+            "--remote" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--remote requires a HOST:DIR argument".to_string());
+                }
+                config.remote = Some(RemoteConfig::parse(&args[i])?);
+            }
             "--verbose" | "-v" => {
                 config.verbose = true;
             }
@@ -149,6 +355,31 @@ fn parse_arguments(args: &[String]) -> Result<Config, String> {
                     .parse()
                     .map_err(|_| format!("Invalid parallel value: {}", args[i]))?;
             }
+            "--events" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--events requires a path argument".to_string());
+                }
+                // Progress and the summary also go to stdout and would corrupt the stream
+                if args[i] == "-" {
+                    return Err("--events requires a file path, not stdout".to_string());
+                }
+                config.events_path = Some(args[i].clone());
+            }
+            "--report" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--report requires a path argument".to_string());
+                }
+                config.report_path = Some(args[i].clone());
+            }
+            "--trace" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("--trace requires a path argument".to_string());
+                }
+                config.trace_path = Some(args[i].clone());
+            }
             arg if arg.starts_with('-') => {
                 return Err(format!("Unknown option: {}", arg));
             }
@@ -193,10 +424,564 @@ fn setup_working_directory(
     Ok(Some(dir))
 }
 
+/// Handles `rustrunner history ...`.
+fn run_history(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let history = RunHistory::default();
+
+    if args.first().map(String::as_str) == Some("compare") {
+        let [base, other] = &args[1..] else {
+            return Err("history compare requires two run IDs".into());
+        };
+        let base = history.find(base)?;
+        let other = history.find(other)?;
+        print!("{}", RunComparison::new(&base, &other));
+        return Ok(());
+    }
+
+    let mut workflow = None;
+    let mut limit = 20;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--workflow" => {
+                i += 1;
+                workflow = Some(args.get(i).ok_or("--workflow requires a path argument")?);
+            }
+            "--limit" => {
+                i += 1;
+                let value = args.get(i).ok_or("--limit requires a number argument")?;
+                limit = value
+                    .parse()
+                    .map_err(|_| format!("Invalid limit value: {}", value))?;
+            }
+            other => return Err(format!("Unknown history argument: {}", other).into()),
+        }
+        i += 1;
+    }
+
+    let runs: Vec<_> = history
+        .load()?
+        .into_iter()
+        .rev()
+        .filter(|run| workflow.map_or(true, |w| &run.workflow_path == w))
+        .take(limit)
+        .collect();
+
+    if runs.is_empty() {
+        println!("No runs recorded in {}", history.path().display());
+        return Ok(());
+    }
+
+    println!(
+        "{:<20} {:<20} {:<24} {:<8} {:>10} {:>7}  host",
+        "run", "started", "workflow", "status", "duration", "steps"
+    );
+    for run in runs {
+        println!(
+            "{:<20} {:<20} {:<24} {:<8} {:>9.1}s {:>7}  {}",
+            run.run_id,
+            run.started_at.format("%Y-%m-%d %H:%M:%S"),
+            run.workflow_path,
+            if run.success { "ok" } else { "failed" },
+            run.duration_ms as f64 / 1000.0,
+            format!("{}/{}", run.completed_steps(), run.steps.len()),
+            run.host
+        );
+    }
+
+    Ok(())
+}
+
+/// Handles `rustrunner rerun-step <STEP_ID> [--run RUN_ID]`.
+fn run_rerun_step(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut step_id = None;
+    let mut run_id = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--run" => {
+                i += 1;
+                run_id = Some(args.get(i).ok_or("--run requires a run ID argument")?.as_str());
+            }
+            arg if arg.starts_with('-') => {
+                return Err(format!("Unknown rerun-step argument: {}", arg).into());
+            }
+            arg if step_id.is_none() => step_id = Some(arg),
+            arg => return Err(format!("Unexpected argument: {}", arg).into()),
+        }
+        i += 1;
+    }
+
+    let step_id = step_id.ok_or("rerun-step requires a step ID")?;
+    let launch = ScriptLaunch::find(Path::new(RUNS_DIR), step_id, run_id)?;
+
+    info!("Re-running {}", launch.script_path.display());
+    let status = launch
+        .command()
+        .status()
+        .map_err(|e| format!("Failed to start '{}': {}", launch.program, e))?;
+
+    if !status.success() {
+        return Err(format!("Step '{}' failed again ({})", step_id, status).into());
+    }
+    Ok(())
+}
+
+/// Handles `rustrunner env <COMMAND> ...`.
+fn run_env(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    match args.first().map(String::as_str) {
+        Some("export") => run_env_export(&args[1..]),
+        Some("bundle") => run_env_bundle(&args[1..]),
+        Some("unbundle") => run_env_unbundle(&args[1..]),
+        Some("doctor") => run_env_doctor(&args[1..]),
+        Some("list") => run_env_list(&args[1..]),
+        Some("create") => run_env_create(&args[1..]),
+        Some("remove") => run_env_remove(&args[1..]),
+        Some("update") => run_env_update(&args[1..]),
+        Some("prune") => run_env_prune(&args[1..]),
+        Some("search") => run_env_search(&args[1..]),
+        Some(other) => Err(format!("Unknown env command: {}", other).into()),
+        None => Err(
+            "env requires a command (export, bundle, unbundle, doctor, list, create, remove, \
+             update, prune, search)"
+                .into(),
+        ),
+    }
+}
+
+/// Arguments shared by the `env` commands that work on a workflow.
+struct EnvArgs {
+    workflow_path: String,
+    output: Option<PathBuf>,
+    working_dir: Option<PathBuf>,
+}
+
+impl EnvArgs {
+    /// Parses `[WORKFLOW_FILE] [--output PATH] [--working-dir PATH]`.
+    fn parse(command: &str, args: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut workflow_path = None;
+        let mut output = None;
+        let mut working_dir = None;
+        let mut i = 0;
+        while i < args.len() {
+            match args[i].as_str() {
+                "--output" => {
+                    i += 1;
+                    output = Some(PathBuf::from(
+                        args.get(i).ok_or("--output requires a path argument")?,
+                    ));
+                }
+                "--working-dir" => {
+                    i += 1;
+                    working_dir = Some(PathBuf::from(
+                        args.get(i).ok_or("--working-dir requires a path argument")?,
+                    ));
+                }
+                arg if arg.starts_with('-') => {
+                    return Err(format!("Unknown env {} argument: {}", command, arg).into());
+                }
+                arg if workflow_path.is_none() => workflow_path = Some(arg.to_string()),
+                arg => return Err(format!("Unexpected argument: {}", arg).into()),
+            }
+            i += 1;
+        }
+
+        Ok(Self {
+            workflow_path: workflow_path.unwrap_or_else(|| DEFAULT_WORKFLOW.to_string()),
+            output,
+            working_dir,
+        })
+    }
+
+    /// Returns the lockfile directory next to the workflow.
+    fn lock_dir(&self) -> PathBuf {
+        lock::lock_dir(Path::new(&self.workflow_path))
+    }
+
+    /// Loads the workflow and lists the conda environments it needs.
+    fn environments(&self) -> Result<Vec<RequiredEnv>, Box<dyn std::error::Error>> {
+        let workflow = load_workflow(&self.workflow_path).map_err(|e| {
+            format!("Could not load workflow from '{}': {}", self.workflow_path, e)
+        })?;
+
+        let mut engine = Engine::new(workflow);
+        if let Some(ref dir) = self.working_dir {
+            engine.set_working_dir(dir);
+        }
+        Ok(engine.environments()?)
+    }
+}
+
+/// Handles `rustrunner env export [WORKFLOW_FILE] [--output DIR] [--working-dir PATH]`.
+fn run_env_export(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = EnvArgs::parse("export", args)?;
+    let lock_dir = args.output.clone().unwrap_or_else(|| args.lock_dir());
+
+    let envs = args.environments()?;
+    if envs.is_empty() {
+        println!("Workflow uses no conda environments");
+        return Ok(());
+    }
+
+    for env in envs {
+        let lockfile = export_lock(&env.name)?;
+        let path = lockfile.save(&lock_dir)?;
+        println!(
+            "{:<24} {:>4} packages  {}",
+            env.name,
+            lockfile.packages.len(),
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Handles `rustrunner env bundle [WORKFLOW_FILE] [--output PATH] [--working-dir PATH]`.
+fn run_env_bundle(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = EnvArgs::parse("bundle", args)?;
+    let lock_dir = args.lock_dir();
+    let output = args.output.clone().unwrap_or_else(|| {
+        let stem = Path::new(&args.workflow_path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "workflow".to_string());
+        Path::new(&args.workflow_path).with_file_name(format!("{}-envs.tar.gz", stem))
+    });
+
+    let envs = args.environments()?;
+    if envs.is_empty() {
+        println!("Workflow uses no conda environments");
+        return Ok(());
+    }
+
+    // Prefer the committed lockfiles so the bundle matches them exactly
+    let lockfiles = envs
+        .iter()
+        .map(|env| {
+            if Lockfile::path(&lock_dir, &env.name).exists() {
+                Lockfile::load(&lock_dir, &env.name)
+            } else {
+                export_lock(&env.name)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let packages = bundle::create_bundle(&lock_dir, &lockfiles, &package_cache_dir(), &output)?;
+    println!(
+        "Bundled {} environments ({} packages) into {}",
+        lockfiles.len(),
+        packages,
+        output.display()
+    );
+    println!("Install on the target machine with: rustrunner env unbundle {}", output.display());
+
+    Ok(())
+}
+
+/// Handles `rustrunner env unbundle <BUNDLE> [--dest DIR]`.
+fn run_env_unbundle(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut bundle_path = None;
+    let mut dest = PathBuf::from(".");
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--dest" => {
+                i += 1;
+                dest = PathBuf::from(args.get(i).ok_or("--dest requires a path argument")?);
+            }
+            arg if arg.starts_with('-') => {
+                return Err(format!("Unknown env unbundle argument: {}", arg).into());
+            }
+            arg if bundle_path.is_none() => bundle_path = Some(PathBuf::from(arg)),
+            arg => return Err(format!("Unexpected argument: {}", arg).into()),
+        }
+        i += 1;
+    }
+
+    let bundle_path = bundle_path.ok_or("env unbundle requires a bundle path")?;
+    let unpacked = bundle::unpack_bundle(&bundle_path, &package_cache_dir(), &dest)?;
+    info!(
+        "Added {} packages to {}",
+        unpacked.new_packages,
+        package_cache_dir().display()
+    );
+
+    let options = EnvOptions::default().with_offline(true);
+    for lock in &unpacked.lockfiles {
+        create_env_from_lock(&lock.env_name, &Lockfile::path(&unpacked.lock_dir, &lock.env_name), &options)?;
+        println!("{:<24} {:>4} packages  ready", lock.env_name, lock.packages.len());
+    }
+    println!("Lockfiles written to {}", unpacked.lock_dir.display());
+
+    Ok(())
+}
+
+/// Handles `rustrunner env doctor`.
+fn run_env_doctor(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(arg) = args.first() {
+        return Err(format!("Unknown env doctor argument: {}", arg).into());
+    }
+
+    let diagnosis = doctor::doctor();
+    print!("{}", diagnosis);
+
+    let problems = diagnosis.problems().count();
+    if problems > 0 {
+        return Err(format!("Found {} environment problem(s)", problems).into());
+    }
+    println!("All environment checks passed");
+    Ok(())
+}
+
+/// Arguments of the `env` commands that manage environments.
+#[derive(Default)]
+struct ManageArgs {
+    names: Vec<String>,
+    env_name: Option<String>,
+    options: EnvOptions,
+    working_dir: Option<PathBuf>,
+    yes: bool,
+    json: bool,
+}
+
+impl ManageArgs {
+    /// Parses positional arguments and the options in `allowed` out of
+    /// `--name`, `--channel`, `--offline`, `--working-dir`, `--dry-run` and
+    /// `--json`.
+    fn parse(command: &str, args: &[String], allowed: &[&str]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut parsed = Self::default();
+        let mut i = 0;
+        while i < args.len() {
+            match args[i].as_str() {
+                arg if arg.starts_with('-') && !allowed.contains(&arg) => {
+                    return Err(format!("Unknown env {} argument: {}", command, arg).into());
+                }
+                "--name" => {
+                    i += 1;
+                    parsed.env_name = Some(args.get(i).ok_or("--name requires an environment name")?.clone());
+                }
+                "--channel" => {
+                    i += 1;
+                    let channel = args.get(i).ok_or("--channel requires a URL or directory")?;
+                    parsed.options = parsed.options.with_channel(channel.as_str());
+                }
+                "--offline" => parsed.options = parsed.options.with_offline(true),
+                "--working-dir" => {
+                    i += 1;
+                    parsed.working_dir = Some(PathBuf::from(
+                        args.get(i).ok_or("--working-dir requires a path argument")?,
+                    ));
+                }
+                "--yes" => parsed.yes = true,
+                "--json" => parsed.json = true,
+                arg => parsed.names.push(arg.to_string()),
+            }
+            i += 1;
+        }
+        Ok(parsed)
+    }
+}
+
+/// Handles `rustrunner env list [--json]`.
+fn run_env_list(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = ManageArgs::parse("list", args, &["--json"])?;
+    if let Some(arg) = args.names.first() {
+        return Err(format!("Unexpected argument: {}", arg).into());
+    }
+
+    let envs = manage::list_envs(&ToolEnvMap::load())?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&envs)?);
+        return Ok(());
+    }
+
+    if envs.is_empty() {
+        println!("No environments in {}", MAMBA_ROOT_PREFIX.display());
+        return Ok(());
+    }
+    println!("{:<24} {:<32} path", "environment", "tools");
+    for env in envs {
+        let tools = if env.tools.is_empty() { "-".to_string() } else { env.tools.join(", ") };
+        println!("{:<24} {:<32} {}", env.name, tools, env.prefix.display());
+    }
+    Ok(())
+}
+
+/// Handles `rustrunner env create <TOOL>... [--name ENV] [--channel URL] [--offline]`.
+fn run_env_create(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = ManageArgs::parse("create", args, &["--name", "--channel", "--offline"])?;
+    let env_name = match (&args.env_name, args.names.first()) {
+        (Some(name), _) => name.clone(),
+        (None, Some(tool)) => tool.clone(),
+        (None, None) => return Err("env create requires at least one tool".into()),
+    };
+
+    manage::create_tool_env(&env_name, &args.names, &ToolRegistry::load()?, &args.options)?;
+
+    println!("Environment '{}' ready for {}", env_name, args.names.join(", "));
+    Ok(())
+}
+
+/// Handles `rustrunner env remove <ENV>...`.
+fn run_env_remove(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = ManageArgs::parse("remove", args, &[])?;
+    if args.names.is_empty() {
+        return Err("env remove requires at least one environment name".into());
+    }
+
+    remove_envs(&args.names)
+}
+
+/// Removes environments, reporting each; stops at the first failure.
+fn remove_envs(names: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    for name in names {
+        let tools = manage::remove_tool_env(name)?;
+        if tools.is_empty() {
+            println!("Removed '{}'", name);
+        } else {
+            println!("Removed '{}' (unmapped {})", name, tools.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// Handles `rustrunner env update <ENV>... [--channel URL] [--offline]`.
+fn run_env_update(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = ManageArgs::parse("update", args, &["--channel", "--offline"])?;
+    if args.names.is_empty() {
+        return Err("env update requires at least one environment name".into());
+    }
+
+    for name in &args.names {
+        update_env(name, &args.options)?;
+        println!("Updated '{}'", name);
+    }
+    Ok(())
+}
+
+/// Handles `rustrunner env prune <WORKFLOW_FILE>... [--working-dir PATH] [--dry-run]`.
+fn run_env_prune(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = ManageArgs::parse("prune", args, &["--working-dir", "--yes"])?;
+    if args.names.is_empty() {
+        return Err("env prune requires the workflow files whose environments to keep".into());
+    }
+
+    let mut referenced = BTreeSet::new();
+    for workflow_path in &args.names {
+        let workflow = EnvArgs {
+            workflow_path: workflow_path.clone(),
+            output: None,
+            working_dir: args.working_dir.clone(),
+        };
+        referenced.extend(workflow.environments()?.into_iter().map(|env| env.name));
+    }
+
+    let installed = env_names(&MAMBA_ROOT_PREFIX.join("envs"))?;
+    let unused = manage::unreferenced_envs(&installed, &referenced);
+    if unused.is_empty() {
+        println!("All {} environments are in use", installed.len());
+        return Ok(());
+    }
+
+    // The root prefix is shared by every project on this machine, so only
+    // list what would go unless removal is confirmed
+    println!("Environments none of the workflows use:");
+    for name in &unused {
+        println!("  {}", name);
+    }
+    if !args.yes {
+        println!();
+        println!("Nothing removed. Re-run with --yes to remove them.");
+        return Ok(());
+    }
+
+    remove_envs(&unused)
+}
+
+/// Handles `rustrunner env search <QUERY> [--channel URL] [--offline] [--json]`.
+fn run_env_search(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let args = ManageArgs::parse("search", args, &["--channel", "--offline", "--json"])?;
+    let [query] = args.names.as_slice() else {
+        return Err("env search requires one query".into());
+    };
+
+    let packages = search_packages(query, &args.options)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&packages)?);
+    } else if packages.is_empty() {
+        println!("No packages found for '{}'", query);
+    } else {
+        println!("{:<24} {:<12} {:<24} channel", "name", "version", "build");
+        for package in packages {
+            println!(
+                "{:<24} {:<12} {:<24} {}/{}",
+                package.name, package.version, package.build, package.channel, package.subdir
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Handles `rustrunner tools [--json]`.
+fn run_tools(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let json = match args {
+        [] => false,
+        [flag] if flag == "--json" => true,
+        [other, ..] => return Err(format!("Unknown tools argument: {}", other).into()),
+    };
+
+    let registry = ToolRegistry::load()?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&registry)?);
+        return Ok(());
+    }
+
+    println!("{:<16} {:<10} {:<20} {:>7}  container", "tool", "mode", "package", "threads");
+    for (tool, info) in registry.tools() {
+        let package = match info.mode {
+            ToolMode::Conda => registry.package_spec(tool),
+            _ => "-".to_string(),
+        };
+        println!(
+            "{:<16} {:<10} {:<20} {:>7}  {}",
+            tool,
+            info.mode,
+            package,
+            info.threads.map_or("-".to_string(), |threads| threads.to_string()),
+            info.container.as_deref().unwrap_or("-")
+        );
+    }
+    println!();
+    println!("Unlisted tools are installed from conda under their own name.");
+    println!("Extend or override entries in {}", USER_REGISTRY_PATH.display());
+    Ok(())
+}
+
 /// Main application entry point.
 fn run() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = env::args().collect();
 
+    match args.get(1).map(String::as_str) {
+        Some("history") => {
+            setup_logging(false);
+            return run_history(&args[2..]);
+        }
+        Some("rerun-step") => {
+            setup_logging(false);
+            return run_rerun_step(&args[2..]);
+        }
+        Some("env") => {
+            setup_logging(false);
+            return run_env(&args[2..]);
+        }
+        Some("tools") => {
+            setup_logging(false);
+            return run_tools(&args[2..]);
+        }
+        _ => {}
+    }
+
     // Parse arguments
     let config = parse_arguments(&args).map_err(|e| {
         eprintln!("Error: {}", e);
@@ -245,6 +1030,37 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
     engine.set_workflow_path(&config.workflow_path);
     engine.set_max_parallel(config.max_parallel);
     engine.set_dry_run(config.dry_run);
+    engine.set_keep_scripts(config.keep_scripts);
+    let env_options = config
+        .channels
+        .iter()
+        .fold(EnvOptions::default(), |options, channel| options.with_channel(channel))
+        .with_locked(config.locked)
+        .with_offline(config.offline);
+    engine.set_env_options(env_options);
+    engine.set_tool_policy(config.tool_policy);
+
+    if let Some(runtime) = config.container_runtime {
+        engine.set_container_runtime(runtime);
+    }
+
+    if config.slurm && config.remote.is_some() {
+        return Err("--remote cannot be combined with --executor slurm".into());
+    }
+
+    if let Some(remote) = config.remote {
+        engine.set_executor(SshExecutor::new(remote));
+    }
+
+    if config.slurm {
+        let mut executor = SlurmExecutor::new();
+        if let Some(partition) = config.partition {
+            executor = executor.with_partition(partition);
+        }
+        engine.set_executor(executor);
+    } else if config.partition.is_some() {
+        return Err("--partition requires --executor slurm".into());
+    }
 
     if let Some(pause_path) = config.pause_flag_path {
         engine.set_pause_flag_path(pause_path);
@@ -254,12 +1070,75 @@ fn run() -> Result<(), Box<dyn std::error::Error>> {
         engine.set_working_dir(dir);
     }
 
-    // Execute workflow
-    engine.run()?;
+    if let Some(ref path) = config.events_path {
+        let file = File::create(path)
+            .map_err(|e| format!("Could not create events file '{}': {}", path, e))?;
+        engine.add_observer(JsonLinesObserver::new(file));
+    }
+
+    // Execute workflow; Ctrl-C and SIGTERM cancel it so running steps,
+    // which sit in their own process groups, are killed too
+    let handle = engine.handle();
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let result = runtime.block_on(async {
+        tokio::spawn(cancel_on_signal(handle));
+        engine.run_async().await
+    });
 
+    if let Some(report) = engine.report() {
+        println!();
+        println!("{}", report);
+
+        if let Some(ref path) = config.report_path {
+            match report.save(path) {
+                Ok(()) => info!("Run report written to {}", path),
+                Err(e) => error!("Failed to write run report '{}': {}", path, e),
+            }
+        }
+    }
+
+    if let (Some(path), Some(timeline)) = (config.trace_path.as_ref(), engine.timeline()) {
+        match timeline.save_chrome_trace(path) {
+            Ok(()) => info!("Execution trace written to {}", path),
+            Err(e) => error!("Failed to write execution trace '{}': {}", path, e),
+        }
+    }
+
+    result.map_err(|e| e as Box<dyn std::error::Error>)?;
     Ok(())
 }
 
+/// Cancels the run on Ctrl-C or SIGTERM.
+async fn cancel_on_signal(handle: EngineHandle) {
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
+
+    warn!("Interrupted, stopping running steps");
+    handle.cancel();
+}
+
 fn main() -> ExitCode {
     match run() {
         Ok(()) => ExitCode::SUCCESS,
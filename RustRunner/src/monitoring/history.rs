@@ -0,0 +1,516 @@
+//! Run History
+//!
+//! Append-only record of every workflow run, stored as JSON lines in
+//! `.rustrunner/history.jsonl`. Each record keeps the per-step fingerprints,
+//! durations, resource peaks and exit status of one run, so runs can be
+//! listed and compared, and the planner can estimate step durations from
+//! earlier runs of the same work.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use rustrunner::monitoring::RunHistory;
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let history = RunHistory::default();
+//!
+//!     for run in history.load()? {
+//!         println!("{} {} {}ms", run.run_id, run.workflow_path, run.duration_ms);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::workflow::Workflow;
+
+use super::report::{RunReport, StepOutcome, StepReport};
+
+/// Default location of the history file.
+pub const HISTORY_FILE: &str = ".rustrunner/history.jsonl";
+
+/// Number of most recent runs of a step averaged for duration estimates.
+const DURATION_WINDOW: usize = 5;
+
+/// Per-step entry of a [`RunRecord`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepRecord {
+    /// Step ID
+    pub id: String,
+    /// Fingerprint of the step's work
+    pub fingerprint: String,
+    /// Final status
+    pub status: StepOutcome,
+    /// Run time in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Exit code of the step's process
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Peak resident memory in megabytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_memory_mb: Option<u64>,
+    /// CPU time in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_time_ms: Option<u64>,
+    /// Bytes read from storage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_bytes: Option<u64>,
+    /// Bytes written to storage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub written_bytes: Option<u64>,
+}
+
+impl From<&StepReport> for StepRecord {
+    fn from(step: &StepReport) -> Self {
+        Self {
+            id: step.id.clone(),
+            fingerprint: step.fingerprint.clone(),
+            status: step.status,
+            duration_ms: step.duration_ms,
+            exit_code: step.exit_code,
+            peak_memory_mb: step.peak_memory_mb,
+            cpu_time_ms: step.cpu_time_ms,
+            read_bytes: step.read_bytes,
+            written_bytes: step.written_bytes,
+        }
+    }
+}
+
+/// One line of the history file: a summary of a single run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// Unique ID of the run
+    pub run_id: String,
+    /// Path of the workflow file
+    pub workflow_path: String,
+    /// Machine the run executed on
+    pub host: String,
+    /// Wall-clock start time
+    pub started_at: DateTime<Utc>,
+    /// Total run time in milliseconds
+    pub duration_ms: u64,
+    /// Whether every step completed
+    pub success: bool,
+    /// Error that ended the run, if it failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Per-step results in execution order
+    pub steps: Vec<StepRecord>,
+}
+
+impl RunRecord {
+    /// Summarizes a run report for the history file.
+    pub fn from_report(report: &RunReport) -> Self {
+        Self {
+            run_id: report.run_id.clone(),
+            workflow_path: report.workflow_path.clone(),
+            host: report.host.clone(),
+            started_at: report.started_at,
+            duration_ms: report.duration_ms,
+            success: report.success,
+            error: report.error.clone(),
+            steps: report.steps.iter().map(StepRecord::from).collect(),
+        }
+    }
+
+    /// Returns the record of a step.
+    pub fn step(&self, step_id: &str) -> Option<&StepRecord> {
+        self.steps.iter().find(|s| s.id == step_id)
+    }
+
+    /// Returns the number of steps that completed in this run.
+    pub fn completed_steps(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|s| s.status == StepOutcome::Completed)
+            .count()
+    }
+}
+
+/// Append-only store of [`RunRecord`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunHistory {
+    path: PathBuf,
+}
+
+impl RunHistory {
+    /// Opens the history stored at the given path.
+    ///
+    /// The file is created on the first [`append`](Self::append).
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Returns the path of the history file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends a run to the history.
+    pub fn append(&self, record: &RunRecord) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Loads all recorded runs, oldest first.
+    ///
+    /// A missing file is an empty history. Lines that can't be parsed
+    /// (e.g. from an interrupted write) are skipped with a warning.
+    pub fn load(&self) -> Result<Vec<RunRecord>, Box<dyn Error>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = fs::read_to_string(&self.path)?;
+        let mut records = Vec::new();
+
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(record) => records.push(record),
+                Err(e) => warn!(
+                    "Skipping malformed line {} in {}: {}",
+                    number + 1,
+                    self.path.display(),
+                    e
+                ),
+            }
+        }
+
+        Ok(records)
+    }
+
+    /// Finds a run by its ID or a unique prefix of it.
+    pub fn find(&self, run_id: &str) -> Result<RunRecord, Box<dyn Error>> {
+        let mut matches: Vec<RunRecord> = self
+            .load()?
+            .into_iter()
+            .filter(|r| r.run_id.starts_with(run_id))
+            .collect();
+
+        if let Some(exact) = matches.iter().position(|r| r.run_id == run_id) {
+            return Ok(matches.swap_remove(exact));
+        }
+
+        match matches.len() {
+            0 => Err(format!("No run found with ID '{}'", run_id).into()),
+            1 => Ok(matches.remove(0)),
+            n => Err(format!("Run ID '{}' is ambiguous ({} matches)", run_id, n).into()),
+        }
+    }
+
+    /// Estimates step durations from earlier runs of the same work.
+    ///
+    /// For each step, averages the durations of its last few successful
+    /// runs with the same fingerprint, regardless of which workflow they
+    /// were part of. Steps without history are left out.
+    pub fn expected_durations(
+        &self,
+        workflow: &Workflow,
+    ) -> Result<HashMap<String, u64>, Box<dyn Error>> {
+        let steps = &workflow.steps;
+        let wanted: HashSet<String> =
+            steps.iter().map(|s| s.fingerprint(&workflow.shell)).collect();
+
+        let mut durations: HashMap<String, Vec<u64>> = HashMap::new();
+        for record in self.load()?.iter().rev() {
+            for step in &record.steps {
+                if step.status != StepOutcome::Completed || !wanted.contains(&step.fingerprint) {
+                    continue;
+                }
+                if let Some(duration) = step.duration_ms {
+                    let recent = durations.entry(step.fingerprint.clone()).or_default();
+                    if recent.len() < DURATION_WINDOW {
+                        recent.push(duration);
+                    }
+                }
+            }
+        }
+
+        Ok(steps
+            .iter()
+            .filter_map(|step| {
+                let recent = durations.get(&step.fingerprint(&workflow.shell))?;
+                let average = recent.iter().sum::<u64>() / recent.len() as u64;
+                Some((step.id.clone(), average))
+            })
+            .collect())
+    }
+}
+
+impl Default for RunHistory {
+    fn default() -> Self {
+        Self::new(HISTORY_FILE)
+    }
+}
+
+/// Side-by-side comparison of two runs, printed by `rustrunner history compare`.
+#[derive(Debug, Clone, Copy)]
+pub struct RunComparison<'a> {
+    /// The earlier (reference) run
+    pub base: &'a RunRecord,
+    /// The run compared against it
+    pub other: &'a RunRecord,
+}
+
+impl<'a> RunComparison<'a> {
+    /// Creates a comparison of `other` against `base`.
+    pub fn new(base: &'a RunRecord, other: &'a RunRecord) -> Self {
+        Self { base, other }
+    }
+
+    /// Step IDs of both runs, in `base` order followed by new steps.
+    fn step_ids(&self) -> Vec<&'a str> {
+        let mut ids: Vec<&str> = self.base.steps.iter().map(|s| s.id.as_str()).collect();
+        for step in &self.other.steps {
+            if !ids.contains(&step.id.as_str()) {
+                ids.push(&step.id);
+            }
+        }
+        ids
+    }
+}
+
+/// Formats a duration change as a signed percentage.
+fn change(base: u64, other: u64) -> String {
+    if base == 0 {
+        return "-".to_string();
+    }
+    format!("{:+.0}%", (other as f64 - base as f64) / base as f64 * 100.0)
+}
+
+fn seconds(ms: Option<u64>) -> String {
+    ms.map(|ms| format!("{:.2}s", ms as f64 / 1000.0))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn megabytes(mb: Option<u64>) -> String {
+    mb.map(|mb| format!("{} MB", mb))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+impl fmt::Display for RunComparison<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (base, other) = (self.base, self.other);
+
+        writeln!(f, "Base:  {} ({}, {})", base.run_id, base.workflow_path, base.host)?;
+        writeln!(f, "Other: {} ({}, {})", other.run_id, other.workflow_path, other.host)?;
+        writeln!(
+            f,
+            "Total: {} -> {} ({})",
+            seconds(Some(base.duration_ms)),
+            seconds(Some(other.duration_ms)),
+            change(base.duration_ms, other.duration_ms)
+        )?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "  {:<24} {:>10} {:>10} {:>7} {:>10} {:>10}  notes",
+            "step", "base", "other", "change", "base mem", "other mem"
+        )?;
+
+        for id in self.step_ids() {
+            let a = base.step(id);
+            let b = other.step(id);
+
+            let mut notes = Vec::new();
+            match (a, b) {
+                (Some(a), Some(b)) => {
+                    if a.fingerprint != b.fingerprint {
+                        notes.push("changed".to_string());
+                    }
+                    if a.status != b.status {
+                        notes.push(format!("{} -> {}", a.status, b.status));
+                    }
+                }
+                (None, _) => notes.push("added".to_string()),
+                (_, None) => notes.push("removed".to_string()),
+            }
+
+            let duration_a = a.and_then(|s| s.duration_ms);
+            let duration_b = b.and_then(|s| s.duration_ms);
+            let delta = match (duration_a, duration_b) {
+                (Some(x), Some(y)) => change(x, y),
+                _ => "-".to_string(),
+            };
+
+            let row = format!(
+                "  {:<24} {:>10} {:>10} {:>7} {:>10} {:>10}  {}",
+                id,
+                seconds(duration_a),
+                seconds(duration_b),
+                delta,
+                megabytes(a.and_then(|s| s.peak_memory_mb)),
+                megabytes(b.and_then(|s| s.peak_memory_mb)),
+                notes.join(", ")
+            );
+            writeln!(f, "{}", row.trim_end())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::{ShellConfig, Step};
+    use tempfile::tempdir;
+
+    fn step_record(step: &Step, duration_ms: u64) -> StepRecord {
+        StepRecord {
+            id: step.id.clone(),
+            fingerprint: step.fingerprint(&ShellConfig::default()),
+            status: StepOutcome::Completed,
+            duration_ms: Some(duration_ms),
+            exit_code: Some(0),
+            peak_memory_mb: Some(100),
+            cpu_time_ms: None,
+            read_bytes: None,
+            written_bytes: None,
+        }
+    }
+
+    fn run_record(run_id: &str, steps: Vec<StepRecord>) -> RunRecord {
+        RunRecord {
+            run_id: run_id.to_string(),
+            workflow_path: "pipeline.yaml".to_string(),
+            host: "node1".to_string(),
+            started_at: Utc::now(),
+            duration_ms: steps.iter().filter_map(|s| s.duration_ms).sum(),
+            success: true,
+            error: None,
+            steps,
+        }
+    }
+
+    #[test]
+    fn test_history_append_and_load() {
+        let temp_dir = tempdir().unwrap();
+        let history = RunHistory::new(temp_dir.path().join("nested/history.jsonl"));
+        assert!(history.load().unwrap().is_empty());
+
+        let step = Step::new("a", "bash", "echo a");
+        history.append(&run_record("run-1", vec![step_record(&step, 100)])).unwrap();
+        history.append(&run_record("run-2", vec![step_record(&step, 200)])).unwrap();
+
+        let runs = history.load().unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].run_id, "run-1");
+        assert_eq!(runs[1].step("a").unwrap().duration_ms, Some(200));
+    }
+
+    #[test]
+    fn test_history_skips_malformed_lines() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("history.jsonl");
+        let history = RunHistory::new(&path);
+
+        history.append(&run_record("run-1", Vec::new())).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(file, "{{\"run_id\": \"trunc").unwrap();
+
+        assert_eq!(history.load().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_history_find_by_prefix() {
+        let temp_dir = tempdir().unwrap();
+        let history = RunHistory::new(temp_dir.path().join("history.jsonl"));
+        history.append(&run_record("20240101-100000-000", Vec::new())).unwrap();
+        history.append(&run_record("20240102-100000-000", Vec::new())).unwrap();
+
+        assert_eq!(history.find("20240102").unwrap().run_id, "20240102-100000-000");
+        assert!(history.find("2024").is_err());
+        assert!(history.find("1999").is_err());
+    }
+
+    #[test]
+    fn test_expected_durations_match_fingerprint() {
+        let temp_dir = tempdir().unwrap();
+        let history = RunHistory::new(temp_dir.path().join("history.jsonl"));
+
+        let a = Step::new("a", "bash", "sleep 1");
+        let b = Step::new("b", "bash", "sleep 2");
+        history.append(&run_record("1", vec![step_record(&a, 1000), step_record(&b, 2000)])).unwrap();
+        history.append(&run_record("2", vec![step_record(&a, 3000)])).unwrap();
+
+        // b's command changed since it was recorded
+        let mut workflow = Workflow::new();
+        workflow.add_step(a).unwrap();
+        workflow.add_step(Step::new("b", "bash", "sleep 5")).unwrap();
+        let durations = history.expected_durations(&workflow).unwrap();
+
+        assert_eq!(durations.get("a"), Some(&2000));
+        assert!(!durations.contains_key("b"));
+
+        // A new prologue is different work
+        workflow.shell.prologue = Some("module load samtools/1.18".to_string());
+        assert!(history.expected_durations(&workflow).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_run_record_from_report() {
+        let report = RunReport {
+            run_id: "r1".to_string(),
+            workflow_path: "pipeline.yaml".to_string(),
+            host: "node1".to_string(),
+            success: false,
+            error: Some("boom".to_string()),
+            dry_run: false,
+            started_at: Utc::now(),
+            finished_at: Utc::now(),
+            duration_ms: 10,
+            steps: Vec::new(),
+            resources: Default::default(),
+        };
+
+        let record = RunRecord::from_report(&report);
+        assert_eq!(record.run_id, "r1");
+        assert_eq!(record.error.as_deref(), Some("boom"));
+        assert!(!record.success);
+    }
+
+    #[test]
+    fn test_run_comparison_display() {
+        let a = Step::new("a", "bash", "echo a");
+        let b = Step::new("b", "bash", "echo b");
+        let c = Step::new("c", "bash", "echo c");
+
+        let base = run_record("run-1", vec![step_record(&a, 1000), step_record(&b, 1000)]);
+        let mut changed_b = step_record(&Step::new("b", "bash", "echo B"), 1500);
+        changed_b.status = StepOutcome::Failed;
+        let other = run_record("run-2", vec![step_record(&a, 500), changed_b, step_record(&c, 10)]);
+
+        let text = RunComparison::new(&base, &other).to_string();
+
+        assert!(text.contains("Base:  run-1"));
+        assert!(text.contains("-50%"));
+        assert!(text.contains("changed, completed -> failed"));
+        assert!(text.contains("added"));
+    }
+}
@@ -0,0 +1,787 @@
+//! HTML Run Reports
+//!
+//! Renders a [`RunReport`] as a single, self-contained HTML page: summary,
+//! Gantt chart of step timings, CPU and memory curves, the workflow DAG
+//! coloured by step status, and each step's command and log tails.
+//!
+//! Charts are inline SVG and the page embeds its own CSS and script, so the
+//! file can be archived or shared without any network access.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::report::{RunReport, StepOutcome, StepReport};
+
+/// Width of the chart area in pixels.
+const CHART_WIDTH: f64 = 900.0;
+
+/// Width reserved for step labels left of the Gantt bars.
+const LABEL_WIDTH: f64 = 180.0;
+
+/// Height of one Gantt row.
+const ROW_HEIGHT: f64 = 26.0;
+
+/// Height of the resource charts.
+const RESOURCE_HEIGHT: f64 = 160.0;
+
+/// Size of a DAG node and the gaps between nodes.
+const NODE_WIDTH: f64 = 150.0;
+const NODE_HEIGHT: f64 = 34.0;
+const NODE_GAP_X: f64 = 60.0;
+const NODE_GAP_Y: f64 = 18.0;
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 2em; color: #212121; }
+h1 { margin-bottom: 0.2em; }
+h2 { margin-top: 1.6em; border-bottom: 1px solid #e0e0e0; padding-bottom: 0.2em; }
+table.summary td { padding: 2px 12px 2px 0; }
+.ok { color: #2e7d32; font-weight: bold; }
+.fail { color: #c62828; font-weight: bold; }
+svg { font-size: 12px; }
+svg a rect, svg a .node { cursor: pointer; }
+.bar:hover, .node:hover { stroke: #212121; stroke-width: 2; }
+.highlight { stroke: #ff6f00 !important; stroke-width: 3 !important; }
+.axis { stroke: #9e9e9e; stroke-width: 1; }
+.grid { stroke: #eeeeee; stroke-width: 1; }
+.edge { stroke: #9e9e9e; stroke-width: 1.5; fill: none; }
+details { border: 1px solid #e0e0e0; border-radius: 4px; margin: 6px 0; padding: 4px 10px; }
+details[open] { background: #fafafa; }
+summary { cursor: pointer; }
+.badge { display: inline-block; padding: 0 6px; border-radius: 3px; color: #fff; font-size: 0.85em; }
+pre { background: #263238; color: #eceff1; padding: 8px; overflow-x: auto; border-radius: 3px; }
+pre.stderr { background: #3e2723; }
+"#;
+
+/// Opens the step details targeted by a chart link and highlights the
+/// step in every chart while hovering.
+const SCRIPT: &str = r#"
+function openStep() {
+  var el = document.getElementById(decodeURIComponent(location.hash.slice(1)));
+  if (el && el.tagName === 'DETAILS') { el.open = true; }
+}
+window.addEventListener('hashchange', openStep);
+window.addEventListener('load', openStep);
+document.addEventListener('mouseover', function (e) {
+  var target = e.target.closest('[data-step]');
+  document.querySelectorAll('.highlight').forEach(function (n) { n.classList.remove('highlight'); });
+  if (!target) { return; }
+  var step = target.getAttribute('data-step');
+  document.querySelectorAll('[data-step]').forEach(function (n) {
+    if (n.getAttribute('data-step') === step && n.tagName !== 'DETAILS') { n.classList.add('highlight'); }
+  });
+});
+"#;
+
+/// Renders a report as a standalone HTML document.
+pub fn render_html(report: &RunReport) -> String {
+    let mut html = String::new();
+
+    let _ = writeln!(html, "<!DOCTYPE html>");
+    let _ = writeln!(html, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">");
+    let _ = writeln!(
+        html,
+        "<title>RustRunner report: {}</title>",
+        escape(&report.workflow_path)
+    );
+    let _ = writeln!(html, "<style>{}</style>\n</head>\n<body>", STYLE);
+
+    render_summary(&mut html, report);
+
+    let _ = writeln!(html, "<h2>Timeline</h2>");
+    render_gantt(&mut html, report);
+
+    let _ = writeln!(html, "<h2>Resource Usage</h2>");
+    render_resources(&mut html, report);
+
+    let _ = writeln!(html, "<h2>Workflow Graph</h2>");
+    render_dag(&mut html, report);
+
+    let _ = writeln!(html, "<h2>Steps</h2>");
+    for step in &report.steps {
+        render_step_details(&mut html, step);
+    }
+
+    let _ = writeln!(html, "<script>{}</script>\n</body>\n</html>", SCRIPT);
+    html
+}
+
+/// Escapes text for use in HTML content and attribute values.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Fill colour for a step status.
+fn status_color(status: StepOutcome) -> &'static str {
+    match status {
+        StepOutcome::Completed => "#2e7d32",
+        StepOutcome::Failed => "#c62828",
+        StepOutcome::Running => "#1565c0",
+        StepOutcome::Skipped => "#9e9e9e",
+        StepOutcome::Pending => "#cfd8dc",
+    }
+}
+
+/// HTML id of a step's details section.
+fn step_anchor(step_id: &str) -> String {
+    format!("step-{}", step_id)
+}
+
+/// Formats milliseconds as a short human-readable duration.
+fn format_ms(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else if ms < 60_000 {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    } else {
+        format!("{}m {:02}s", ms / 60_000, (ms % 60_000) / 1000)
+    }
+}
+
+fn render_summary(html: &mut String, report: &RunReport) {
+    let (class, label) = if report.success {
+        ("ok", "succeeded")
+    } else {
+        ("fail", "failed")
+    };
+
+    let _ = writeln!(html, "<h1>{}</h1>", escape(&report.workflow_path));
+    let _ = writeln!(html, "<table class=\"summary\">");
+    let _ = writeln!(
+        html,
+        "<tr><td>Result</td><td class=\"{}\">Workflow {}{}</td></tr>",
+        class,
+        label,
+        if report.dry_run { " (dry run)" } else { "" }
+    );
+    if let Some(error) = &report.error {
+        let _ = writeln!(html, "<tr><td>Error</td><td>{}</td></tr>", escape(error));
+    }
+    let _ = writeln!(
+        html,
+        "<tr><td>Run</td><td>{} on {}</td></tr>",
+        escape(&report.run_id),
+        escape(&report.host)
+    );
+    let _ = writeln!(
+        html,
+        "<tr><td>Started</td><td>{}</td></tr>",
+        report.started_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    let _ = writeln!(
+        html,
+        "<tr><td>Duration</td><td>{}</td></tr>",
+        format_ms(report.duration_ms)
+    );
+
+    let count = |outcome: StepOutcome| report.steps.iter().filter(|s| s.status == outcome).count();
+    let _ = writeln!(
+        html,
+        "<tr><td>Steps</td><td>{} total, {} completed, {} failed, {} skipped, {} pending</td></tr>",
+        report.steps.len(),
+        count(StepOutcome::Completed),
+        count(StepOutcome::Failed),
+        count(StepOutcome::Skipped),
+        count(StepOutcome::Pending) + count(StepOutcome::Running)
+    );
+    let _ = writeln!(
+        html,
+        "<tr><td>Resources</td><td>avg CPU {:.1}%, peak memory {} MB</td></tr>",
+        report.resources.average_cpu, report.resources.peak_memory_mb
+    );
+    let _ = writeln!(html, "</table>");
+}
+
+/// Start offset and length of a step relative to the run start, if it ran.
+fn step_span(report: &RunReport, step: &StepReport) -> Option<(u64, u64)> {
+    let started_at = step.started_at?;
+    let offset = (started_at - report.started_at).num_milliseconds().max(0) as u64;
+
+    let length = step.duration_ms.unwrap_or_else(|| match step.finished_at {
+        Some(finished_at) => (finished_at - started_at).num_milliseconds().max(0) as u64,
+        None => report.duration_ms.saturating_sub(offset),
+    });
+
+    Some((offset, length))
+}
+
+fn render_gantt(html: &mut String, report: &RunReport) {
+    if report.steps.is_empty() {
+        let _ = writeln!(html, "<p>No steps.</p>");
+        return;
+    }
+
+    let spans: Vec<Option<(u64, u64)>> =
+        report.steps.iter().map(|s| step_span(report, s)).collect();
+    let total_ms = spans
+        .iter()
+        .flatten()
+        .map(|(offset, length)| offset + length)
+        .max()
+        .unwrap_or(0)
+        .max(report.duration_ms)
+        .max(1);
+
+    let plot_width = CHART_WIDTH - LABEL_WIDTH;
+    let scale = plot_width / total_ms as f64;
+    let height = ROW_HEIGHT * report.steps.len() as f64 + 24.0;
+
+    let _ = writeln!(
+        html,
+        "<svg class=\"gantt\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = CHART_WIDTH,
+        h = height
+    );
+
+    // Time axis with five ticks.
+    let axis_y = height - 18.0;
+    let _ = writeln!(
+        html,
+        "<line class=\"axis\" x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\"/>",
+        LABEL_WIDTH, axis_y, CHART_WIDTH, axis_y
+    );
+    for tick in 0..=4 {
+        let ms = total_ms * tick / 4;
+        let x = LABEL_WIDTH + ms as f64 * scale;
+        let _ = writeln!(
+            html,
+            "<line class=\"grid\" x1=\"{x:.1}\" y1=\"0\" x2=\"{x:.1}\" y2=\"{}\"/>\
+             <text x=\"{x:.1}\" y=\"{}\" text-anchor=\"{}\">{}</text>",
+            axis_y,
+            height - 4.0,
+            if tick == 4 { "end" } else { "middle" },
+            format_ms(ms)
+        );
+    }
+
+    for (row, (step, span)) in report.steps.iter().zip(&spans).enumerate() {
+        let y = row as f64 * ROW_HEIGHT;
+        let id = escape(&step.id);
+
+        let _ = writeln!(
+            html,
+            "<text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            LABEL_WIDTH - 8.0,
+            y + ROW_HEIGHT / 2.0 + 4.0,
+            id
+        );
+
+        let Some((offset, length)) = span else {
+            continue;
+        };
+
+        let tooltip = format!("{} ({}): {}", step.id, step.status, format_ms(*length));
+        let _ = writeln!(
+            html,
+            "<a href=\"#{anchor}\"><rect class=\"bar\" data-step=\"{id}\" x=\"{:.1}\" y=\"{:.1}\" \
+             width=\"{:.1}\" height=\"{:.1}\" rx=\"3\" fill=\"{}\"><title>{}</title></rect></a>",
+            LABEL_WIDTH + *offset as f64 * scale,
+            y + 4.0,
+            (*length as f64 * scale).max(2.0),
+            ROW_HEIGHT - 8.0,
+            status_color(step.status),
+            escape(&tooltip),
+            anchor = escape(&step_anchor(&step.id)),
+            id = id,
+        );
+    }
+
+    let _ = writeln!(html, "</svg>");
+}
+
+fn render_resources(html: &mut String, report: &RunReport) {
+    let samples = &report.resources.samples;
+    if samples.is_empty() {
+        let _ = writeln!(html, "<p>No resource data collected.</p>");
+        return;
+    }
+
+    let cpu: Vec<(u64, f64)> = samples
+        .iter()
+        .map(|s| (s.offset_ms, s.cpu_usage as f64))
+        .collect();
+    let memory: Vec<(u64, f64)> = samples
+        .iter()
+        .map(|s| (s.offset_ms, s.memory_mb as f64))
+        .collect();
+
+    render_line_chart(html, "CPU usage", "%", &cpu, "#1565c0", Some(100.0));
+    render_line_chart(html, "Memory usage", " MB", &memory, "#6a1b9a", None);
+}
+
+/// Draws a single polyline chart of `(offset_ms, value)` points.
+fn render_line_chart(
+    html: &mut String,
+    title: &str,
+    unit: &str,
+    points: &[(u64, f64)],
+    color: &str,
+    fixed_max: Option<f64>,
+) {
+    let left = 60.0;
+    let top = 20.0;
+    let plot_width = CHART_WIDTH - left - 10.0;
+    let plot_height = RESOURCE_HEIGHT - top - 24.0;
+
+    let max_ms = points.iter().map(|(ms, _)| *ms).max().unwrap_or(0).max(1);
+    let max_value = fixed_max
+        .unwrap_or_else(|| points.iter().map(|(_, v)| *v).fold(0.0, f64::max))
+        .max(1.0);
+
+    let coordinates: Vec<String> = points
+        .iter()
+        .map(|(ms, value)| {
+            format!(
+                "{:.1},{:.1}",
+                left + *ms as f64 / max_ms as f64 * plot_width,
+                top + plot_height - value / max_value * plot_height
+            )
+        })
+        .collect();
+
+    let _ = writeln!(
+        html,
+        "<svg class=\"resource\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = CHART_WIDTH,
+        h = RESOURCE_HEIGHT
+    );
+    let _ = writeln!(
+        html,
+        "<text x=\"{}\" y=\"14\" font-weight=\"bold\">{}</text>",
+        left,
+        escape(title)
+    );
+    let _ = writeln!(
+        html,
+        "<line class=\"axis\" x1=\"{l}\" y1=\"{t}\" x2=\"{l}\" y2=\"{b}\"/>\
+         <line class=\"axis\" x1=\"{l}\" y1=\"{b}\" x2=\"{r}\" y2=\"{b}\"/>",
+        l = left,
+        t = top,
+        b = top + plot_height,
+        r = left + plot_width
+    );
+    let _ = writeln!(
+        html,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{:.0}{}</text>\
+         <text x=\"{}\" y=\"{}\" text-anchor=\"end\">0{}</text>",
+        left - 6.0,
+        top + 4.0,
+        max_value,
+        escape(unit),
+        left - 6.0,
+        top + plot_height + 4.0,
+        escape(unit)
+    );
+    let _ = writeln!(
+        html,
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>",
+        left + plot_width,
+        RESOURCE_HEIGHT - 4.0,
+        format_ms(max_ms)
+    );
+    let _ = writeln!(
+        html,
+        "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"2\" points=\"{}\"/>",
+        color,
+        coordinates.join(" ")
+    );
+    let _ = writeln!(html, "</svg>");
+}
+
+/// Assigns each step a layer: the length of its longest dependency chain.
+fn dag_layers(steps: &[StepReport]) -> HashMap<&str, usize> {
+    fn depth<'a>(
+        id: &'a str,
+        steps: &HashMap<&'a str, &'a StepReport>,
+        layers: &mut HashMap<&'a str, usize>,
+        visiting: &mut Vec<&'a str>,
+    ) -> usize {
+        if let Some(&layer) = layers.get(id) {
+            return layer;
+        }
+        // Reports come from validated workflows, but guard against cycles anyway.
+        if visiting.contains(&id) {
+            return 0;
+        }
+        visiting.push(id);
+
+        let layer = steps
+            .get(id)
+            .map(|step| {
+                step.depends_on
+                    .iter()
+                    .filter(|dep| steps.contains_key(dep.as_str()))
+                    .map(|dep| depth(dep, steps, layers, visiting) + 1)
+                    .max()
+                    .unwrap_or(0)
+            })
+            .unwrap_or(0);
+
+        visiting.pop();
+        layers.insert(id, layer);
+        layer
+    }
+
+    let by_id: HashMap<&str, &StepReport> = steps.iter().map(|s| (s.id.as_str(), s)).collect();
+    let mut layers = HashMap::new();
+    for step in steps {
+        depth(&step.id, &by_id, &mut layers, &mut Vec::new());
+    }
+    layers
+}
+
+fn render_dag(html: &mut String, report: &RunReport) {
+    if report.steps.is_empty() {
+        let _ = writeln!(html, "<p>No steps.</p>");
+        return;
+    }
+
+    let layers = dag_layers(&report.steps);
+
+    // Position nodes column by column, keeping report order within a layer.
+    let mut rows_per_layer: HashMap<usize, usize> = HashMap::new();
+    let mut positions: HashMap<&str, (f64, f64)> = HashMap::new();
+    for step in &report.steps {
+        let layer = layers[step.id.as_str()];
+        let row = rows_per_layer.entry(layer).or_insert(0);
+        positions.insert(
+            &step.id,
+            (
+                10.0 + layer as f64 * (NODE_WIDTH + NODE_GAP_X),
+                10.0 + *row as f64 * (NODE_HEIGHT + NODE_GAP_Y),
+            ),
+        );
+        *row += 1;
+    }
+
+    let columns = layers.values().max().map_or(1, |max| max + 1);
+    let rows = rows_per_layer.values().copied().max().unwrap_or(1);
+    let width = 20.0 + columns as f64 * (NODE_WIDTH + NODE_GAP_X) - NODE_GAP_X;
+    let height = 20.0 + rows as f64 * (NODE_HEIGHT + NODE_GAP_Y) - NODE_GAP_Y;
+
+    let _ = writeln!(
+        html,
+        "<svg class=\"dag\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = width,
+        h = height
+    );
+    let _ = writeln!(
+        html,
+        "<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+         markerWidth=\"6\" markerHeight=\"6\" orient=\"auto\">\
+         <path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"#9e9e9e\"/></marker></defs>"
+    );
+
+    for step in &report.steps {
+        let (to_x, to_y) = positions[step.id.as_str()];
+        for dep in &step.depends_on {
+            let Some(&(from_x, from_y)) = positions.get(dep.as_str()) else {
+                continue;
+            };
+            let (x1, y1) = (from_x + NODE_WIDTH, from_y + NODE_HEIGHT / 2.0);
+            let (x2, y2) = (to_x, to_y + NODE_HEIGHT / 2.0);
+            let mid = (x1 + x2) / 2.0;
+            let _ = writeln!(
+                html,
+                "<path class=\"edge\" d=\"M {x1:.1} {y1:.1} C {mid:.1} {y1:.1}, {mid:.1} {y2:.1}, {x2:.1} {y2:.1}\" \
+                 marker-end=\"url(#arrow)\"/>"
+            );
+        }
+    }
+
+    for step in &report.steps {
+        let (x, y) = positions[step.id.as_str()];
+        let id = escape(&step.id);
+        let text_color = match step.status {
+            StepOutcome::Pending => "#212121",
+            _ => "#ffffff",
+        };
+
+        let _ = writeln!(
+            html,
+            "<a href=\"#{}\"><g><rect class=\"node\" data-step=\"{id}\" x=\"{x:.1}\" y=\"{y:.1}\" \
+             width=\"{}\" height=\"{}\" rx=\"6\" fill=\"{}\"><title>{id} ({})</title></rect>\
+             <text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\" fill=\"{}\" pointer-events=\"none\">{}</text></g></a>",
+            escape(&step_anchor(&step.id)),
+            NODE_WIDTH,
+            NODE_HEIGHT,
+            status_color(step.status),
+            step.status,
+            x + NODE_WIDTH / 2.0,
+            y + NODE_HEIGHT / 2.0 + 4.0,
+            text_color,
+            escape(&truncate(&step.id, 20)),
+            id = id,
+        );
+    }
+
+    let _ = writeln!(html, "</svg>");
+}
+
+/// Shortens a label to at most `max` characters.
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_string()
+    } else {
+        let mut short: String = text.chars().take(max - 1).collect();
+        short.push('…');
+        short
+    }
+}
+
+fn render_step_details(html: &mut String, step: &StepReport) {
+    let _ = writeln!(
+        html,
+        "<details id=\"{}\" data-step=\"{}\"{}>",
+        escape(&step_anchor(&step.id)),
+        escape(&step.id),
+        if step.status == StepOutcome::Failed {
+            " open"
+        } else {
+            ""
+        }
+    );
+    let _ = writeln!(
+        html,
+        "<summary><strong>{}</strong> <span class=\"badge\" style=\"background:{}\">{}</span> {}</summary>",
+        escape(&step.id),
+        status_color(step.status),
+        step.status,
+        step.duration_ms.map(format_ms).unwrap_or_default()
+    );
+
+    let _ = writeln!(html, "<table class=\"summary\">");
+    let _ = writeln!(
+        html,
+        "<tr><td>Tool</td><td>{}</td></tr>",
+        escape(&step.tool)
+    );
+    if !step.depends_on.is_empty() {
+        let links: Vec<String> = step
+            .depends_on
+            .iter()
+            .map(|dep| {
+                format!(
+                    "<a href=\"#{}\">{}</a>",
+                    escape(&step_anchor(dep)),
+                    escape(dep)
+                )
+            })
+            .collect();
+        let _ = writeln!(
+            html,
+            "<tr><td>Depends on</td><td>{}</td></tr>",
+            links.join(", ")
+        );
+    }
+    if let Some(started_at) = step.started_at {
+        let _ = writeln!(
+            html,
+            "<tr><td>Started</td><td>{}</td></tr>",
+            started_at.format("%H:%M:%S%.3f")
+        );
+    }
+    if let Some(finished_at) = step.finished_at {
+        let _ = writeln!(
+            html,
+            "<tr><td>Finished</td><td>{}</td></tr>",
+            finished_at.format("%H:%M:%S%.3f")
+        );
+    }
+    if let Some(code) = step.exit_code {
+        let _ = writeln!(html, "<tr><td>Exit code</td><td>{}</td></tr>", code);
+    }
+    if let Some(peak) = step.peak_memory_mb {
+        let _ = writeln!(html, "<tr><td>Peak memory</td><td>{} MB</td></tr>", peak);
+    }
+    if let Some(cpu_time) = step.cpu_time_ms {
+        let _ = writeln!(html, "<tr><td>CPU time</td><td>{}</td></tr>", format_ms(cpu_time));
+    }
+    if let (Some(read), Some(written)) = (step.read_bytes, step.written_bytes) {
+        let _ = writeln!(
+            html,
+            "<tr><td>I/O</td><td>{:.1} MB read, {:.1} MB written</td></tr>",
+            read as f64 / (1024.0 * 1024.0),
+            written as f64 / (1024.0 * 1024.0)
+        );
+    }
+    if let Some(error) = &step.error {
+        let _ = writeln!(html, "<tr><td>Error</td><td>{}</td></tr>", escape(error));
+    }
+    let _ = writeln!(html, "</table>");
+
+    let _ = writeln!(
+        html,
+        "<div>Command</div>\n<pre>{}</pre>",
+        escape(&step.command)
+    );
+    if !step.stdout_tail.is_empty() {
+        let _ = writeln!(
+            html,
+            "<div>stdout (last {} lines)</div>\n<pre>{}</pre>",
+            step.stdout_tail.len(),
+            escape(&step.stdout_tail.join("\n"))
+        );
+    }
+    if !step.stderr_tail.is_empty() {
+        let _ = writeln!(
+            html,
+            "<div>stderr (last {} lines)</div>\n<pre class=\"stderr\">{}</pre>",
+            step.stderr_tail.len(),
+            escape(&step.stderr_tail.join("\n"))
+        );
+    }
+
+    let _ = writeln!(html, "</details>");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitoring::report::{ResourcePoint, ResourceSummary};
+    use chrono::{Duration, Utc};
+
+    fn step(id: &str, depends_on: &[&str], status: StepOutcome) -> StepReport {
+        StepReport {
+            id: id.to_string(),
+            tool: "bash".to_string(),
+            command: format!("echo {} > out.txt", id),
+            fingerprint: String::new(),
+            depends_on: depends_on.iter().map(|s| s.to_string()).collect(),
+            status,
+            error: None,
+            started_at: None,
+            finished_at: None,
+            duration_ms: None,
+            exit_code: None,
+            retries: 0,
+            peak_memory_mb: None,
+            cpu_time_ms: None,
+            read_bytes: None,
+            written_bytes: None,
+            stdout_tail: Vec::new(),
+            stderr_tail: Vec::new(),
+        }
+    }
+
+    fn sample_report() -> RunReport {
+        let started_at = Utc::now();
+
+        let mut a = step("a", &[], StepOutcome::Completed);
+        a.started_at = Some(started_at);
+        a.finished_at = Some(started_at + Duration::milliseconds(400));
+        a.duration_ms = Some(400);
+        a.exit_code = Some(0);
+        a.stdout_tail = vec!["hello".to_string()];
+
+        let mut b = step("b", &["a"], StepOutcome::Failed);
+        b.started_at = Some(started_at + Duration::milliseconds(400));
+        b.duration_ms = Some(100);
+        b.exit_code = Some(1);
+        b.error = Some("Step 'b' failed".to_string());
+        b.stderr_tail = vec!["<boom>".to_string()];
+
+        let c = step("c", &["a", "b"], StepOutcome::Pending);
+
+        RunReport {
+            run_id: RunReport::new_run_id(&started_at),
+            workflow_path: "pipeline.yaml".to_string(),
+            host: "localhost".to_string(),
+            success: false,
+            error: Some("Workflow failed at step 'b'".to_string()),
+            dry_run: false,
+            started_at,
+            finished_at: started_at + Duration::milliseconds(500),
+            duration_ms: 500,
+            steps: vec![a, b, c],
+            resources: ResourceSummary {
+                average_cpu: 12.5,
+                peak_memory_mb: 2048,
+                min_memory_mb: 1024,
+                samples: vec![
+                    ResourcePoint {
+                        offset_ms: 0,
+                        cpu_usage: 10.0,
+                        memory_mb: 1024,
+                    },
+                    ResourcePoint {
+                        offset_ms: 500,
+                        cpu_usage: 15.0,
+                        memory_mb: 2048,
+                    },
+                ],
+            },
+        }
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("<a href=\"x\">&'"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&#39;"
+        );
+    }
+
+    #[test]
+    fn test_dag_layers() {
+        let report = sample_report();
+        let layers = dag_layers(&report.steps);
+
+        assert_eq!(layers["a"], 0);
+        assert_eq!(layers["b"], 1);
+        assert_eq!(layers["c"], 2);
+    }
+
+    #[test]
+    fn test_render_html_is_self_contained() {
+        let html = render_html(&sample_report());
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.trim_end().ends_with("</html>"));
+        assert!(!html.contains("http://") && !html.contains("https://"));
+        assert!(!html.contains("<script src"));
+        assert!(!html.contains("<link"));
+    }
+
+    #[test]
+    fn test_render_html_contents() {
+        let html = render_html(&sample_report());
+
+        // Gantt bars, resource curves and DAG edges
+        assert!(html.contains("class=\"bar\" data-step=\"a\""));
+        assert_eq!(html.matches("<polyline").count(), 2);
+        assert_eq!(html.matches("class=\"edge\"").count(), 3);
+
+        // Status colours, commands and escaped log tails
+        assert!(html.contains(status_color(StepOutcome::Failed)));
+        assert!(html.contains("echo a &gt; out.txt"));
+        assert!(html.contains("hello"));
+        assert!(html.contains("&lt;boom&gt;"));
+        assert!(html.contains("id=\"step-b\" data-step=\"b\" open"));
+    }
+
+    #[test]
+    fn test_render_html_without_samples() {
+        let mut report = sample_report();
+        report.resources.samples.clear();
+
+        assert!(render_html(&report).contains("No resource data collected"));
+    }
+
+    #[test]
+    fn test_format_ms() {
+        assert_eq!(format_ms(250), "250ms");
+        assert_eq!(format_ms(1500), "1.5s");
+        assert_eq!(format_ms(125_000), "2m 05s");
+    }
+}
@@ -6,10 +6,20 @@
 //! # Components
 //!
 //! - [`ResourceMonitor`]: CPU and memory usage tracking
-//! - [`ExecutionTimeline`]: Step start/end timing for Gantt charts
+//! - [`ProcessTreeMonitor`]: Per-step CPU time, peak memory and I/O
+//! - [`ExecutionTimeline`]: Step start/end timing for Gantt charts and trace export
+//! - [`RunReport`]: Structured, serializable summary of a finished run
+//! - [`render_html`]: Self-contained HTML page for a [`RunReport`]
+//! - [`RunHistory`]: Persistent record of past runs for comparisons and estimates
 
+pub mod history;
+pub mod html;
+pub mod report;
 pub mod resource;
 pub mod timeline;
 
-pub use resource::{ResourceMonitor, ResourceSample};
-pub use timeline::{EventType, ExecutionTimeline, TimelineEvent};
+pub use history::{RunComparison, RunHistory, RunRecord, StepRecord};
+pub use html::render_html;
+pub use report::{ResourcePoint, ResourceSummary, RunReport, StepOutcome, StepReport};
+pub use resource::{ProcessTreeMonitor, ProcessUsage, ResourceMonitor, ResourceSample};
+pub use timeline::{EventType, ExecutionTimeline, TimelineEvent, TimelineSample};
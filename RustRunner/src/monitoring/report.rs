@@ -0,0 +1,553 @@
+//! Run Reports
+//!
+//! Structured summary of a workflow run, returned by
+//! [`Engine::run`](crate::execution::Engine::run) and serializable to
+//! JSON, YAML or a standalone HTML page for archiving alongside results.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sysinfo::System;
+
+use crate::workflow::planner::{StepMetrics, StepStatus};
+use crate::workflow::{ExecutionPlanner, ShellConfig, Step};
+
+use super::html::render_html;
+use super::resource::ResourceMonitor;
+
+/// Final status of a step in a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepOutcome {
+    /// Never started (an earlier step failed or the run was cancelled)
+    Pending,
+    /// Still running when the report was taken
+    Running,
+    /// Finished successfully
+    Completed,
+    /// Finished with an error
+    Failed,
+    /// Not run (completed in a previous run)
+    Skipped,
+}
+
+impl From<&StepStatus> for StepOutcome {
+    fn from(status: &StepStatus) -> Self {
+        match status {
+            StepStatus::Pending => Self::Pending,
+            StepStatus::Running => Self::Running,
+            StepStatus::Completed => Self::Completed,
+            StepStatus::Failed(_) => Self::Failed,
+            StepStatus::Skipped => Self::Skipped,
+        }
+    }
+}
+
+impl fmt::Display for StepOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Per-step entry of a [`RunReport`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepReport {
+    /// Step ID
+    pub id: String,
+    /// Tool the step ran with
+    pub tool: String,
+    /// Command template of the step (or the code of its inline script)
+    pub command: String,
+    /// Fingerprint of the step's work (see [`Step::fingerprint`])
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub fingerprint: String,
+    /// IDs of steps this step depends on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Final status
+    pub status: StepOutcome,
+    /// Failure message, if the step failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Wall-clock start time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    /// Wall-clock end time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Run time in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Exit code of the step's process
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// Number of times the step was retried; always 0 until the engine
+    /// retries failed steps
+    #[serde(default)]
+    pub retries: u32,
+    /// Peak resident memory of the step's process tree in megabytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_memory_mb: Option<u64>,
+    /// CPU time used by the step's process tree in milliseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_time_ms: Option<u64>,
+    /// Bytes the step read from storage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_bytes: Option<u64>,
+    /// Bytes the step wrote to storage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub written_bytes: Option<u64>,
+    /// Last lines the step wrote to stdout
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stdout_tail: Vec<String>,
+    /// Last lines the step wrote to stderr
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stderr_tail: Vec<String>,
+}
+
+impl StepReport {
+    /// Builds a step entry from its definition, the workflow's shell
+    /// settings and collected metrics.
+    pub fn new(step: &Step, workflow_shell: &ShellConfig, metrics: Option<&StepMetrics>) -> Self {
+        let status = metrics
+            .map(|m| StepOutcome::from(&m.status))
+            .unwrap_or(StepOutcome::Pending);
+
+        let error = match metrics.map(|m| &m.status) {
+            Some(StepStatus::Failed(error)) => Some(error.to_string()),
+            _ => None,
+        };
+
+        let resources = metrics.and_then(|m| m.resources);
+
+        Self {
+            id: step.id.clone(),
+            tool: step.tool.clone(),
+            command: match &step.script {
+                Some(script) => script.code.clone(),
+                None => step.command.clone(),
+            },
+            fingerprint: step.fingerprint(workflow_shell),
+            depends_on: step.previous.clone(),
+            status,
+            error,
+            started_at: metrics.and_then(|m| m.started_at),
+            finished_at: metrics.and_then(|m| m.finished_at),
+            duration_ms: metrics.and_then(|m| m.duration_ms).map(|ms| ms as u64),
+            exit_code: metrics.and_then(|m| m.exit_code),
+            retries: 0,
+            peak_memory_mb: resources.map(|r| r.peak_memory_mb),
+            cpu_time_ms: resources.map(|r| r.cpu_time_ms),
+            read_bytes: resources.map(|r| r.read_bytes),
+            written_bytes: resources.map(|r| r.written_bytes),
+            stdout_tail: metrics.map(|m| m.stdout_tail.clone()).unwrap_or_default(),
+            stderr_tail: metrics.map(|m| m.stderr_tail.clone()).unwrap_or_default(),
+        }
+    }
+}
+
+/// A resource usage sample, relative to the start of the run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourcePoint {
+    /// Milliseconds since the run started
+    pub offset_ms: u64,
+    /// CPU usage percentage
+    pub cpu_usage: f32,
+    /// Memory usage in megabytes
+    pub memory_mb: u64,
+}
+
+/// Workflow-level resource usage.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceSummary {
+    /// Average CPU usage percentage
+    pub average_cpu: f32,
+    /// Peak memory usage in megabytes
+    pub peak_memory_mb: u64,
+    /// Lowest memory usage in megabytes
+    pub min_memory_mb: u64,
+    /// All samples taken during the run
+    #[serde(default)]
+    pub samples: Vec<ResourcePoint>,
+}
+
+impl ResourceSummary {
+    /// Summarizes a monitor's samples relative to the run start.
+    pub fn from_monitor(monitor: &ResourceMonitor, run_start: Instant) -> Self {
+        let samples: Vec<ResourcePoint> = monitor
+            .get_samples()
+            .iter()
+            .map(|s| ResourcePoint {
+                offset_ms: s.timestamp.saturating_duration_since(run_start).as_millis() as u64,
+                cpu_usage: s.cpu_usage,
+                memory_mb: s.memory_mb,
+            })
+            .collect();
+
+        Self {
+            average_cpu: monitor.average_cpu(),
+            peak_memory_mb: monitor.peak_memory_mb(),
+            min_memory_mb: samples.iter().map(|s| s.memory_mb).min().unwrap_or(0),
+            samples,
+        }
+    }
+}
+
+/// Structured summary of a workflow run.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use rustrunner::execution::Engine;
+/// use rustrunner::load_workflow;
+///
+/// fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let mut engine = Engine::new(load_workflow("pipeline.yaml")?);
+///     let report = engine.run()?;
+///
+///     report.save("report.json")?;
+///     println!("{}", report);
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunReport {
+    /// Unique ID of the run, starting with its start time
+    #[serde(default)]
+    pub run_id: String,
+    /// Path of the workflow file
+    pub workflow_path: String,
+    /// Name of the machine the run executed on
+    #[serde(default)]
+    pub host: String,
+    /// Whether every step completed
+    pub success: bool,
+    /// Error that ended the run, if it failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Whether this was a dry run
+    pub dry_run: bool,
+    /// Wall-clock start time
+    pub started_at: DateTime<Utc>,
+    /// Wall-clock end time
+    pub finished_at: DateTime<Utc>,
+    /// Total run time in milliseconds
+    pub duration_ms: u64,
+    /// Per-step results in execution order
+    pub steps: Vec<StepReport>,
+    /// Workflow-level resource usage
+    pub resources: ResourceSummary,
+}
+
+impl RunReport {
+    /// Builds a report from a planner's metrics and the run's resource samples.
+    ///
+    /// `run_id` comes from [`RunReport::new_run_id`]; `planner` is `None` if
+    /// the run failed before planning started.
+    #[allow(clippy::too_many_arguments)]
+    pub fn build(
+        run_id: String,
+        workflow_path: &str,
+        dry_run: bool,
+        started_at: DateTime<Utc>,
+        run_start: Instant,
+        planner: Option<&ExecutionPlanner>,
+        monitor: &ResourceMonitor,
+        error: Option<String>,
+    ) -> Self {
+        let steps = planner
+            .map(|planner| {
+                planner
+                    .workflow()
+                    .steps
+                    .iter()
+                    .map(|step| {
+                        let metrics = planner.get_metrics().get(&step.id);
+                        StepReport::new(step, &planner.workflow().shell, metrics)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self {
+            run_id,
+            workflow_path: workflow_path.to_string(),
+            host: System::host_name().unwrap_or_else(|| "unknown".to_string()),
+            success: error.is_none(),
+            error,
+            dry_run,
+            started_at,
+            finished_at: Utc::now(),
+            duration_ms: run_start.elapsed().as_millis() as u64,
+            steps,
+            resources: ResourceSummary::from_monitor(monitor, run_start),
+        }
+    }
+
+    /// Returns a new run ID for a run started at the given time.
+    ///
+    /// IDs sort chronologically, e.g. `20240131-142501-123-4242-0`. The
+    /// process ID and a per-process counter after the timestamp keep runs
+    /// started in the same millisecond apart.
+    pub fn new_run_id(started_at: &DateTime<Utc>) -> String {
+        static NEXT_RUN: AtomicU32 = AtomicU32::new(0);
+
+        format!(
+            "{}-{}-{}",
+            started_at.format("%Y%m%d-%H%M%S-%3f"),
+            std::process::id(),
+            NEXT_RUN.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+
+    /// Returns the report entry for a step.
+    pub fn step(&self, step_id: &str) -> Option<&StepReport> {
+        self.steps.iter().find(|s| s.id == step_id)
+    }
+
+    /// Serializes the report as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Serializes the report as YAML.
+    pub fn to_yaml(&self) -> Result<String, Box<dyn Error>> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    /// Renders the report as a self-contained HTML page.
+    pub fn to_html(&self) -> String {
+        render_html(self)
+    }
+
+    /// Writes the report to a file.
+    ///
+    /// The format is chosen by extension: `.yaml`/`.yml` for YAML,
+    /// `.html`/`.htm` for a standalone HTML page, anything else for JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+
+        let content = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml") | Some("yml") => self.to_yaml()?,
+            Some("html") | Some("htm") => self.to_html(),
+            _ => self.to_json()?,
+        };
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for RunReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.success {
+            writeln!(f, "Workflow completed successfully")?;
+        } else {
+            writeln!(f, "Workflow failed")?;
+        }
+        writeln!(
+            f,
+            "Total execution time: {:.2}s",
+            self.duration_ms as f64 / 1000.0
+        )?;
+
+        if !self.steps.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Steps:")?;
+            writeln!(
+                f,
+                "  {:<24} {:<10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+                "", "status", "time", "cpu", "peak mem", "read", "written"
+            )?;
+            for step in &self.steps {
+                let seconds = |ms: Option<u64>| {
+                    ms.map(|ms| format!("{:.2}s", ms as f64 / 1000.0))
+                        .unwrap_or_else(|| "-".to_string())
+                };
+                let megabytes = |mb: Option<u64>| {
+                    mb.map(|mb| format!("{} MB", mb))
+                        .unwrap_or_else(|| "-".to_string())
+                };
+                let bytes = |b: Option<u64>| megabytes(b.map(|b| b / (1024 * 1024)));
+
+                writeln!(
+                    f,
+                    "  {:<24} {:<10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+                    step.id,
+                    step.status,
+                    seconds(step.duration_ms),
+                    seconds(step.cpu_time_ms),
+                    megabytes(step.peak_memory_mb),
+                    bytes(step.read_bytes),
+                    bytes(step.written_bytes)
+                )?;
+            }
+        }
+
+        writeln!(f)?;
+        if self.resources.samples.is_empty() {
+            write!(f, "No resource data collected")
+        } else {
+            write!(
+                f,
+                "Resource Usage:\n  Average CPU: {:.1}%\n  Peak Memory: {} MB\n  Min Memory: {} MB\n  Samples: {}",
+                self.resources.average_cpu,
+                self.resources.peak_memory_mb,
+                self.resources.min_memory_mb,
+                self.resources.samples.len()
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitoring::ProcessUsage;
+    use crate::workflow::Workflow;
+    use tempfile::tempdir;
+
+    fn sample_report() -> RunReport {
+        let mut workflow = Workflow::new();
+        workflow.add_step(Step::new("a", "bash", "echo a")).unwrap();
+        workflow.add_step(Step::new("b", "bash", "echo b")).unwrap();
+
+        let mut planner = ExecutionPlanner::new(workflow, false, 4, None).unwrap();
+        planner.mark_step_running("a");
+        planner.mark_step_completed("a");
+        planner.set_exit_code("a", Some(0));
+        planner.set_resources(
+            "a",
+            ProcessUsage {
+                peak_memory_mb: 42,
+                cpu_time_ms: 1200,
+                read_bytes: 0,
+                written_bytes: 4096,
+            },
+        );
+
+        RunReport::build(
+            "run-1".to_string(),
+            "test.yaml",
+            false,
+            Utc::now(),
+            Instant::now(),
+            Some(&planner),
+            &ResourceMonitor::new(),
+            Some("b never ran".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_report_build() {
+        let report = sample_report();
+
+        assert!(!report.success);
+        assert_eq!(report.steps.len(), 2);
+        assert_eq!(report.run_id, "run-1");
+        assert!(!report.host.is_empty());
+
+        let a = report.step("a").unwrap();
+        assert_eq!(a.status, StepOutcome::Completed);
+        assert_eq!(a.exit_code, Some(0));
+        assert!(a.started_at.is_some());
+        assert!(a.duration_ms.is_some());
+        assert_eq!(a.peak_memory_mb, Some(42));
+        assert_eq!(a.cpu_time_ms, Some(1200));
+        let shell = ShellConfig::default();
+        assert_eq!(a.fingerprint, Step::new("a", "bash", "echo a").fingerprint(&shell));
+
+        assert_eq!(report.step("b").unwrap().status, StepOutcome::Pending);
+    }
+
+    #[test]
+    fn test_report_without_planner() {
+        let report = RunReport::build(
+            "run-2".to_string(),
+            "test.yaml",
+            true,
+            Utc::now(),
+            Instant::now(),
+            None,
+            &ResourceMonitor::new(),
+            None,
+        );
+
+        assert!(report.success);
+        assert!(report.steps.is_empty());
+    }
+
+    #[test]
+    fn test_new_run_id_unique_within_millisecond() {
+        let started_at = Utc::now();
+        let first = RunReport::new_run_id(&started_at);
+        let second = RunReport::new_run_id(&started_at);
+
+        assert_ne!(first, second);
+        assert!(first.starts_with(&started_at.format("%Y%m%d-%H%M%S-%3f").to_string()));
+    }
+
+    #[test]
+    fn test_report_json_roundtrip() {
+        let report = sample_report();
+        let json = report.to_json().unwrap();
+
+        assert!(json.contains("\"status\": \"completed\""));
+        let loaded: RunReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded, report);
+    }
+
+    #[test]
+    fn test_report_yaml_roundtrip() {
+        let report = sample_report();
+        let yaml = report.to_yaml().unwrap();
+
+        let loaded: RunReport = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(loaded.steps.len(), 2);
+        assert_eq!(loaded.workflow_path, "test.yaml");
+    }
+
+    #[test]
+    fn test_report_save_by_extension() {
+        let temp_dir = tempdir().unwrap();
+        let report = sample_report();
+
+        let json_path = temp_dir.path().join("report.json");
+        report.save(&json_path).unwrap();
+        assert!(fs::read_to_string(&json_path).unwrap().starts_with('{'));
+
+        let yaml_path = temp_dir.path().join("nested/report.yaml");
+        report.save(&yaml_path).unwrap();
+        assert!(fs::read_to_string(&yaml_path).unwrap().contains("workflow_path: test.yaml"));
+
+        let html_path = temp_dir.path().join("report.html");
+        report.save(&html_path).unwrap();
+        assert!(fs::read_to_string(&html_path).unwrap().starts_with("<!DOCTYPE html>"));
+    }
+
+    #[test]
+    fn test_report_display() {
+        let text = sample_report().to_string();
+
+        assert!(text.contains("Workflow failed"));
+        assert!(text.contains("completed"));
+        assert!(text.contains("42 MB"));
+        assert!(text.contains("No resource data collected"));
+    }
+}
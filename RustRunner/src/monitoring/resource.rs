@@ -2,11 +2,69 @@
 //!
 //! Tracks CPU and memory usage during workflow execution
 //! for performance analysis and reporting.
+//!
+//! Usage is always measured over a whole process tree, so the tools a step
+//! launches (e.g. bash → micromamba → tool) are included.
+//!
+//! All monitors read one shared process table. It is rescanned at most once
+//! per [`PROCESS_TABLE_MAX_AGE`], so sampling many running steps in the same
+//! tick costs a single scan of the system's processes.
 
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
 use std::time::{Duration, Instant};
 
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use sysinfo::{get_current_pid, Pid, ProcessRefreshKind, System};
 
+/// How long a scan of the process table is reused before it is refreshed.
+///
+/// Half the engine's and the steps' sampling interval, so samples taken in
+/// the same tick share a scan.
+pub const PROCESS_TABLE_MAX_AGE: Duration = Duration::from_millis(250);
+
+/// Process table shared by every monitor.
+static PROCESS_TABLE: Lazy<Mutex<ProcessTable>> = Lazy::new(|| {
+    Mutex::new(ProcessTable {
+        system: System::new(),
+        refreshed: None,
+    })
+});
+
+/// The system's processes as of the last scan.
+struct ProcessTable {
+    system: System,
+    refreshed: Option<Instant>,
+}
+
+impl ProcessTable {
+    /// Rescans the processes if the last scan is older than
+    /// [`PROCESS_TABLE_MAX_AGE`]. Returns `true` if it rescanned.
+    fn refresh_if_stale(&mut self, now: Instant) -> bool {
+        let stale = self
+            .refreshed
+            .map_or(true, |refreshed| now.duration_since(refreshed) >= PROCESS_TABLE_MAX_AGE);
+
+        if stale {
+            let refresh_kind = ProcessRefreshKind::new()
+                .with_cpu()
+                .with_memory()
+                .with_disk_usage();
+            self.system.refresh_processes_specifics(refresh_kind);
+            self.refreshed = Some(now);
+        }
+        stale
+    }
+}
+
+/// Runs `f` on the shared process table, rescanning it first if stale.
+fn with_process_table<R>(f: impl FnOnce(&System) -> R) -> R {
+    let mut table = PROCESS_TABLE.lock().unwrap_or_else(|e| e.into_inner());
+    table.refresh_if_stale(Instant::now());
+    f(&table.system)
+}
+
 /// A single resource usage sample.
 #[derive(Debug, Clone)]
 pub struct ResourceSample {
@@ -18,7 +76,7 @@ pub struct ResourceSample {
     pub memory_mb: u64,
 }
 
-/// Monitors system resource usage for the current process.
+/// Monitors system resource usage for the current process and its children.
 ///
 /// # Example
 ///
@@ -38,7 +96,6 @@ pub struct ResourceSample {
 /// println!("{}", monitor.get_summary());
 /// ```
 pub struct ResourceMonitor {
-    system: System,
     process_id: Pid,
     samples: Vec<ResourceSample>,
     warmup_done: bool,
@@ -50,7 +107,6 @@ impl ResourceMonitor {
     /// Creates a new resource monitor for the current process.
     pub fn new() -> Self {
         Self {
-            system: System::new(),
             process_id: get_current_pid().expect("Failed to get process ID"),
             samples: Vec::new(),
             warmup_done: false,
@@ -73,14 +129,9 @@ impl ResourceMonitor {
         let pid = self.process_id;
         let now = Instant::now();
 
-        // Create refresh kind for CPU and memory
-        let refresh_kind = ProcessRefreshKind::new()
-            .with_cpu()
-            .with_memory();
-
         // First call: warmup
         if !self.warmup_done {
-            self.system.refresh_processes_specifics(refresh_kind);
+            with_process_table(|_| ());
             self.warmup_done = true;
             self.last_sample = Some(now);
             return;
@@ -93,21 +144,32 @@ impl ResourceMonitor {
             }
         }
 
-        // Refresh process info
-        self.system.refresh_processes_specifics(refresh_kind);
         self.last_sample = Some(now);
 
-        // Record sample
-        if let Some(process) = self.system.process(pid) {
-            let mem_mb = process.memory() / (1024 * 1024);
-            let cpu = process.cpu_usage();
+        // Record sample over rustrunner and every step it launched
+        let totals = with_process_table(|system| {
+            let tree = process_tree(system, pid);
+            if tree.is_empty() {
+                return None;
+            }
 
-            self.samples.push(ResourceSample {
-                timestamp: now,
-                cpu_usage: cpu,
-                memory_mb: mem_mb,
-            });
-        }
+            Some(
+                tree.iter()
+                    .filter_map(|pid| system.process(*pid))
+                    .fold((0, 0.0), |(memory, cpu), process| {
+                        (memory + process.memory(), cpu + process.cpu_usage())
+                    }),
+            )
+        });
+        let Some((memory, cpu)) = totals else {
+            return;
+        };
+
+        self.samples.push(ResourceSample {
+            timestamp: now,
+            cpu_usage: cpu,
+            memory_mb: memory / (1024 * 1024),
+        });
     }
 
     /// Returns a human-readable summary of resource usage.
@@ -154,6 +216,136 @@ impl Default for ResourceMonitor {
     }
 }
 
+/// Resource usage accumulated by a process tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessUsage {
+    /// Highest combined resident memory of the tree, in megabytes
+    pub peak_memory_mb: u64,
+    /// CPU time consumed by the tree, in milliseconds
+    pub cpu_time_ms: u64,
+    /// Bytes read from storage
+    pub read_bytes: u64,
+    /// Bytes written to storage
+    pub written_bytes: u64,
+}
+
+impl ProcessUsage {
+    /// Returns `true` if nothing was recorded.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Tracks the resource usage of one process and all of its descendants.
+///
+/// Used per step, so the figures cover the step's shell, `micromamba run`
+/// and the tool itself. Values are sampled: CPU time is integrated from
+/// per-process CPU usage between samples, and processes that start and
+/// exit between two samples are not seen.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// let mut tree = ProcessTreeMonitor::new(child.id().unwrap());
+/// tree.sample();
+/// // ... later
+/// tree.sample();
+/// println!("peak RSS: {} MB", tree.usage().peak_memory_mb);
+/// ```
+pub struct ProcessTreeMonitor {
+    root: Pid,
+    usage: ProcessUsage,
+    /// Cumulative (read, written) bytes last seen per process, kept after
+    /// the process exits so its I/O still counts
+    io_by_pid: HashMap<Pid, (u64, u64)>,
+    last_sample: Option<Instant>,
+}
+
+impl ProcessTreeMonitor {
+    /// Creates a monitor for the tree rooted at `root_pid`.
+    pub fn new(root_pid: u32) -> Self {
+        Self {
+            root: Pid::from_u32(root_pid),
+            usage: ProcessUsage::default(),
+            io_by_pid: HashMap::new(),
+            last_sample: None,
+        }
+    }
+
+    /// Reads the shared process table and updates the accumulated usage.
+    pub fn sample(&mut self) {
+        let now = Instant::now();
+        let elapsed = self
+            .last_sample
+            .map(|last| now.duration_since(last))
+            .unwrap_or_default();
+        self.last_sample = Some(now);
+
+        let mut memory = 0;
+        let mut cpu_percent = 0.0;
+        with_process_table(|system| {
+            for pid in process_tree(system, self.root) {
+                let Some(process) = system.process(pid) else {
+                    continue;
+                };
+
+                memory += process.memory();
+                cpu_percent += process.cpu_usage() as f64;
+
+                let disk = process.disk_usage();
+                let io = self.io_by_pid.entry(pid).or_default();
+                io.0 = io.0.max(disk.total_read_bytes);
+                io.1 = io.1.max(disk.total_written_bytes);
+            }
+        });
+
+        self.usage.peak_memory_mb = self.usage.peak_memory_mb.max(memory / (1024 * 1024));
+        self.usage.cpu_time_ms += (cpu_percent / 100.0 * elapsed.as_millis() as f64) as u64;
+        self.usage.read_bytes = self.io_by_pid.values().map(|io| io.0).sum();
+        self.usage.written_bytes = self.io_by_pid.values().map(|io| io.1).sum();
+    }
+
+    /// Returns the usage accumulated so far.
+    pub fn usage(&self) -> ProcessUsage {
+        self.usage
+    }
+}
+
+/// Returns `root` and all of its live descendants.
+///
+/// Threads are skipped; on Linux they are listed alongside processes and
+/// would otherwise count their parent's memory again.
+fn process_tree(system: &System, root: Pid) -> Vec<Pid> {
+    if system.process(root).is_none() {
+        return Vec::new();
+    }
+
+    let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+    for (pid, process) in system.processes() {
+        if process.thread_kind().is_some() {
+            continue;
+        }
+        if let Some(parent) = process.parent() {
+            children.entry(parent).or_default().push(*pid);
+        }
+    }
+
+    let mut tree = vec![root];
+    let mut seen: HashSet<Pid> = HashSet::from([root]);
+    let mut index = 0;
+    while index < tree.len() {
+        if let Some(kids) = children.get(&tree[index]) {
+            for kid in kids {
+                if seen.insert(*kid) {
+                    tree.push(*kid);
+                }
+            }
+        }
+        index += 1;
+    }
+    tree
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -210,7 +402,7 @@ mod tests {
         // peak_memory_mb returns a value (u64, always >= 0)
         let _peak = monitor.peak_memory_mb();
         // Just verify it doesn't panic
-        assert!(monitor.get_samples().len() >= 1);
+        assert!(!monitor.get_samples().is_empty());
     }
 
     #[test]
@@ -281,4 +473,58 @@ mod tests {
         let monitor = ResourceMonitor::new();
         assert_eq!(monitor.peak_memory_mb(), 0);
     }
+
+    #[test]
+    fn test_process_tree_includes_children() {
+        let mut child = std::process::Command::new("sleep").arg("5").spawn().unwrap();
+
+        let mut system = System::new();
+        system.refresh_processes_specifics(ProcessRefreshKind::new());
+        let tree = process_tree(&system, get_current_pid().unwrap());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert_eq!(tree[0], get_current_pid().unwrap());
+        assert!(tree.contains(&Pid::from_u32(child.id())));
+    }
+
+    #[test]
+    fn test_process_tree_monitor_tracks_child() {
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "head -c 20000000 /dev/zero | tail -c 1 > /dev/null; sleep 5"])
+            .spawn()
+            .unwrap();
+
+        let mut tree = ProcessTreeMonitor::new(child.id());
+        tree.sample();
+        thread::sleep(Duration::from_millis(300));
+        tree.sample();
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert!(!tree.usage().is_empty());
+    }
+
+    #[test]
+    fn test_process_table_scanned_once_per_tick() {
+        let mut table = ProcessTable {
+            system: System::new(),
+            refreshed: None,
+        };
+        let start = Instant::now();
+
+        assert!(table.refresh_if_stale(start));
+        assert!(!table.refresh_if_stale(start + Duration::from_millis(100)));
+        assert!(table.refresh_if_stale(start + PROCESS_TABLE_MAX_AGE));
+    }
+
+    #[test]
+    fn test_process_tree_monitor_missing_root() {
+        let mut tree = ProcessTreeMonitor::new(u32::MAX - 1);
+        tree.sample();
+
+        assert!(tree.usage().is_empty());
+    }
 }
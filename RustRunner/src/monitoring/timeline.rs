@@ -2,10 +2,22 @@
 //!
 //! Tracks step start/end times for generating execution
 //! reports and Gantt charts.
+//!
+//! Events carry both a monotonic [`Instant`] (for durations) and a
+//! wall-clock timestamp (for export and cross-run comparison). A timeline
+//! can be exported in the Chrome Trace Event format with
+//! [`ExecutionTimeline::to_chrome_trace`] and opened in `chrome://tracing`
+//! or [Perfetto](https://ui.perfetto.dev).
 
 use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
 use std::time::Instant;
 
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
 /// Type of timeline event.
 #[derive(Debug, Clone, PartialEq)]
 pub enum EventType {
@@ -26,6 +38,21 @@ pub struct TimelineEvent {
     pub event_type: EventType,
     /// When the event occurred
     pub timestamp: Instant,
+    /// Wall-clock time of the event
+    pub wall_time: DateTime<Utc>,
+}
+
+/// A resource usage sample recorded on the timeline.
+#[derive(Debug, Clone)]
+pub struct TimelineSample {
+    /// When the sample was taken
+    pub timestamp: Instant,
+    /// Wall-clock time of the sample
+    pub wall_time: DateTime<Utc>,
+    /// CPU usage percentage (0-100+)
+    pub cpu_usage: f32,
+    /// Memory usage in megabytes
+    pub memory_mb: u64,
 }
 
 /// Tracks the execution timeline of a workflow.
@@ -35,7 +62,9 @@ pub struct TimelineEvent {
 #[derive(Debug, Clone)]
 pub struct ExecutionTimeline {
     events: Vec<TimelineEvent>,
+    samples: Vec<TimelineSample>,
     start_time: Instant,
+    started_at: DateTime<Utc>,
 }
 
 impl ExecutionTimeline {
@@ -43,7 +72,9 @@ impl ExecutionTimeline {
     pub fn new() -> Self {
         Self {
             events: Vec::new(),
+            samples: Vec::new(),
             start_time: Instant::now(),
+            started_at: Utc::now(),
         }
     }
 
@@ -53,6 +84,17 @@ impl ExecutionTimeline {
             step_id,
             event_type,
             timestamp: Instant::now(),
+            wall_time: Utc::now(),
+        });
+    }
+
+    /// Records a resource usage sample.
+    pub fn add_sample(&mut self, cpu_usage: f32, memory_mb: u64) {
+        self.samples.push(TimelineSample {
+            timestamp: Instant::now(),
+            wall_time: Utc::now(),
+            cpu_usage,
+            memory_mb,
         });
     }
 
@@ -61,6 +103,16 @@ impl ExecutionTimeline {
         &self.events
     }
 
+    /// Returns all recorded resource samples.
+    pub fn get_samples(&self) -> &[TimelineSample] {
+        &self.samples
+    }
+
+    /// Returns the wall-clock time the timeline started.
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+
     /// Returns the total elapsed time since timeline creation.
     pub fn elapsed(&self) -> std::time::Duration {
         self.start_time.elapsed()
@@ -152,6 +204,150 @@ impl ExecutionTimeline {
 
         durations
     }
+
+    /// Assigns each step to a worker lane.
+    ///
+    /// A starting step takes the lowest lane not occupied by a running
+    /// step, so the number of lanes equals the peak parallelism and each
+    /// lane corresponds to one worker slot.
+    pub fn lanes(&self) -> HashMap<String, usize> {
+        let mut lanes: HashMap<String, usize> = HashMap::new();
+        let mut busy: Vec<bool> = Vec::new();
+
+        for event in &self.events {
+            match event.event_type {
+                EventType::Started => {
+                    let lane = busy.iter().position(|b| !b).unwrap_or(busy.len());
+                    if lane == busy.len() {
+                        busy.push(true);
+                    } else {
+                        busy[lane] = true;
+                    }
+                    lanes.insert(event.step_id.clone(), lane);
+                }
+                EventType::Completed | EventType::Failed => {
+                    if let Some(&lane) = lanes.get(&event.step_id) {
+                        busy[lane] = false;
+                    }
+                }
+            }
+        }
+
+        lanes
+    }
+
+    /// Exports the timeline in the Chrome Trace Event JSON format.
+    ///
+    /// Each step becomes a complete (`"X"`) slice on the thread matching its
+    /// worker lane, and resource samples become `CPU` and `Memory` counter
+    /// tracks. Steps still running are closed at the last recorded time.
+    pub fn to_chrome_trace(&self) -> String {
+        let offset_us = |at: Instant| at.saturating_duration_since(self.start_time).as_micros() as u64;
+
+        let end_us = self
+            .events
+            .iter()
+            .map(|e| offset_us(e.timestamp))
+            .chain(self.samples.iter().map(|s| offset_us(s.timestamp)))
+            .max()
+            .unwrap_or(0);
+
+        let lanes = self.lanes();
+        let lane_count = lanes.values().max().map_or(0, |max| max + 1);
+
+        let mut trace_events = vec![json!({
+            "name": "process_name",
+            "ph": "M",
+            "pid": 1,
+            "args": { "name": "rustrunner" },
+        })];
+
+        for lane in 0..lane_count {
+            trace_events.push(json!({
+                "name": "thread_name",
+                "ph": "M",
+                "pid": 1,
+                "tid": lane + 1,
+                "args": { "name": format!("worker {}", lane + 1) },
+            }));
+        }
+
+        let mut starts: HashMap<&str, &TimelineEvent> = HashMap::new();
+        let slice = |step_id: &str, start: &TimelineEvent, end_us: u64, status: &str| {
+            let start_us = offset_us(start.timestamp);
+            json!({
+                "name": step_id,
+                "cat": "step",
+                "ph": "X",
+                "ts": start_us,
+                "dur": end_us.saturating_sub(start_us),
+                "pid": 1,
+                "tid": lanes.get(step_id).map_or(0, |lane| lane + 1),
+                "args": {
+                    "status": status,
+                    "started_at": start.wall_time.to_rfc3339(),
+                },
+            })
+        };
+
+        for event in &self.events {
+            match event.event_type {
+                EventType::Started => {
+                    starts.insert(&event.step_id, event);
+                }
+                EventType::Completed | EventType::Failed => {
+                    if let Some(start) = starts.remove(event.step_id.as_str()) {
+                        let status = if event.event_type == EventType::Completed {
+                            "completed"
+                        } else {
+                            "failed"
+                        };
+                        trace_events.push(slice(&event.step_id, start, offset_us(event.timestamp), status));
+                    }
+                }
+            }
+        }
+
+        let mut unfinished: Vec<_> = starts.into_iter().collect();
+        unfinished.sort_by_key(|(_, start)| start.timestamp);
+        for (step_id, start) in unfinished {
+            trace_events.push(slice(step_id, start, end_us, "running"));
+        }
+
+        for sample in &self.samples {
+            let ts = offset_us(sample.timestamp);
+            trace_events.push(json!({
+                "name": "CPU",
+                "ph": "C",
+                "ts": ts,
+                "pid": 1,
+                "args": { "cpu_usage": sample.cpu_usage },
+            }));
+            trace_events.push(json!({
+                "name": "Memory",
+                "ph": "C",
+                "ts": ts,
+                "pid": 1,
+                "args": { "memory_mb": sample.memory_mb },
+            }));
+        }
+
+        let trace: Value = json!({
+            "traceEvents": trace_events,
+            "displayTimeUnit": "ms",
+            "otherData": {
+                "started_at": self.started_at.to_rfc3339(),
+            },
+        });
+
+        trace.to_string()
+    }
+
+    /// Writes the Chrome trace export to a file.
+    pub fn save_chrome_trace(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.to_chrome_trace())?;
+        Ok(())
+    }
 }
 
 impl Default for ExecutionTimeline {
@@ -292,6 +488,63 @@ mod tests {
         assert_ne!(EventType::Started, EventType::Completed);
     }
 
+    #[test]
+    fn test_events_have_wall_clock_time() {
+        let mut timeline = ExecutionTimeline::new();
+        timeline.add_event("step1".to_string(), EventType::Started);
+
+        let event = &timeline.get_events()[0];
+        assert!(event.wall_time >= timeline.started_at());
+    }
+
+    #[test]
+    fn test_lanes_reuse_free_slots() {
+        let mut timeline = ExecutionTimeline::new();
+        timeline.add_event("a".to_string(), EventType::Started);
+        timeline.add_event("b".to_string(), EventType::Started);
+        timeline.add_event("a".to_string(), EventType::Completed);
+        timeline.add_event("c".to_string(), EventType::Started);
+        timeline.add_event("d".to_string(), EventType::Started);
+
+        let lanes = timeline.lanes();
+        assert_eq!(lanes["a"], 0);
+        assert_eq!(lanes["b"], 1);
+        assert_eq!(lanes["c"], 0);
+        assert_eq!(lanes["d"], 2);
+    }
+
+    #[test]
+    fn test_chrome_trace_export() {
+        let mut timeline = ExecutionTimeline::new();
+        timeline.add_event("a".to_string(), EventType::Started);
+        timeline.add_event("b".to_string(), EventType::Started);
+        timeline.add_sample(50.0, 128);
+        thread::sleep(Duration::from_millis(5));
+        timeline.add_event("a".to_string(), EventType::Completed);
+        timeline.add_event("b".to_string(), EventType::Failed);
+        timeline.add_event("c".to_string(), EventType::Started);
+
+        let trace: serde_json::Value = serde_json::from_str(&timeline.to_chrome_trace()).unwrap();
+        let events = trace["traceEvents"].as_array().unwrap();
+
+        let slices: Vec<_> = events.iter().filter(|e| e["ph"] == "X").collect();
+        assert_eq!(slices.len(), 3);
+        assert_eq!(slices[0]["name"], "a");
+        assert_eq!(slices[0]["tid"], 1);
+        assert!(slices[0]["dur"].as_u64().unwrap() >= 5000);
+        assert_eq!(slices[1]["tid"], 2);
+        assert_eq!(slices[1]["args"]["status"], "failed");
+        assert_eq!(slices[2]["args"]["status"], "running");
+
+        let threads = events.iter().filter(|e| e["name"] == "thread_name").count();
+        assert_eq!(threads, 2);
+
+        let counters: Vec<_> = events.iter().filter(|e| e["ph"] == "C").collect();
+        assert_eq!(counters.len(), 2);
+        assert_eq!(counters[1]["args"]["memory_mb"], 128);
+        assert!(trace["otherData"]["started_at"].is_string());
+    }
+
     #[test]
     fn test_multiple_steps_durations() {
         let mut timeline = ExecutionTimeline::new();
@@ -0,0 +1,96 @@
+//! Stable Fingerprints
+//!
+//! Short content hashes used to recognise the same piece of work across
+//! runs, e.g. a step with an unchanged tool, command and files.
+//!
+//! Uses 64-bit FNV-1a rather than `std`'s `DefaultHasher`, whose output
+//! may change between Rust releases and so can't be persisted.
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Hashes a sequence of strings into a 16-character hex fingerprint.
+///
+/// Parts are separated internally, so `["ab", "c"]` and `["a", "bc"]`
+/// produce different fingerprints.
+///
+/// # Example
+///
+/// ```
+/// use rustrunner::workflow::fingerprint::fingerprint;
+///
+/// let a = fingerprint(["bowtie2", "bowtie2 -x ref {input}"]);
+/// assert_eq!(a.len(), 16);
+/// assert_eq!(a, fingerprint(["bowtie2", "bowtie2 -x ref {input}"]));
+/// ```
+pub fn fingerprint<I, S>(parts: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut hash = FNV_OFFSET_BASIS;
+
+    for part in parts {
+        for byte in part.as_ref().bytes().chain(std::iter::once(0)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    format!("{:016x}", hash)
+}
+
+/// Hashes named fields into a 16-character hex fingerprint.
+///
+/// Each field is written as its tag and the length of its value before the
+/// value, so optional fields that are left out can't be mistaken for one
+/// another: `[("container", "x")]` and `[("shell", "x")]` differ.
+///
+/// # Example
+///
+/// ```
+/// use rustrunner::workflow::fingerprint::fingerprint_fields;
+///
+/// let a = fingerprint_fields([("tool", "bwa"), ("container", "bwa:0.7")]);
+/// assert_ne!(a, fingerprint_fields([("tool", "bwa"), ("conda", "bwa:0.7")]));
+/// ```
+pub fn fingerprint_fields<'a, I>(fields: I) -> String
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    fingerprint(
+        fields
+            .into_iter()
+            .flat_map(|(tag, value)| [tag.to_string(), value.len().to_string(), value.to_string()]),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        // Pinned so persisted fingerprints stay valid across releases
+        assert_eq!(fingerprint([""; 0]), "cbf29ce484222325");
+        assert_eq!(fingerprint(["a"]), fingerprint(["a"]));
+    }
+
+    #[test]
+    fn test_fingerprint_separates_parts() {
+        assert_ne!(fingerprint(["ab", "c"]), fingerprint(["a", "bc"]));
+        assert_ne!(fingerprint(["a"]), fingerprint(["b"]));
+    }
+
+    #[test]
+    fn test_fingerprint_fields_tagged() {
+        assert_ne!(
+            fingerprint_fields([("container", ""), ("shell", "x")]),
+            fingerprint_fields([("container", "x")])
+        );
+        assert_ne!(
+            fingerprint_fields([("input", "a\0")]),
+            fingerprint_fields([("input", "a"), ("input", "")])
+        );
+    }
+}
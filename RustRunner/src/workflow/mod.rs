@@ -9,10 +9,18 @@
 //! - [`parser`]: YAML parsing and loading
 //! - [`validator`]: Validation rules and dependency checking
 //! - [`planner`]: Execution planning and scheduling
+//! - [`shell`]: Interpreter, prologue and epilogue of generated scripts
+//! - [`remote`]: Host and directory for running steps over SSH
+//! - [`script`]: Inline Python/R/Bash script steps
+//! - [`fingerprint`]: Stable content hashes for recognising repeated work
 
+pub mod fingerprint;
 pub mod model;
 pub mod parser;
 pub mod planner;
+pub mod remote;
+pub mod script;
+pub mod shell;
 pub mod state;
 pub mod validator;
 pub mod wildcards;
@@ -20,6 +28,9 @@ pub mod wildcards;
 pub use model::{Step, Workflow};
 pub use parser::load_workflow;
 pub use planner::ExecutionPlanner;
+pub use remote::RemoteConfig;
+pub use script::{InlineScript, ScriptLanguage};
+pub use shell::ShellConfig;
 pub use state::WorkflowState;
 pub use wildcards::{
     expand_workflow_wildcards,
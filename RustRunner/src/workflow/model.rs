@@ -26,9 +26,16 @@
 use serde::de::{self, Deserializer};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::Path;
 
+use crate::environment::spec::CondaSpec;
+
+use super::fingerprint::fingerprint_fields;
+use super::remote::RemoteConfig;
+use super::script::InlineScript;
+use super::shell::ShellConfig;
+
 /// Represents a single step in a workflow.
 ///
 /// Each step defines a command to execute, along with its inputs, outputs,
@@ -43,8 +50,17 @@ pub struct Step {
 
     /// Command template with placeholders
     /// Supported placeholders: {input}, {output}, {inputs}, {outputs}
+    #[serde(default)]
     pub command: String,
 
+    /// Inline Python/R/Bash program run instead of `command`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<InlineScript>,
+
+    /// Parameters passed to inline scripts
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, Value>,
+
     /// Input file(s) for this step
     #[serde(deserialize_with = "single_or_vec", default)]
     pub input: Vec<String>,
@@ -65,6 +81,14 @@ pub struct Step {
     #[serde(default = "default_threads")]
     pub threads: usize,
 
+    /// Maximum run time in seconds before the step is killed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+
+    /// Memory the step needs, e.g. `16G` (requested from cluster executors)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<String>,
+
     /// Optional color for GUI visualization
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub color: Option<String>,
@@ -72,6 +96,22 @@ pub struct Step {
     /// Wildcard file mappings (wildcard_name -> list of concrete files)
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub wildcard_files: HashMap<String, Vec<String>>,
+
+    /// ID of the wildcard step this step was expanded from (auto-populated)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+
+    /// Overrides of the workflow's shell settings for this step
+    #[serde(default, skip_serializing_if = "ShellConfig::is_default")]
+    pub shell: ShellConfig,
+
+    /// Conda environment to run in, instead of one named after the tool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conda: Option<CondaSpec>,
+
+    /// Container image to run in, e.g. `quay.io/biocontainers/samtools:1.17--h00cdaf9_0`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<String>,
 }
 
 /// Default thread count for steps that don't specify
@@ -124,13 +164,21 @@ impl Step {
             id: id.into().trim().to_string(),
             tool: tool.into().trim().to_string(),
             command: command.into().trim().to_string(),
+            script: None,
+            params: BTreeMap::new(),
             input: Vec::new(),
             output: Vec::new(),
             previous: Vec::new(),
             next: Vec::new(),
             threads: 1,
+            timeout: None,
+            memory: None,
             color: None,
             wildcard_files: HashMap::new(),
+            rule: None,
+            shell: ShellConfig::default(),
+            conda: None,
+            container: None,
         }
     }
 
@@ -164,12 +212,110 @@ impl Step {
         self
     }
 
+    /// Sets the maximum run time in seconds for this step.
+    pub fn with_timeout(mut self, seconds: u64) -> Self {
+        self.timeout = Some(seconds);
+        self
+    }
+
+    /// Sets the memory this step needs, e.g. `16G`.
+    pub fn with_memory(mut self, memory: impl Into<String>) -> Self {
+        self.memory = Some(memory.into());
+        self
+    }
+
+    /// Runs an inline script instead of the command.
+    pub fn with_script(mut self, script: InlineScript) -> Self {
+        self.script = Some(script);
+        self
+    }
+
+    /// Sets a parameter passed to the inline script.
+    pub fn with_param(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.params.insert(name.into(), value.into());
+        self
+    }
+
+    /// Sets shell overrides for this step.
+    pub fn with_shell(mut self, shell: ShellConfig) -> Self {
+        self.shell = shell;
+        self
+    }
+
+    /// Runs the step in the environment described by a conda spec.
+    pub fn with_conda(mut self, conda: CondaSpec) -> Self {
+        self.conda = Some(conda);
+        self
+    }
+
+    /// Runs the step inside a container image.
+    pub fn with_container(mut self, image: impl Into<String>) -> Self {
+        self.container = Some(image.into());
+        self
+    }
+
+    /// Returns the rule this step belongs to.
+    ///
+    /// For steps expanded from a wildcard step this is the original step's
+    /// ID, shared by all instances; otherwise it is the step's own ID.
+    pub fn rule_name(&self) -> &str {
+        self.rule.as_deref().unwrap_or(&self.id)
+    }
+
     /// Adds a dependency on another step.
     pub fn depends_on(mut self, step_id: impl Into<String>) -> Self {
         self.previous.push(step_id.into());
         self
     }
 
+    /// Returns a fingerprint of the work this step does.
+    ///
+    /// Covers the tool, command or inline script and its parameters, the
+    /// shell settings the script runs with (see [`Step::shell_with`]), conda
+    /// spec or container image, input and output files and thread count, so
+    /// it changes whenever the step would produce different results or take
+    /// a different amount of time.
+    pub fn fingerprint(&self, workflow_shell: &ShellConfig) -> String {
+        let threads = self.threads.to_string();
+        let params = (!self.params.is_empty())
+            .then(|| serde_json::to_string(&self.params).unwrap_or_default());
+        let shell = serde_json::to_string(&self.shell_with(workflow_shell)).unwrap_or_default();
+        let conda = self
+            .conda
+            .as_ref()
+            .map(|conda| serde_json::to_string(conda).unwrap_or_default());
+
+        // Optional fields are tagged so a missing one can't shift the rest
+        let fields = [
+            ("tool", self.tool.as_str()),
+            ("command", self.command.as_str()),
+            ("threads", threads.as_str()),
+        ]
+        .into_iter()
+        .chain(self.script.iter().flat_map(|script| {
+            [("language", script.language.interpreter()), ("script", script.code.as_str())]
+        }))
+        .chain(params.as_deref().map(|params| ("params", params)))
+        .chain(std::iter::once(("shell", shell.as_str())))
+        .chain(conda.as_deref().map(|conda| ("conda", conda)))
+        .chain(self.container.as_deref().map(|image| ("container", image)))
+        .chain(self.input.iter().map(|file| ("input", file.as_str())))
+        .chain(self.output.iter().map(|file| ("output", file.as_str())));
+
+        fingerprint_fields(fields)
+    }
+
+    /// Returns the shell settings this step's script runs with.
+    ///
+    /// Commands use the workflow's `shell:` section with the step's
+    /// overrides applied; inline scripts use only the step's overrides.
+    pub fn shell_with(&self, workflow_shell: &ShellConfig) -> ShellConfig {
+        match &self.script {
+            Some(script) => script.shell(&self.shell),
+            None => workflow_shell.merged(&self.shell),
+        }
+    }
+
     /// Checks if all output files exist.
     pub fn outputs_exist(&self) -> bool {
         if self.output.is_empty() {
@@ -289,6 +435,14 @@ pub struct Workflow {
     /// List of unique tools used (auto-populated)
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub tools: Vec<String>,
+
+    /// Interpreter, prologue and epilogue for generated step scripts
+    #[serde(default, skip_serializing_if = "ShellConfig::is_default")]
+    pub shell: ShellConfig,
+
+    /// Host to run the steps on over SSH, instead of this machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<RemoteConfig>,
 }
 
 impl Workflow {
@@ -297,6 +451,8 @@ impl Workflow {
         Self {
             steps: Vec::new(),
             tools: Vec::new(),
+            shell: ShellConfig::default(),
+            remote: None,
         }
     }
 
@@ -305,6 +461,8 @@ impl Workflow {
         let mut workflow = Self {
             steps,
             tools: Vec::new(),
+            shell: ShellConfig::default(),
+            remote: None,
         };
         workflow.refresh_tools();
         workflow
@@ -366,6 +524,11 @@ impl Workflow {
         self.tools.sort();
     }
 
+    /// Returns the shell settings for a step; see [`Step::shell_with`].
+    pub fn shell_for(&self, step: &Step) -> ShellConfig {
+        step.shell_with(&self.shell)
+    }
+
     /// Returns the number of steps in the workflow.
     pub fn len(&self) -> usize {
         self.steps.len()
@@ -386,6 +549,12 @@ impl Default for Workflow {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::workflow::script::ScriptLanguage;
+
+    /// Fingerprints a step outside any workflow shell settings.
+    fn fingerprint(step: &Step) -> String {
+        step.fingerprint(&ShellConfig::default())
+    }
 
     #[test]
     fn test_step_creation() {
@@ -484,6 +653,45 @@ mod tests {
         assert!(step.outputs_outdated());
     }
 
+    #[test]
+    fn test_step_timeout() {
+        let step = Step::new("test", "bash", "sleep 10");
+        assert_eq!(step.timeout, None);
+
+        let step = step.with_timeout(5);
+        assert_eq!(step.timeout, Some(5));
+    }
+
+    #[test]
+    fn test_step_memory() {
+        let step: Step = serde_yaml::from_str("id: a\ntool: bwa\ncommand: bwa mem\nmemory: 16G\n").unwrap();
+        assert_eq!(step.memory.as_deref(), Some("16G"));
+
+        // Resources don't change what a step computes
+        assert_eq!(fingerprint(&step), fingerprint(&step.clone().with_memory("32G")));
+    }
+
+    #[test]
+    fn test_step_fingerprint() {
+        let step = Step::new("align", "bowtie2", "bowtie2 {input}").with_input("a.fq");
+
+        assert_eq!(fingerprint(&step), fingerprint(&step.clone()));
+        assert_ne!(fingerprint(&step), fingerprint(&step.clone().with_input("b.fq")));
+        assert_ne!(fingerprint(&step), fingerprint(&step.clone().with_threads(4)));
+
+        // The ID is not part of the work
+        let mut renamed = step.clone();
+        renamed.id = "align2".to_string();
+        assert_eq!(fingerprint(&step), fingerprint(&renamed));
+
+        // The workflow's prologue is part of the script that runs
+        let module = |version: &str| ShellConfig {
+            prologue: Some(format!("module load samtools/{}", version)),
+            ..ShellConfig::default()
+        };
+        assert_ne!(step.fingerprint(&module("1.17")), step.fingerprint(&module("1.18")));
+    }
+
     #[test]
     fn test_step_should_run_force() {
         let step = Step::new("test", "bash", "echo test");
@@ -618,6 +826,88 @@ mod tests {
         assert!(workflow.is_empty());
     }
 
+    #[test]
+    fn test_shell_section_and_step_override() {
+        let yaml = r#"
+shell:
+  prologue: export TMPDIR=/scratch
+steps:
+  - id: a
+    tool: bash
+    command: sort in > out
+  - id: b
+    tool: python
+    shell:
+      interpreter: python3
+    command: print('hi')
+"#;
+        let workflow: Workflow = serde_yaml::from_str(yaml).unwrap();
+
+        let a = workflow.shell_for(&workflow.steps[0]);
+        assert_eq!(a.interpreter(), "bash");
+        assert_eq!(a.prologue.as_deref(), Some("export TMPDIR=/scratch"));
+
+        let b = workflow.shell_for(&workflow.steps[1]);
+        assert_eq!(b.interpreter(), "python3");
+        assert_eq!(b.prologue.as_deref(), Some("export TMPDIR=/scratch"));
+
+        // Unset shell sections are not written back out
+        let yaml = serde_yaml::to_string(&Workflow::from_steps(vec![Step::new("c", "bash", "ls")]))
+            .unwrap();
+        assert!(!yaml.contains("shell"));
+    }
+
+    #[test]
+    fn test_step_conda_spec() {
+        let yaml = r#"
+steps:
+  - id: index
+    tool: bowtie2-build
+    command: bowtie2-build ref.fa ref
+    conda:
+      packages: [bowtie2=2.5.1]
+  - id: call
+    tool: bcftools
+    command: bcftools call in.bcf
+    conda: envs/calling.yml
+"#;
+        let workflow: Workflow = serde_yaml::from_str(yaml).unwrap();
+
+        let index = workflow.steps[0].conda.as_ref().unwrap();
+        assert_eq!(index.packages, vec!["bowtie2=2.5.1"]);
+        let call = workflow.steps[1].conda.as_ref().unwrap();
+        assert_eq!(call.file.as_deref(), Some(Path::new("envs/calling.yml")));
+
+        // A different pin is different work
+        let step = Step::new("a", "samtools", "samtools index {input}");
+        let pinned = step.clone().with_conda(CondaSpec::with_packages(["samtools=1.17"]));
+        assert_ne!(fingerprint(&step), fingerprint(&pinned));
+    }
+
+    #[test]
+    fn test_step_container() {
+        let yaml = "id: sort\ntool: samtools\ncommand: samtools sort in.bam\ncontainer: quay.io/biocontainers/samtools:1.17--h00cdaf9_0\n";
+        let step: Step = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            step.container.as_deref(),
+            Some("quay.io/biocontainers/samtools:1.17--h00cdaf9_0")
+        );
+
+        let other = step.clone().with_container("quay.io/biocontainers/samtools:1.18--h50ea8bc_1");
+        assert_ne!(fingerprint(&step), fingerprint(&other));
+    }
+
+    #[test]
+    fn test_step_fingerprint_tags_optional_fields() {
+        let step = Step::new("a", "bash", "run");
+        let python = step.clone().with_script(InlineScript::new(ScriptLanguage::Python, "x"));
+        let r = step.clone().with_script(InlineScript::new(ScriptLanguage::R, "x"));
+        let contained = step.clone().with_container("x");
+
+        assert_ne!(fingerprint(&python), fingerprint(&contained));
+        assert_ne!(fingerprint(&python), fingerprint(&r));
+    }
+
     #[test]
     fn test_step_has_wildcards() {
         let step = Step::new("test", "bash", "cat {sample}.fastq")
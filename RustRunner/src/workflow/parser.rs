@@ -9,6 +9,9 @@ use std::error::Error;
 use std::fs;
 
 use log::{debug, info, warn};
+use serde_yaml::Value;
+
+use crate::environment::registry::{ToolRegistry, TOOL_REGISTRY};
 
 use super::model::Workflow;
 #[cfg(test)]
@@ -41,7 +44,7 @@ fn expand_wildcards_in_workflow(workflow: &mut Workflow) -> Result<(), String> {
         for (name, files) in &step.wildcard_files {
             wildcard_files
                 .entry(name.clone())
-                .or_insert_with(Vec::new)
+                .or_default()
                 .extend(files.clone());
         }
     }
@@ -119,6 +122,8 @@ pub fn load_workflow(path: &str) -> Result<Workflow, Box<dyn Error>> {
         )
     })?;
 
+    apply_tool_defaults(&mut workflow, &yaml_content, &TOOL_REGISTRY);
+
     info!(
         "Parsed {} steps, {} tools defined",
         workflow.steps.len(),
@@ -137,6 +142,37 @@ pub fn load_workflow(path: &str) -> Result<Workflow, Box<dyn Error>> {
     Ok(workflow)
 }
 
+/// Fills in the registry's defaults for fields a step leaves out: `threads`,
+/// and `container` for container tools without a `conda:` spec.
+///
+/// `yaml_content` is the workflow's source, which tells which fields were
+/// given; it must have been parsed into `workflow` already.
+fn apply_tool_defaults(workflow: &mut Workflow, yaml_content: &str, registry: &ToolRegistry) {
+    let Ok(source) = serde_yaml::from_str::<Value>(yaml_content) else {
+        return;
+    };
+    let Some(sources) = source.get("steps").and_then(Value::as_sequence) else {
+        return;
+    };
+
+    for (step, source) in workflow.steps.iter_mut().zip(sources) {
+        let given = |field: &str| source.get(field).is_some_and(|value| !value.is_null());
+
+        if !given("threads") {
+            if let Some(threads) = registry.threads(&step.tool) {
+                debug!("Step '{}': {} threads from the tool registry", step.id, threads);
+                step.threads = threads;
+            }
+        }
+        if !given("container") && !given("conda") {
+            if let Some(image) = registry.container(&step.tool) {
+                debug!("Step '{}': container {} from the tool registry", step.id, image);
+                step.container = Some(image.to_string());
+            }
+        }
+    }
+}
+
 /// Populates step dependencies based on the workflow structure.
 ///
 /// Supports two modes:
@@ -294,6 +330,43 @@ pub fn save_workflow(workflow: &Workflow, path: &str) -> Result<(), Box<dyn Erro
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_apply_tool_defaults() {
+        let yaml = r#"
+steps:
+  - id: sort
+    tool: samtools
+    command: samtools sort {input}
+  - id: index
+    tool: samtools
+    command: samtools index {input}
+    threads: 1
+  - id: call
+    tool: gatk
+    command: gatk HaplotypeCaller
+  - id: local_call
+    tool: gatk
+    command: gatk HaplotypeCaller
+    conda:
+      packages: [gatk4]
+"#;
+        let mut registry = ToolRegistry::builtin();
+        registry.extend(
+            ToolRegistry::parse(
+                "tools:\n  samtools: { threads: 4 }\n  gatk: { mode: container, container: broadinstitute/gatk:4.5.0.0 }\n",
+            )
+            .unwrap(),
+        );
+
+        let mut workflow: Workflow = serde_yaml::from_str(yaml).unwrap();
+        apply_tool_defaults(&mut workflow, yaml, &registry);
+
+        assert_eq!(workflow.steps[0].threads, 4);
+        assert_eq!(workflow.steps[1].threads, 1);
+        assert_eq!(workflow.steps[2].container.as_deref(), Some("broadinstitute/gatk:4.5.0.0"));
+        assert_eq!(workflow.steps[3].container, None);
+    }
+
     #[test]
     fn test_derive_dependencies() {
         let mut workflow = Workflow::from_steps(vec![
@@ -11,11 +11,14 @@ use super::wildcards::expand_workflow_wildcards;
 use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 
+use chrono::{DateTime, Utc};
 use log::{debug, info};
 use num_cpus;
 
 use super::model::{Step, Workflow};
 use super::state::WorkflowState;
+use crate::execution::StepError;
+use crate::monitoring::ProcessUsage;
 
 /// Status of a workflow step during execution.
 #[derive(Debug, Clone, PartialEq)]
@@ -26,8 +29,8 @@ pub enum StepStatus {
     Running,
     /// Step completed successfully
     Completed,
-    /// Step failed with error message
-    Failed(String),
+    /// Step failed; carries the exit code, stderr tail and command
+    Failed(StepError),
     /// Step was skipped (outputs exist)
     Skipped,
 }
@@ -39,8 +42,20 @@ pub struct StepMetrics {
     pub start_time: Option<Instant>,
     /// When the step finished
     pub end_time: Option<Instant>,
+    /// Wall-clock time the step started
+    pub started_at: Option<DateTime<Utc>>,
+    /// Wall-clock time the step finished
+    pub finished_at: Option<DateTime<Utc>>,
     /// Duration in milliseconds
     pub duration_ms: Option<u128>,
+    /// Exit code of the step's process
+    pub exit_code: Option<i32>,
+    /// Last lines the step wrote to stdout
+    pub stdout_tail: Vec<String>,
+    /// Last lines the step wrote to stderr
+    pub stderr_tail: Vec<String>,
+    /// Resources used by the step's process tree
+    pub resources: Option<ProcessUsage>,
     /// Current status
     pub status: StepStatus,
 }
@@ -50,7 +65,13 @@ impl StepMetrics {
         Self {
             start_time: None,
             end_time: None,
+            started_at: None,
+            finished_at: None,
             duration_ms: None,
+            exit_code: None,
+            stdout_tail: Vec::new(),
+            stderr_tail: Vec::new(),
+            resources: None,
             status: StepStatus::Pending,
         }
     }
@@ -80,8 +101,11 @@ pub struct ExecutionPlanner {
     current_threads_used: usize,
     /// Maximum system threads available
     max_system_threads: usize,
-    /// Wildcard files to expand before planning
-    wildcard_files: Option<HashMap<String, Vec<String>>>,
+    /// Expected step durations in milliseconds, from earlier runs
+    expected_durations: HashMap<String, u64>,
+    /// Longest expected time from each step's start to the end of the
+    /// workflow; ready steps with more work behind them start first
+    priorities: HashMap<String, u64>,
 }
 
 impl ExecutionPlanner {
@@ -103,7 +127,7 @@ impl ExecutionPlanner {
         // Expand wildcards before planning
         let mut workflow = workflow;
         if let Some(files) = &wildcard_files {
-            expand_workflow_wildcards(&mut workflow, &files)?;
+            expand_workflow_wildcards(&mut workflow, files)?;
         }
 
         info!(
@@ -125,7 +149,8 @@ impl ExecutionPlanner {
             step_metrics,
             current_threads_used: 0,
             max_system_threads,
-            wildcard_files,
+            expected_durations: HashMap::new(),
+            priorities: HashMap::new(),
         })
     }
 
@@ -153,33 +178,220 @@ impl ExecutionPlanner {
         Ok(planner)
     }
 
+    /// Sets how many threads running steps may use in total.
+    ///
+    /// Defaults to the CPUs of this machine; `None` removes the limit, e.g.
+    /// when a cluster scheduler allocates CPUs.
+    pub fn set_max_threads(&mut self, max_threads: Option<usize>) {
+        self.max_system_threads = max_threads.unwrap_or(usize::MAX);
+    }
+
+    /// Sets the expected duration of steps, e.g. from the run history.
+    ///
+    /// Used to start steps on the critical path first and for
+    /// [`critical_path`](Self::critical_path) estimates.
+    pub fn set_expected_durations(&mut self, durations: HashMap<String, u64>) {
+        self.priorities = self.longest_chains(&durations);
+        self.expected_durations = durations;
+    }
+
+    /// Returns the expected duration of a step in milliseconds, if known.
+    pub fn expected_duration(&self, step_id: &str) -> Option<u64> {
+        self.expected_durations.get(step_id).copied()
+    }
+
+    /// Computes, for every step, its duration plus the longest chain of
+    /// steps that depend on it. Steps missing from `durations` count as 0.
+    fn longest_chains(&self, durations: &HashMap<String, u64>) -> HashMap<String, u64> {
+        fn visit(
+            id: &str,
+            dependents: &HashMap<&str, Vec<&str>>,
+            durations: &HashMap<String, u64>,
+            priorities: &mut HashMap<String, u64>,
+        ) -> u64 {
+            if let Some(&priority) = priorities.get(id) {
+                return priority;
+            }
+            let longest_tail = dependents
+                .get(id)
+                .map(|next| {
+                    next.iter()
+                        .map(|n| visit(n, dependents, durations, priorities))
+                        .max()
+                        .unwrap_or(0)
+                })
+                .unwrap_or(0);
+            let priority = durations.get(id).copied().unwrap_or(0) + longest_tail;
+            priorities.insert(id.to_string(), priority);
+            priority
+        }
+
+        let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+        for step in &self.workflow.steps {
+            for dep in &step.previous {
+                dependents.entry(dep.as_str()).or_default().push(&step.id);
+            }
+        }
+
+        let mut priorities = HashMap::new();
+        for step in &self.workflow.steps {
+            visit(&step.id, &dependents, durations, &mut priorities);
+        }
+        priorities
+    }
+
+    /// Estimates the time until the workflow finishes, in milliseconds.
+    ///
+    /// Each unfinished step is estimated from, in order of preference:
+    /// 1. Its expected duration from earlier runs
+    /// 2. The average duration of completed instances of the same rule in
+    ///    this run (wildcard steps expanded from one template)
+    /// 3. The average duration of all steps completed in this run
+    ///
+    /// Running steps count only their expected time left. The estimate is
+    /// the larger of the longest remaining dependency chain and the
+    /// remaining work spread over the available job slots and threads.
+    ///
+    /// Returns `None` until there is anything to base an estimate on.
+    pub fn estimate_remaining_ms(&self) -> Option<u64> {
+        let mut rule_durations: HashMap<&str, (u64, u64)> = HashMap::new();
+        let mut run_total = (0u64, 0u64);
+        for step in &self.workflow.steps {
+            let Some(metrics) = self.step_metrics.get(&step.id) else {
+                continue;
+            };
+            if metrics.status != StepStatus::Completed {
+                continue;
+            }
+            if let Some(duration) = metrics.duration_ms {
+                let entry = rule_durations.entry(step.rule_name()).or_default();
+                *entry = (entry.0 + duration as u64, entry.1 + 1);
+                run_total = (run_total.0 + duration as u64, run_total.1 + 1);
+            }
+        }
+
+        let average = |(sum, count): (u64, u64)| (count > 0).then(|| sum / count);
+        let run_average = average(run_total);
+
+        let mut remaining: HashMap<String, u64> = HashMap::new();
+        for step in &self.workflow.steps {
+            if self.completed_steps.contains(&step.id) {
+                continue;
+            }
+
+            let expected = self
+                .expected_duration(&step.id)
+                .or_else(|| rule_durations.get(step.rule_name()).copied().and_then(average))
+                .or(run_average)?;
+
+            let elapsed = self
+                .step_metrics
+                .get(&step.id)
+                .and_then(|m| m.start_time)
+                .filter(|_| self.running_steps.contains(&step.id))
+                .map(|start| start.elapsed().as_millis() as u64)
+                .unwrap_or(0);
+
+            remaining.insert(step.id.clone(), expected.saturating_sub(elapsed));
+        }
+
+        let chain = self.longest_chains(&remaining).into_values().max().unwrap_or(0);
+
+        let work: u64 = remaining.values().sum();
+        let thread_work: u64 = self
+            .workflow
+            .steps
+            .iter()
+            .filter_map(|s| remaining.get(&s.id).map(|ms| ms * s.threads as u64))
+            .sum();
+
+        let by_jobs = work / self.max_parallel_jobs.max(1) as u64;
+        let by_threads = thread_work / self.max_system_threads.max(1) as u64;
+
+        Some(chain.max(by_jobs).max(by_threads))
+    }
+
+    /// Returns the number of steps currently running.
+    pub fn running_count(&self) -> usize {
+        self.running_steps.len()
+    }
+
+    /// Returns the chain of steps with the longest expected total duration,
+    /// and that duration in milliseconds.
+    ///
+    /// Returns `None` if no expected durations are known.
+    pub fn critical_path(&self) -> Option<(Vec<String>, u64)> {
+        if self.expected_durations.is_empty() {
+            return None;
+        }
+
+        let priority = |id: &str| self.priorities.get(id).copied().unwrap_or(0);
+        let mut current = self
+            .workflow
+            .steps
+            .iter()
+            .max_by_key(|s| priority(&s.id))?;
+        let total = priority(&current.id);
+        let mut path = vec![current.id.clone()];
+
+        loop {
+            let next = self
+                .workflow
+                .steps
+                .iter()
+                .filter(|s| s.previous.contains(&current.id))
+                .max_by_key(|s| priority(&s.id));
+            match next {
+                Some(step) => {
+                    path.push(step.id.clone());
+                    current = step;
+                }
+                None => break,
+            }
+        }
+
+        Some((path, total))
+    }
+
     /// Returns steps that are ready to execute.
     ///
     /// A step is ready if:
     /// - It hasn't completed or started
     /// - All its dependencies are completed
     /// - Adding it wouldn't exceed resource limits
+    ///
+    /// When expected durations are known, steps with the longest expected
+    /// remaining chain come first.
     pub fn get_ready_steps(&self) -> Vec<Step> {
         let mut ready_steps = Vec::new();
         let mut threads_to_allocate = 0;
 
-        for step in &self.workflow.steps {
-            // Skip completed or running steps
-            if self.completed_steps.contains(&step.id) || self.running_steps.contains(&step.id) {
-                continue;
-            }
-
-            // Check if all dependencies are completed
-            let deps_complete = step.previous.is_empty()
-                || step
-                    .previous
-                    .iter()
-                    .all(|dep| self.completed_steps.contains(dep));
-
-            if !deps_complete {
-                continue;
-            }
+        let mut candidates: Vec<&Step> = self
+            .workflow
+            .steps
+            .iter()
+            .filter(|step| {
+                // Skip completed or running steps
+                !self.completed_steps.contains(&step.id) && !self.running_steps.contains(&step.id)
+            })
+            .filter(|step| {
+                // Check if all dependencies are completed
+                step.previous.is_empty()
+                    || step
+                        .previous
+                        .iter()
+                        .all(|dep| self.completed_steps.contains(dep))
+            })
+            .collect();
+
+        // Stable sort keeps workflow order among equal priorities
+        if !self.priorities.is_empty() {
+            candidates.sort_by_key(|step| {
+                std::cmp::Reverse(self.priorities.get(&step.id).copied().unwrap_or(0))
+            });
+        }
 
+        for step in candidates {
             // Check parallel job limit
             if ready_steps.len() >= self.max_parallel_jobs {
                 break;
@@ -221,6 +433,7 @@ impl ExecutionPlanner {
 
         if let Some(metrics) = self.step_metrics.get_mut(step_id) {
             metrics.start_time = Some(Instant::now());
+            metrics.started_at = Some(Utc::now());
             metrics.status = StepStatus::Running;
         }
     }
@@ -242,6 +455,7 @@ impl ExecutionPlanner {
         if let Some(metrics) = self.step_metrics.get_mut(step_id) {
             let now = Instant::now();
             metrics.end_time = Some(now);
+            metrics.finished_at = Some(Utc::now());
             if let Some(start) = metrics.start_time {
                 metrics.duration_ms = Some(start.elapsed().as_millis());
             }
@@ -250,7 +464,10 @@ impl ExecutionPlanner {
     }
 
     /// Marks a step as failed.
-    pub fn mark_step_failed(&mut self, step_id: &str, error: String) {
+    ///
+    /// The error's exit code, stderr tail and resource usage are copied
+    /// into the step's metrics.
+    pub fn mark_step_failed(&mut self, step_id: &str, error: StepError) {
         self.running_steps.remove(step_id);
 
         // Release thread resources
@@ -261,13 +478,49 @@ impl ExecutionPlanner {
         if let Some(metrics) = self.step_metrics.get_mut(step_id) {
             let now = Instant::now();
             metrics.end_time = Some(now);
+            metrics.finished_at = Some(Utc::now());
             if let Some(start) = metrics.start_time {
                 metrics.duration_ms = Some(start.elapsed().as_millis());
             }
+            metrics.exit_code = error.exit_code;
+            metrics.stderr_tail = error.stderr_tail.clone();
+            metrics.resources = Some(error.resources);
             metrics.status = StepStatus::Failed(error);
         }
     }
 
+    /// Records the exit code of a step's process.
+    pub fn set_exit_code(&mut self, step_id: &str, exit_code: Option<i32>) {
+        if let Some(metrics) = self.step_metrics.get_mut(step_id) {
+            metrics.exit_code = exit_code;
+        }
+    }
+
+    /// Records the trailing output lines of a finished step.
+    pub fn set_log_tail(
+        &mut self,
+        step_id: &str,
+        stdout_tail: Vec<String>,
+        stderr_tail: Vec<String>,
+    ) {
+        if let Some(metrics) = self.step_metrics.get_mut(step_id) {
+            metrics.stdout_tail = stdout_tail;
+            metrics.stderr_tail = stderr_tail;
+        }
+    }
+
+    /// Records the resources a finished step used.
+    pub fn set_resources(&mut self, step_id: &str, resources: ProcessUsage) {
+        if let Some(metrics) = self.step_metrics.get_mut(step_id) {
+            metrics.resources = Some(resources);
+        }
+    }
+
+    /// Returns the workflow being planned (after wildcard expansion).
+    pub fn workflow(&self) -> &Workflow {
+        &self.workflow
+    }
+
     /// Returns true if there are more steps to execute.
     pub fn has_work_remaining(&self) -> bool {
         self.completed_steps.len() < self.workflow.steps.len()
@@ -384,13 +637,21 @@ mod tests {
         let mut planner = ExecutionPlanner::new(workflow, false, 4, None).unwrap();
 
         planner.mark_step_running("step1");
-        planner.mark_step_failed("step1", "Test error".to_string());
+        planner.mark_step_failed(
+            "step1",
+            StepError::new("step1", "Test error")
+                .with_exit_code(Some(1))
+                .with_stderr_tail(vec!["oops".to_string()]),
+        );
 
         let metrics = planner.get_metrics();
-        match &metrics.get("step1").unwrap().status {
-            StepStatus::Failed(msg) => assert_eq!(msg, "Test error"),
+        let step1 = metrics.get("step1").unwrap();
+        match &step1.status {
+            StepStatus::Failed(error) => assert_eq!(error.message, "Test error"),
             _ => panic!("Expected Failed status"),
         }
+        assert_eq!(step1.exit_code, Some(1));
+        assert_eq!(step1.stderr_tail, vec!["oops"]);
     }
 
     #[test]
@@ -440,6 +701,115 @@ mod tests {
         assert!(step1_metrics.end_time.is_some());
         assert!(step1_metrics.duration_ms.is_some());
         assert!(step1_metrics.duration_ms.unwrap() >= 10);
+        assert!(step1_metrics.started_at.unwrap() <= step1_metrics.finished_at.unwrap());
+    }
+
+    #[test]
+    fn test_planner_set_exit_code() {
+        let workflow = create_test_workflow();
+        let mut planner = ExecutionPlanner::new(workflow, false, 4, None).unwrap();
+
+        planner.mark_step_running("step1");
+        planner.mark_step_failed("step1", StepError::new("step1", "boom"));
+        planner.set_exit_code("step1", Some(2));
+
+        assert_eq!(planner.get_metrics()["step1"].exit_code, Some(2));
+    }
+
+    #[test]
+    fn test_planner_set_resources() {
+        let workflow = create_test_workflow();
+        let mut planner = ExecutionPlanner::new(workflow, false, 4, None).unwrap();
+
+        let usage = ProcessUsage {
+            peak_memory_mb: 512,
+            cpu_time_ms: 1500,
+            ..Default::default()
+        };
+        planner.set_resources("step1", usage);
+
+        assert_eq!(planner.get_metrics()["step1"].resources, Some(usage));
+        assert_eq!(planner.get_metrics()["step2"].resources, None);
+    }
+
+    #[test]
+    fn test_planner_critical_path() {
+        let mut workflow = Workflow::new();
+        workflow.add_step(Step::new("short", "bash", "echo")).unwrap();
+        workflow.add_step(Step::new("long", "bash", "echo")).unwrap();
+        workflow
+            .add_step(Step::new("merge", "bash", "echo").depends_on("short").depends_on("long"))
+            .unwrap();
+
+        let mut planner = ExecutionPlanner::new(workflow, false, 4, None).unwrap();
+        assert!(planner.critical_path().is_none());
+
+        planner.set_expected_durations(HashMap::from([
+            ("short".to_string(), 100),
+            ("long".to_string(), 5000),
+            ("merge".to_string(), 1000),
+        ]));
+
+        let (path, total) = planner.critical_path().unwrap();
+        assert_eq!(path, vec!["long", "merge"]);
+        assert_eq!(total, 6000);
+        assert_eq!(planner.expected_duration("short"), Some(100));
+    }
+
+    #[test]
+    fn test_planner_starts_critical_steps_first() {
+        let mut workflow = Workflow::new();
+        workflow.add_step(Step::new("quick", "bash", "echo")).unwrap();
+        workflow.add_step(Step::new("slow", "bash", "echo")).unwrap();
+
+        let mut planner = ExecutionPlanner::new(workflow, false, 1, None).unwrap();
+        assert_eq!(planner.get_ready_steps()[0].id, "quick");
+
+        planner.set_expected_durations(HashMap::from([
+            ("quick".to_string(), 10),
+            ("slow".to_string(), 10_000),
+        ]));
+        assert_eq!(planner.get_ready_steps()[0].id, "slow");
+    }
+
+    #[test]
+    fn test_planner_estimate_remaining_from_history() {
+        let workflow = create_test_workflow();
+        let mut planner = ExecutionPlanner::new(workflow, false, 4, None).unwrap();
+        assert_eq!(planner.estimate_remaining_ms(), None);
+
+        planner.set_expected_durations(HashMap::from([
+            ("step1".to_string(), 1000),
+            ("step2".to_string(), 2000),
+        ]));
+        // step2 depends on step1, so they can't overlap
+        assert_eq!(planner.estimate_remaining_ms(), Some(3000));
+
+        planner.mark_step_running("step1");
+        planner.mark_step_completed("step1");
+        assert_eq!(planner.estimate_remaining_ms(), Some(2000));
+    }
+
+    #[test]
+    fn test_planner_estimate_remaining_from_rule_average() {
+        let mut workflow = Workflow::new();
+        for sample in ["a", "b", "c"] {
+            let mut step = Step::new(format!("trim_{}", sample), "bash", "echo");
+            step.rule = Some("trim".to_string());
+            workflow.add_step(step).unwrap();
+        }
+        workflow.add_step(Step::new("other", "bash", "echo")).unwrap();
+
+        let mut planner = ExecutionPlanner::new(workflow, false, 1, None).unwrap();
+        planner.mark_step_running("trim_a");
+        planner.mark_step_completed("trim_a");
+        planner.step_metrics.get_mut("trim_a").unwrap().duration_ms = Some(600);
+        planner.mark_step_running("other");
+        planner.mark_step_completed("other");
+        planner.step_metrics.get_mut("other").unwrap().duration_ms = Some(10);
+
+        // Two trim instances left at 600ms each, one job slot
+        assert_eq!(planner.estimate_remaining_ms(), Some(1200));
     }
 
     #[test]
@@ -486,6 +856,19 @@ mod tests {
         assert_eq!(ready.len(), 2);
     }
 
+    #[test]
+    fn test_planner_max_threads() {
+        let mut workflow = Workflow::new();
+        let threads = num_cpus::get() + 1;
+        workflow.add_step(Step::new("big", "bwa", "bwa mem").with_threads(threads)).unwrap();
+
+        let mut planner = ExecutionPlanner::new(workflow, false, 4, None).unwrap();
+        assert!(planner.get_ready_steps().is_empty());
+
+        planner.set_max_threads(None);
+        assert_eq!(planner.get_ready_steps().len(), 1);
+    }
+
     #[test]
     fn test_planner_step_metrics_new_default() {
         let metrics = StepMetrics::new();
@@ -0,0 +1,85 @@
+//! Remote Host Configuration
+//!
+//! A workflow can run its steps on another machine over SSH, e.g. a lab
+//! server holding the data while the GUI runs on a laptop. The `remote:`
+//! section names the host and the working directory on it:
+//!
+//! ```yaml
+//! remote:
+//!   host: alice@lab-server      # anything `ssh` accepts, including ~/.ssh/config aliases
+//!   dir: /data/projects/rnaseq
+//!
+//! steps:
+//!   - id: count
+//!     tool: bash
+//!     command: wc -l {input} > {output}
+//!     input: reads.txt
+//!     output: counts/reads.txt
+//! ```
+//!
+//! Relative input and output paths refer to the same files below the local
+//! and the remote working directory.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// A host that steps run on and the directory they run in there.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RemoteConfig {
+    /// SSH destination, e.g. `lab-server` or `alice@10.0.0.5`
+    pub host: String,
+
+    /// Working directory on the host (absolute)
+    pub dir: PathBuf,
+}
+
+impl RemoteConfig {
+    /// Creates a remote configuration.
+    pub fn new(host: impl Into<String>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            host: host.into(),
+            dir: dir.into(),
+        }
+    }
+
+    /// Parses `HOST:DIR`, e.g. `alice@lab-server:/data/projects/rnaseq`.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.split_once(':') {
+            Some((host, dir)) if !host.is_empty() && dir.starts_with('/') => Ok(Self::new(host, dir)),
+            _ => Err(format!(
+                "Invalid remote '{}' (expected HOST:/absolute/dir)",
+                value
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RemoteConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.dir.display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_remote() {
+        let remote = RemoteConfig::parse("alice@lab:/data/run").unwrap();
+        assert_eq!(remote, RemoteConfig::new("alice@lab", "/data/run"));
+        assert_eq!(remote.to_string(), "alice@lab:/data/run");
+
+        assert!(RemoteConfig::parse("lab").is_err());
+        assert!(RemoteConfig::parse("lab:relative/dir").is_err());
+        assert!(RemoteConfig::parse(":/data").is_err());
+    }
+
+    #[test]
+    fn test_remote_yaml() {
+        let remote: RemoteConfig = serde_yaml::from_str("host: lab\ndir: /data\n").unwrap();
+        assert_eq!(remote, RemoteConfig::new("lab", "/data"));
+    }
+}
@@ -0,0 +1,252 @@
+//! Inline Script Steps
+//!
+//! A step can carry a short Python, R or Bash program in a `script:` block
+//! instead of a shell `command`. The program is written to the run's script
+//! directory and run with the language's interpreter from the step's conda
+//! environment.
+//!
+//! Rather than substituting placeholders into the code, the step's inputs,
+//! outputs, threads and `params` are written to a JSON sidecar and exposed
+//! to the program as a `step` object:
+//!
+//! - Python: a `dict`, e.g. `step["input"][0]`, `step["params"]["min_count"]`
+//! - R: a named `list`, e.g. `step$input[1]`, `step$params$min_count`
+//! - Bash: the `RUSTRUNNER_STEP_JSON` variable holds the sidecar path
+//!
+//! # Example YAML Format
+//!
+//! ```yaml
+//! steps:
+//!   - id: count_reads
+//!     tool: pandas
+//!     input: reads.tsv
+//!     output: counts.csv
+//!     params:
+//!       min_count: 10
+//!     script:
+//!       language: python
+//!       code: |
+//!         import pandas as pd
+//!         df = pd.read_csv(step["input"][0], sep="\t")
+//!         df[df["count"] >= step["params"]["min_count"]].to_csv(step["output"][0])
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::shell::ShellConfig;
+
+/// Environment variable holding the path of a step's JSON sidecar.
+pub const STEP_JSON_ENV: &str = "RUSTRUNNER_STEP_JSON";
+
+/// Language of an inline script.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptLanguage {
+    /// Run with `python`
+    Python,
+    /// Run with `Rscript`
+    #[serde(alias = "R")]
+    R,
+    /// Run with `bash`
+    Bash,
+}
+
+impl ScriptLanguage {
+    /// Returns the default interpreter for the language.
+    pub fn interpreter(self) -> &'static str {
+        match self {
+            Self::Python => "python",
+            Self::R => "Rscript",
+            Self::Bash => "bash",
+        }
+    }
+}
+
+/// A program embedded in a workflow step.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InlineScript {
+    /// Language the code is written in
+    pub language: ScriptLanguage,
+
+    /// Program source
+    pub code: String,
+}
+
+impl InlineScript {
+    /// Creates an inline script.
+    pub fn new(language: ScriptLanguage, code: impl Into<String>) -> Self {
+        Self {
+            language,
+            code: code.into(),
+        }
+    }
+
+    /// Returns the shell settings for running this script.
+    ///
+    /// Only the step's own overrides apply; the workflow's `shell:` section
+    /// is written for the workflow's shell, not for this language.
+    pub fn shell(&self, step_overrides: &ShellConfig) -> ShellConfig {
+        ShellConfig::with_interpreter(self.language.interpreter()).merged(step_overrides)
+    }
+
+    /// Returns the program with a preamble that loads the step's sidecar
+    /// into a `step` object.
+    pub fn render(&self, data: &StepData, sidecar_path: &Path) -> String {
+        let preamble = match self.language {
+            ScriptLanguage::Python => format!(
+                "import json as _rr_json\nwith open({}) as _rr_file:\n    step = _rr_json.load(_rr_file)\n",
+                json_string(&sidecar_path.to_string_lossy())
+            ),
+            ScriptLanguage::R => format!("step <- {}\n", r_literal(&data.to_value())),
+            ScriptLanguage::Bash => String::new(),
+        };
+
+        format!("{}{}", preamble, self.code)
+    }
+}
+
+/// Structured description of a step passed to inline scripts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct StepData {
+    /// ID of the step
+    pub step_id: String,
+
+    /// Input files
+    pub input: Vec<String>,
+
+    /// Output files
+    pub output: Vec<String>,
+
+    /// Number of threads the step may use
+    pub threads: usize,
+
+    /// User-defined parameters from the step's `params:` section
+    pub params: BTreeMap<String, Value>,
+}
+
+impl StepData {
+    /// Converts the data to a JSON value.
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+
+    /// Serializes the data as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+/// Quotes a string as a JSON (and Python) string literal.
+fn json_string(s: &str) -> String {
+    Value::String(s.to_string()).to_string()
+}
+
+/// Converts a JSON value to an equivalent R expression.
+///
+/// Arrays of scalars become vectors, other arrays and objects become lists.
+fn r_literal(value: &Value) -> String {
+    match value {
+        Value::Null => "NULL".to_string(),
+        Value::Bool(true) => "TRUE".to_string(),
+        Value::Bool(false) => "FALSE".to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => json_string(s),
+        Value::Array(items) => {
+            let scalars = items
+                .iter()
+                .all(|v| matches!(v, Value::Bool(_) | Value::Number(_) | Value::String(_)));
+            let body: Vec<String> = items.iter().map(r_literal).collect();
+            if items.is_empty() {
+                "character(0)".to_string()
+            } else if scalars {
+                format!("c({})", body.join(", "))
+            } else {
+                format!("list({})", body.join(", "))
+            }
+        }
+        Value::Object(map) => {
+            let body: Vec<String> = map
+                .iter()
+                .map(|(key, v)| format!("{} = {}", json_string(key), r_literal(v)))
+                .collect();
+            format!("list({})", body.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample_data() -> StepData {
+        StepData {
+            step_id: "count".to_string(),
+            input: vec!["reads.tsv".to_string()],
+            output: vec!["counts.csv".to_string()],
+            threads: 2,
+            params: BTreeMap::from([("min_count".to_string(), json!(10))]),
+        }
+    }
+
+    #[test]
+    fn test_language_parsing() {
+        let script: InlineScript =
+            serde_yaml::from_str("language: R\ncode: print(step$threads)\n").unwrap();
+        assert_eq!(script.language, ScriptLanguage::R);
+
+        let script: InlineScript =
+            serde_yaml::from_str("language: python\ncode: print(1)\n").unwrap();
+        assert_eq!(script.language, ScriptLanguage::Python);
+        assert_eq!(script.shell(&ShellConfig::default()).interpreter(), "python");
+    }
+
+    #[test]
+    fn test_step_overrides_interpreter() {
+        let script = InlineScript::new(ScriptLanguage::Python, "print(1)");
+        let shell = script.shell(&ShellConfig::with_interpreter("python3 -u"));
+
+        assert_eq!(shell.interpreter(), "python3 -u");
+        assert_eq!(shell.script_extension(), "py");
+    }
+
+    #[test]
+    fn test_python_preamble_reads_sidecar() {
+        let script = InlineScript::new(ScriptLanguage::Python, "print(step['params'])");
+        let rendered = script.render(&sample_data(), Path::new("/runs/r1/count.step.json"));
+
+        assert!(rendered.contains("open(\"/runs/r1/count.step.json\")"));
+        assert!(rendered.ends_with("print(step['params'])"));
+    }
+
+    #[test]
+    fn test_r_preamble_embeds_list() {
+        let script = InlineScript::new(ScriptLanguage::R, "print(step$params$min_count)");
+        let rendered = script.render(&sample_data(), Path::new("/unused"));
+
+        assert!(rendered.starts_with(
+            "step <- list(\"input\" = c(\"reads.tsv\"), \"output\" = c(\"counts.csv\"), \
+             \"params\" = list(\"min_count\" = 10), \"step_id\" = \"count\", \"threads\" = 2)\n"
+        ));
+    }
+
+    #[test]
+    fn test_r_literal() {
+        assert_eq!(r_literal(&json!(null)), "NULL");
+        assert_eq!(r_literal(&json!([true, false])), "c(TRUE, FALSE)");
+        assert_eq!(r_literal(&json!([])), "character(0)");
+        assert_eq!(r_literal(&json!([[1], "a"])), "list(c(1), \"a\")");
+        assert_eq!(r_literal(&json!("say \"hi\"")), "\"say \\\"hi\\\"\"");
+    }
+
+    #[test]
+    fn test_step_data_json() {
+        let json = sample_data().to_json();
+        let parsed: StepData = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, sample_data());
+    }
+}
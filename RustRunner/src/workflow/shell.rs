@@ -0,0 +1,228 @@
+//! Shell Configuration
+//!
+//! Controls how generated step scripts are run: the interpreter, a
+//! prologue written before the command and an epilogue written after it.
+//! A workflow-level `shell:` section sets the defaults and each step can
+//! override individual fields.
+//!
+//! # Example YAML Format
+//!
+//! ```yaml
+//! shell:
+//!   prologue: |
+//!     module load samtools
+//!     export TMPDIR=/scratch/tmp
+//!
+//! steps:
+//!   - id: align
+//!     tool: bwa
+//!     command: bwa mem ref.fa {input} | samtools sort -o {output}
+//!     input: reads.fq
+//!     output: aligned.bam
+//!
+//!   - id: summarize
+//!     tool: python
+//!     shell:
+//!       interpreter: python3
+//!     command: |
+//!       import json
+//!       print(json.dumps({"done": True}))
+//! ```
+//!
+//! Bash scripts run in strict mode (`set -euo pipefail`) unless `strict:
+//! false` is given, so a failure anywhere in a pipe fails the step.
+
+use serde::{Deserialize, Serialize};
+
+/// Interpreter used when none is configured.
+pub const DEFAULT_INTERPRETER: &str = "bash";
+
+/// How a step's script is interpreted and wrapped.
+///
+/// All fields are optional so that a step's settings can be layered over
+/// the workflow's with [`ShellConfig::merged`].
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ShellConfig {
+    /// Interpreter command, optionally with arguments (default: `bash`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<String>,
+
+    /// Enable the interpreter's strict mode (default: true)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+
+    /// Code inserted before the step's command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prologue: Option<String>,
+
+    /// Code inserted after the step's command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epilogue: Option<String>,
+}
+
+impl ShellConfig {
+    /// Creates a configuration for the given interpreter.
+    pub fn with_interpreter(interpreter: impl Into<String>) -> Self {
+        Self {
+            interpreter: Some(interpreter.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Returns true if nothing is configured.
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Returns this configuration with the fields set in `overrides` replaced.
+    pub fn merged(&self, overrides: &ShellConfig) -> ShellConfig {
+        ShellConfig {
+            interpreter: overrides.interpreter.clone().or_else(|| self.interpreter.clone()),
+            strict: overrides.strict.or(self.strict),
+            prologue: overrides.prologue.clone().or_else(|| self.prologue.clone()),
+            epilogue: overrides.epilogue.clone().or_else(|| self.epilogue.clone()),
+        }
+    }
+
+    /// Returns the interpreter command.
+    pub fn interpreter(&self) -> &str {
+        self.interpreter
+            .as_deref()
+            .map(str::trim)
+            .filter(|i| !i.is_empty())
+            .unwrap_or(DEFAULT_INTERPRETER)
+    }
+
+    /// Returns the interpreter program and its arguments.
+    pub fn interpreter_argv(&self) -> Vec<String> {
+        self.interpreter().split_whitespace().map(str::to_string).collect()
+    }
+
+    /// Returns the interpreter program name without directory or arguments.
+    fn program_name(&self) -> &str {
+        let program = self.interpreter().split_whitespace().next().unwrap_or_default();
+        program.rsplit('/').next().unwrap_or(program)
+    }
+
+    /// Returns the strict-mode line for the interpreter, if it has one.
+    pub fn strict_mode(&self) -> Option<&'static str> {
+        if !self.strict.unwrap_or(true) {
+            return None;
+        }
+
+        match self.program_name() {
+            "bash" | "zsh" => Some("set -euo pipefail"),
+            "sh" | "dash" | "ksh" => Some("set -eu"),
+            _ => None,
+        }
+    }
+
+    /// Returns the file extension used for scripts of this interpreter.
+    pub fn script_extension(&self) -> &'static str {
+        let program = self.program_name();
+        if program.starts_with("python") {
+            "py"
+        } else if program == "Rscript" || program == "R" {
+            "R"
+        } else if program == "perl" {
+            "pl"
+        } else {
+            "sh"
+        }
+    }
+
+    /// Renders a complete script around a step's command text.
+    pub fn render_script(&self, command_text: &str) -> String {
+        let mut script = format!("#!/usr/bin/env {}\n", self.interpreter());
+
+        for part in [self.strict_mode(), self.prologue.as_deref()]
+            .into_iter()
+            .flatten()
+            .chain(std::iter::once(command_text))
+            .chain(self.epilogue.as_deref())
+        {
+            script.push_str(part.trim_end());
+            script.push('\n');
+        }
+
+        script
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_is_strict_bash() {
+        let shell = ShellConfig::default();
+
+        assert_eq!(shell.interpreter(), "bash");
+        assert_eq!(shell.script_extension(), "sh");
+        assert_eq!(
+            shell.render_script("bwa mem ref.fa r.fq | samtools sort"),
+            "#!/usr/bin/env bash\nset -euo pipefail\nbwa mem ref.fa r.fq | samtools sort\n"
+        );
+    }
+
+    #[test]
+    fn test_merged_prefers_overrides() {
+        let workflow = ShellConfig {
+            prologue: Some("module load samtools".to_string()),
+            epilogue: Some("echo done".to_string()),
+            ..ShellConfig::default()
+        };
+        let step = ShellConfig {
+            interpreter: Some("python3".to_string()),
+            epilogue: Some("print('done')".to_string()),
+            ..ShellConfig::default()
+        };
+
+        let merged = workflow.merged(&step);
+        assert_eq!(merged.interpreter(), "python3");
+        assert_eq!(merged.prologue.as_deref(), Some("module load samtools"));
+        assert_eq!(merged.epilogue.as_deref(), Some("print('done')"));
+    }
+
+    #[test]
+    fn test_prologue_and_epilogue() {
+        let shell = ShellConfig {
+            strict: Some(false),
+            prologue: Some("export TMPDIR=/scratch\n".to_string()),
+            epilogue: Some("echo finished".to_string()),
+            ..ShellConfig::default()
+        };
+
+        assert_eq!(
+            shell.render_script("sort in > out"),
+            "#!/usr/bin/env bash\nexport TMPDIR=/scratch\nsort in > out\necho finished\n"
+        );
+    }
+
+    #[test]
+    fn test_non_shell_interpreters() {
+        let python = ShellConfig::with_interpreter("/usr/bin/python3 -u");
+        assert_eq!(python.interpreter_argv(), vec!["/usr/bin/python3", "-u"]);
+        assert_eq!(python.script_extension(), "py");
+        assert_eq!(python.strict_mode(), None);
+        assert!(python.render_script("print(1)").ends_with("\nprint(1)\n"));
+
+        assert_eq!(ShellConfig::with_interpreter("Rscript").script_extension(), "R");
+        assert_eq!(ShellConfig::with_interpreter("sh").strict_mode(), Some("set -eu"));
+    }
+
+    #[test]
+    fn test_blank_interpreter_falls_back_to_bash() {
+        assert_eq!(ShellConfig::with_interpreter("  ").interpreter(), "bash");
+    }
+
+    #[test]
+    fn test_yaml_parsing() {
+        let shell: ShellConfig =
+            serde_yaml::from_str("interpreter: Rscript\nstrict: false\n").unwrap();
+
+        assert_eq!(shell.interpreter(), "Rscript");
+        assert_eq!(shell.strict, Some(false));
+        assert!(shell.prologue.is_none());
+    }
+}
@@ -15,6 +15,8 @@ use std::time::SystemTime;
 use log::info;
 use serde::{Deserialize, Serialize};
 
+use crate::execution::StepError;
+
 /// Persistent state for a workflow execution.
 ///
 /// Tracks which steps have completed and any failures,
@@ -30,6 +32,10 @@ pub struct WorkflowState {
     /// ID of the step that failed (if any)
     pub failed_step: Option<String>,
 
+    /// Why the failed step failed, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure: Option<StepError>,
+
     /// Last time the state was updated
     pub timestamp: SystemTime,
 }
@@ -41,6 +47,7 @@ impl WorkflowState {
             workflow_path: workflow_path.to_string(),
             completed_steps: HashSet::new(),
             failed_step: None,
+            failure: None,
             timestamp: SystemTime::now(),
         }
     }
@@ -94,12 +101,21 @@ impl WorkflowState {
     pub fn mark_completed(&mut self, step_id: &str) {
         self.completed_steps.insert(step_id.to_string());
         self.failed_step = None;
+        self.failure = None;
         self.timestamp = SystemTime::now();
     }
 
     /// Marks a step as failed.
     pub fn mark_failed(&mut self, step_id: &str) {
         self.failed_step = Some(step_id.to_string());
+        self.failure = None;
+        self.timestamp = SystemTime::now();
+    }
+
+    /// Marks a step as failed, keeping the structured error.
+    pub fn mark_failed_with_error(&mut self, error: &StepError) {
+        self.failed_step = Some(error.step_id.clone());
+        self.failure = Some(error.clone());
         self.timestamp = SystemTime::now();
     }
 
@@ -112,6 +128,7 @@ impl WorkflowState {
     pub fn clear(&mut self) {
         self.completed_steps.clear();
         self.failed_step = None;
+        self.failure = None;
         self.timestamp = SystemTime::now();
     }
 
@@ -157,6 +174,34 @@ mod tests {
         assert!(state.is_resume());
     }
 
+    #[test]
+    fn test_mark_failed_with_error() {
+        let mut state = WorkflowState::new("test.yaml");
+        let error = StepError::new("step2", "Step 'step2' failed with exit code 1")
+            .with_exit_code(Some(1))
+            .with_stderr_tail(vec!["oops".to_string()]);
+        state.mark_failed_with_error(&error);
+
+        assert_eq!(state.failed_step, Some("step2".to_string()));
+
+        let json = serde_json::to_string(&state).unwrap();
+        let loaded: WorkflowState = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.failure, Some(error));
+
+        state.mark_completed("step2");
+        assert!(state.failure.is_none());
+    }
+
+    #[test]
+    fn test_state_without_failure_field_loads() {
+        let json = r#"{"workflow_path":"old.yaml","completed_steps":["a"],"failed_step":"b",
+            "timestamp":{"secs_since_epoch":0,"nanos_since_epoch":0}}"#;
+        let state: WorkflowState = serde_json::from_str(json).unwrap();
+
+        assert_eq!(state.failed_step, Some("b".to_string()));
+        assert!(state.failure.is_none());
+    }
+
     #[test]
     fn test_state_serialization_roundtrip() {
         // Test serialization/deserialization without filesystem cwd changes
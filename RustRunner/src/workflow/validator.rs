@@ -20,6 +20,10 @@ pub enum ValidationError {
     EmptyStepId,
     EmptyTool(String),
     EmptyCommand(String),
+    CommandAndScript(String),
+    InvalidCondaSpec { step: String, reason: String },
+    ContainerAndConda(String),
+    InvalidMemory { step: String, memory: String },
     InvalidReference { step: String, reference: String },
     CyclicDependency,
     UnusedPlaceholder { step: String, placeholder: String },
@@ -33,6 +37,20 @@ impl std::fmt::Display for ValidationError {
             Self::EmptyStepId => write!(f, "Step has empty or whitespace-only ID"),
             Self::EmptyTool(step) => write!(f, "Step '{}' has no tool specified", step),
             Self::EmptyCommand(step) => write!(f, "Step '{}' has no command specified", step),
+            Self::CommandAndScript(step) => {
+                write!(f, "Step '{}' has both a command and a script; use one", step)
+            }
+            Self::InvalidCondaSpec { step, reason } => {
+                write!(f, "Step '{}' has an invalid conda spec: {}", step, reason)
+            }
+            Self::ContainerAndConda(step) => {
+                write!(f, "Step '{}' has both a container image and a conda spec; use one", step)
+            }
+            Self::InvalidMemory { step, memory } => write!(
+                f,
+                "Step '{}' has invalid memory '{}' (expected a number with an optional K, M, G or T suffix)",
+                step, memory
+            ),
             Self::InvalidReference { step, reference } => {
                 write!(f, "Step '{}' references unknown step '{}'", step, reference)
             }
@@ -61,9 +79,37 @@ fn validate_step(step: &Step) -> Vec<ValidationError> {
         errors.push(ValidationError::EmptyTool(step.id.clone()));
     }
 
-    // Check command
-    if step.command.trim().is_empty() {
-        errors.push(ValidationError::EmptyCommand(step.id.clone()));
+    // Check command (or inline script)
+    match &step.script {
+        None if step.command.trim().is_empty() => {
+            errors.push(ValidationError::EmptyCommand(step.id.clone()));
+        }
+        Some(script) if script.code.trim().is_empty() => {
+            errors.push(ValidationError::EmptyCommand(step.id.clone()));
+        }
+        Some(_) if !step.command.trim().is_empty() => {
+            errors.push(ValidationError::CommandAndScript(step.id.clone()));
+        }
+        _ => {}
+    }
+
+    // Check conda spec
+    if let Some(Err(reason)) = step.conda.as_ref().map(|conda| conda.validate()) {
+        errors.push(ValidationError::InvalidCondaSpec {
+            step: step.id.clone(),
+            reason,
+        });
+    }
+
+    if step.container.is_some() && step.conda.is_some() {
+        errors.push(ValidationError::ContainerAndConda(step.id.clone()));
+    }
+
+    if let Some(memory) = step.memory.as_ref().filter(|memory| !is_valid_memory(memory)) {
+        errors.push(ValidationError::InvalidMemory {
+            step: step.id.clone(),
+            memory: memory.clone(),
+        });
     }
 
     // Warn about placeholder mismatches
@@ -254,7 +300,7 @@ pub fn quick_validate(workflow: &Workflow) -> Vec<String> {
             errors.push(format!("Step '{}': missing tool", step.id));
         }
 
-        if step.command.trim().is_empty() {
+        if step.command.trim().is_empty() && step.script.is_none() {
             errors.push(format!("Step '{}': missing command", step.id));
         }
 
@@ -285,6 +331,12 @@ pub fn quick_validate(workflow: &Workflow) -> Vec<String> {
     errors
 }
 
+/// Checks a memory amount such as `500M` or `16G`.
+fn is_valid_memory(memory: &str) -> bool {
+    let digits = memory.trim_end_matches(['K', 'M', 'G', 'T', 'k', 'm', 'g', 't']);
+    !digits.is_empty() && memory.len() - digits.len() <= 1 && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -351,6 +403,61 @@ mod tests {
         assert!(errors.iter().any(|e| matches!(e, ValidationError::EmptyCommand(_))));
     }
 
+    #[test]
+    fn test_validate_step_inline_script() {
+        use crate::workflow::{InlineScript, ScriptLanguage};
+
+        let script = InlineScript::new(ScriptLanguage::Python, "print(step['input'])");
+        let step = Step::new("py", "python", "").with_script(script.clone());
+        assert!(validate_step(&step).is_empty());
+
+        let both = Step::new("py", "python", "echo hi").with_script(script);
+        assert!(validate_step(&both)
+            .iter()
+            .any(|e| matches!(e, ValidationError::CommandAndScript(_))));
+
+        let empty = Step::new("py", "python", "")
+            .with_script(InlineScript::new(ScriptLanguage::R, "  "));
+        assert!(validate_step(&empty)
+            .iter()
+            .any(|e| matches!(e, ValidationError::EmptyCommand(_))));
+    }
+
+    #[test]
+    fn test_validate_step_conda_spec() {
+        use crate::environment::CondaSpec;
+
+        let step = Step::new("sort", "samtools", "samtools sort {input}")
+            .with_conda(CondaSpec::with_packages(["samtools=1.17"]));
+        assert!(validate_step(&step).is_empty());
+
+        let empty = Step::new("sort", "samtools", "samtools sort {input}")
+            .with_conda(CondaSpec::default());
+        assert!(validate_step(&empty)
+            .iter()
+            .any(|e| matches!(e, ValidationError::InvalidCondaSpec { .. })));
+
+        let both = step.with_container("quay.io/biocontainers/samtools:1.17--h00cdaf9_0");
+        assert!(validate_step(&both)
+            .iter()
+            .any(|e| matches!(e, ValidationError::ContainerAndConda(_))));
+    }
+
+    #[test]
+    fn test_validate_step_memory() {
+        for memory in ["16G", "500m", "2048"] {
+            assert!(is_valid_memory(memory), "{}", memory);
+        }
+        for memory in ["", "G", "16GB", "1.5G", "-1G"] {
+            assert!(!is_valid_memory(memory), "{}", memory);
+        }
+
+        let step = Step::new("align", "bwa", "bwa mem {input}").with_memory("lots");
+        assert!(validate_step(&step)
+            .iter()
+            .any(|e| matches!(e, ValidationError::InvalidMemory { .. })));
+    }
+
     #[test]
     fn test_validate_step_empty_id() {
         let step = Step::new("", "bash", "echo test");
@@ -118,11 +118,10 @@ pub fn has_wildcards(text: &str) -> bool {
 /// ```
 pub fn extract_wildcard_names(pattern: &str) -> Vec<String> {
     let mut names = Vec::new();
-    let mut chars = pattern.chars().peekable();
     let mut in_wildcard = false;
     let mut current_name = String::new();
 
-    while let Some(ch) = chars.next() {
+    for ch in pattern.chars() {
         match ch {
             '{' => {
                 in_wildcard = true;
@@ -224,6 +223,7 @@ pub fn expand_workflow_wildcards(
 
             // Update step ID
             new_step.id = format!("{}_{}", step.id, value);
+            new_step.rule = Some(step.rule_name().to_string());
 
             // Substitute wildcards in inputs
             new_step.input = step
@@ -281,6 +281,7 @@ fn substitute_wildcard(text: &str, wildcard_name: &str, value: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::workflow::Step;
 
     #[test]
     fn test_extract_wildcard_values() {
@@ -326,4 +327,27 @@ mod tests {
         let result = substitute_wildcard("reads/{sample}.fastq", "sample", "sample1");
         assert_eq!(result, "reads/sample1.fastq");
     }
+
+    #[test]
+    fn test_expanded_steps_keep_rule() {
+        let mut workflow = Workflow::new();
+        workflow
+            .add_step(
+                Step::new("trim", "bash", "cp {input} {output}")
+                    .with_input("reads/{sample}.fastq")
+                    .with_output("trimmed/{sample}.fastq"),
+            )
+            .unwrap();
+
+        let mut files = HashMap::new();
+        files.insert(
+            "sample".to_string(),
+            vec!["a.fastq".to_string(), "b.fastq".to_string()],
+        );
+        expand_workflow_wildcards(&mut workflow, &files).unwrap();
+
+        assert_eq!(workflow.steps.len(), 2);
+        assert_eq!(workflow.steps[0].id, "trim_a");
+        assert!(workflow.steps.iter().all(|s| s.rule_name() == "trim"));
+    }
 }
@@ -0,0 +1,94 @@
+//! Signal handling of the `rustrunner` binary.
+
+#![cfg(target_os = "linux")]
+
+use std::fs;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const WORKFLOW: &str = r#"
+steps:
+  - id: nap
+    tool: bash
+    command: echo $$ > nap.pid; exec sleep 47
+"#;
+
+/// Polls `check` until it returns a value or `limit` passes.
+fn wait_for<T>(limit: Duration, mut check: impl FnMut() -> Option<T>) -> Option<T> {
+    let start = Instant::now();
+    while start.elapsed() < limit {
+        if let Some(value) = check() {
+            return Some(value);
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    None
+}
+
+/// Returns `true` if a process exists and hasn't exited.
+fn is_running(pid: u32) -> bool {
+    fs::read_to_string(format!("/proc/{}/stat", pid))
+        .map(|stat| {
+            // The state follows the parenthesised command name
+            let state = stat.rsplit(')').next().unwrap_or_default().trim_start();
+            !state.starts_with('Z')
+        })
+        .unwrap_or(false)
+}
+
+fn signal(child: &Child, name: &str) {
+    let status = Command::new("kill")
+        .arg(format!("-{}", name))
+        .arg(child.id().to_string())
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+fn run_until_step_started(dir: &Path) -> (Child, u32) {
+    fs::write(dir.join("workflow.yaml"), WORKFLOW).unwrap();
+
+    let child = Command::new(env!("CARGO_BIN_EXE_rustrunner"))
+        .arg("workflow.yaml")
+        .current_dir(dir)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let pid = wait_for(Duration::from_secs(20), || {
+        fs::read_to_string(dir.join("nap.pid"))
+            .ok()
+            .and_then(|pid| pid.trim().parse().ok())
+    })
+    .expect("step never started");
+
+    (child, pid)
+}
+
+fn assert_stops_step(signal_name: &str) {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let (mut child, step_pid) = run_until_step_started(temp_dir.path());
+    assert!(is_running(step_pid));
+
+    signal(&child, signal_name);
+
+    let status = wait_for(Duration::from_secs(10), || child.try_wait().unwrap())
+        .expect("rustrunner did not exit");
+    assert!(!status.success());
+
+    let gone = wait_for(Duration::from_secs(5), || (!is_running(step_pid)).then_some(()));
+    assert!(gone.is_some(), "step process {} outlived rustrunner", step_pid);
+}
+
+#[test]
+fn test_sigint_kills_running_steps() {
+    assert_stops_step("INT");
+}
+
+#[test]
+fn test_sigterm_kills_running_steps() {
+    assert_stops_step("TERM");
+}